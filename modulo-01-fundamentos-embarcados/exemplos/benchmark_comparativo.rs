@@ -1,20 +1,63 @@
 // benchmark_comparativo.rs
 // Exemplo de benchmark comparativo entre Rust e C em sistemas embarcados
 
-#![no_std]
-#![no_main]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 
+use core::fmt::Write;
 use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(not(test))]
 use panic_halt as _;
 
 // Estruturas para medição de performance
+#[derive(Clone, Copy)]
 pub struct PerformanceMetrics {
-    pub execution_time: u32,
+    pub execution_time: u32, // Em ticks do timer (EXECUTION_TIME_UNIT)
     pub memory_usage: usize,
     pub stack_usage: usize,
     pub binary_size: usize,
 }
 
+// Unidades de tempo para exibição dos resultados
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    Ticks,
+    Microseconds,
+    Milliseconds,
+}
+
+impl TimeUnit {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            TimeUnit::Ticks => "ticks",
+            TimeUnit::Microseconds => "µs",
+            TimeUnit::Milliseconds => "ms",
+        }
+    }
+}
+
+// Frequência do timer usado em get_system_time (ticks por segundo).
+// get_system_time ainda é um contador que avança 1 por chamada, não um
+// timer: os tempos são simulados e 16 MHz é só o clock nominal do ATmega328P
+// que um Timer1 sem prescaler teria. Os relatórios indicam isso
+pub const TIMER_FREQUENCY_HZ: u32 = 16_000_000;
+pub const TIMER_IS_SIMULATED: bool = true;
+pub const EXECUTION_TIME_UNIT: TimeUnit = TimeUnit::Ticks;
+
+pub fn ticks_to(ticks: u32, unit: TimeUnit, frequency_hz: u32) -> f32 {
+    match unit {
+        TimeUnit::Ticks => ticks as f32,
+        TimeUnit::Microseconds => ticks as f32 * 1_000_000.0 / frequency_hz as f32,
+        TimeUnit::Milliseconds => ticks as f32 * 1_000.0 / frequency_hz as f32,
+    }
+}
+
+impl PerformanceMetrics {
+    pub fn execution_time_in(&self, unit: TimeUnit) -> f32 {
+        ticks_to(self.execution_time, unit, TIMER_FREQUENCY_HZ)
+    }
+}
+
 pub struct BenchmarkSuite {
     pub results: [PerformanceMetrics; 4],
 }
@@ -100,10 +143,10 @@ impl BenchmarkSuite {
     
     pub fn generate_report(&self) -> BenchmarkReport {
         BenchmarkReport {
-            sorting: self.results[0].clone(),
-            math: self.results[1].clone(),
-            strings: self.results[2].clone(),
-            memory: self.results[3].clone(),
+            sorting: self.results[0],
+            math: self.results[1],
+            strings: self.results[2],
+            memory: self.results[3],
         }
     }
 }
@@ -116,6 +159,103 @@ pub struct BenchmarkReport {
     pub memory: PerformanceMetrics,
 }
 
+impl BenchmarkReport {
+    // Unidade em que os tempos do relatório foram medidos
+    pub fn time_unit(&self) -> TimeUnit {
+        EXECUTION_TIME_UNIT
+    }
+    
+    // Tabela Markdown com as métricas, sem alocação (funciona em no_std)
+    pub fn to_markdown(&self) -> heapless::String<512> {
+        let mut table = heapless::String::new();
+        let rows = [
+            ("sorting", &self.sorting),
+            ("math", &self.math),
+            ("strings", &self.strings),
+            ("memory", &self.memory),
+        ];
+        
+        let _ = writeln!(
+            table,
+            "| Benchmark | Tempo ({}{}) | Memória (bytes) | Stack (bytes) | Binário (bytes) |",
+            self.time_unit().suffix(),
+            if TIMER_IS_SIMULATED { ", simulado" } else { "" }
+        );
+        let _ = table.push_str("|---|---:|---:|---:|---:|\n");
+        
+        for (name, metrics) in rows.iter() {
+            let _ = writeln!(
+                table,
+                "| {} | {} | {} | {} | {} |",
+                name,
+                metrics.execution_time,
+                metrics.memory_usage,
+                metrics.stack_usage,
+                metrics.binary_size
+            );
+        }
+        
+        table
+    }
+    
+    // Tempos de execução (sorting, math, strings, memory) na unidade pedida
+    pub fn execution_times_in(&self, unit: TimeUnit) -> [f32; 4] {
+        [
+            self.sorting.execution_time_in(unit),
+            self.math.execution_time_in(unit),
+            self.strings.execution_time_in(unit),
+            self.memory.execution_time_in(unit),
+        ]
+    }
+    
+    // Speedup de cada benchmark: tempo do baseline / tempo atual
+    pub fn relative_to(&self, baseline: &BenchmarkReport) -> SpeedupReport {
+        SpeedupReport {
+            sorting: speedup(self.sorting.execution_time, baseline.sorting.execution_time),
+            math: speedup(self.math.execution_time, baseline.math.execution_time),
+            strings: speedup(self.strings.execution_time, baseline.strings.execution_time),
+            memory: speedup(self.memory.execution_time, baseline.memory.execution_time),
+        }
+    }
+}
+
+// Tempo zero (abaixo da resolução do timer) conta como 1 tick
+fn speedup(current: u32, baseline: u32) -> f32 {
+    baseline.max(1) as f32 / current.max(1) as f32
+}
+
+// Fatores > 1.0 = mais rápido que o baseline; < 1.0 = mais lento
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedupReport {
+    pub sorting: f32,
+    pub math: f32,
+    pub strings: f32,
+    pub memory: f32,
+}
+
+impl SpeedupReport {
+    // Uma linha por benchmark, ex.: "sorting 1.30x mais rápido que o baseline"
+    pub fn to_text(&self) -> heapless::String<256> {
+        let mut text = heapless::String::new();
+        let rows = [
+            ("sorting", self.sorting),
+            ("math", self.math),
+            ("strings", self.strings),
+            ("memory", self.memory),
+        ];
+        
+        for (name, factor) in rows.iter() {
+            let _ = if *factor >= 1.0 {
+                writeln!(text, "{} {:.2}x mais rápido que o baseline", name, factor)
+            } else {
+                writeln!(text, "{} {:.2}x mais lento que o baseline", name, 1.0 / factor)
+            };
+        }
+        
+        text
+    }
+}
+
 // Algoritmos de benchmark em Rust
 pub fn bubble_sort_rust(arr: &mut [i32]) {
     let len = arr.len();
@@ -160,8 +300,7 @@ pub fn memory_operations_rust() -> [u32; 16] {
 
 // Funções auxiliares para medição
 fn get_system_time() -> u32 {
-    // Implementar com timer do sistema
-    // Para exemplo, retornar valor simulado
+    // Simulado (TIMER_IS_SIMULATED): implementar com o Timer1 do sistema
     unsafe {
         static COUNTER: AtomicU32 = AtomicU32::new(0);
         COUNTER.fetch_add(1, Ordering::Relaxed)
@@ -258,9 +397,40 @@ impl CBenchmark {
     }
 }
 
+// Resultado resumido de uma implementação qualquer (Rust, C, C++, MicroPython...)
+#[derive(Debug, Clone, Copy)]
+pub struct ImplementationMetrics {
+    pub name: &'static str,
+    pub execution_time: f32, // Tempo médio por benchmark (ticks)
+    pub memory_usage: usize,
+    pub safety_score: f32,   // 0 a 100
+}
+
+// Pesos do score composto (somam 1.0)
+const PERFORMANCE_WEIGHT: f32 = 0.4;
+const MEMORY_WEIGHT: f32 = 0.3;
+const SAFETY_WEIGHT: f32 = 0.3;
+
+// Rust sem unsafe é a referência de segurança (100)
+const RUST_SAFETY_SCORE: f32 = 100.0;
+
+impl ImplementationMetrics {
+    // Mesmas escalas da StatisticalAnalysis: menor tempo/memória = maior score
+    pub fn composite_score(&self) -> f32 {
+        let performance = 100.0 / (self.execution_time + 1.0);
+        let memory = 100.0 / (self.memory_usage as f32 / 1000.0 + 1.0);
+        
+        performance * PERFORMANCE_WEIGHT + memory * MEMORY_WEIGHT + self.safety_score * SAFETY_WEIGHT
+    }
+}
+
+// Rust, C e até quatro implementações adicionais
+pub const MAX_IMPLEMENTATIONS: usize = 6;
+
 pub struct ComparativeAnalysis {
     pub rust_metrics: BenchmarkReport,
     pub c_metrics: CBenchmark,
+    pub other_metrics: heapless::Vec<ImplementationMetrics, 4>,
 }
 
 impl ComparativeAnalysis {
@@ -293,7 +463,36 @@ impl ComparativeAnalysis {
                 },
             },
             c_metrics: CBenchmark::new(),
+            other_metrics: heapless::Vec::new(),
+        }
+    }
+    
+    pub fn add_implementation(&mut self, metrics: ImplementationMetrics) -> Result<(), ImplementationMetrics> {
+        self.other_metrics.push(metrics)
+    }
+    
+    // (implementação, score composto), do melhor ao pior
+    pub fn ranked_scores(&self) -> heapless::Vec<(&'static str, f32), MAX_IMPLEMENTATIONS> {
+        let rust = ImplementationMetrics {
+            name: "Rust",
+            execution_time: self.calculate_average_execution_time(&self.rust_metrics),
+            memory_usage: self.rust_total_memory(),
+            safety_score: RUST_SAFETY_SCORE,
+        };
+        let c = ImplementationMetrics {
+            name: "C",
+            execution_time: self.c_metrics.execution_time as f32,
+            memory_usage: self.c_metrics.memory_usage,
+            safety_score: self.c_metrics.safety_score,
+        };
+        
+        let mut scores: heapless::Vec<(&'static str, f32), MAX_IMPLEMENTATIONS> = heapless::Vec::new();
+        for metrics in [rust, c].iter().chain(self.other_metrics.iter()) {
+            let _ = scores.push((metrics.name, metrics.composite_score()));
         }
+        
+        scores.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(core::cmp::Ordering::Equal));
+        scores
     }
     
     pub fn generate_comparison_report(&self) -> ComparisonReport {
@@ -328,13 +527,15 @@ impl ComparativeAnalysis {
         total as f32 / 4.0
     }
     
+    fn rust_total_memory(&self) -> usize {
+        self.rust_metrics.sorting.memory_usage +
+            self.rust_metrics.math.memory_usage +
+            self.rust_metrics.strings.memory_usage +
+            self.rust_metrics.memory.memory_usage
+    }
+    
     fn calculate_memory_ratio(&self) -> f32 {
-        let rust_total = self.rust_metrics.sorting.memory_usage +
-                        self.rust_metrics.math.memory_usage +
-                        self.rust_metrics.strings.memory_usage +
-                        self.rust_metrics.memory.memory_usage;
-        
-        self.c_metrics.memory_usage as f32 / rust_total as f32
+        self.c_metrics.memory_usage as f32 / self.rust_total_memory() as f32
     }
     
     fn generate_recommendation(&self, perf_ratio: f32, mem_ratio: f32) -> String {
@@ -369,3 +570,100 @@ pub fn run_benchmark_comparison() -> ComparisonReport {
     let comparative_analysis = ComparativeAnalysis::new();
     comparative_analysis.generate_comparison_report()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn ticks_convert_to_us_and_ms() {
+        // 16 000 ticks a 16 MHz = 1 ms
+        assert_eq!(ticks_to(16_000, TimeUnit::Ticks, TIMER_FREQUENCY_HZ), 16_000.0);
+        assert_eq!(ticks_to(16_000, TimeUnit::Microseconds, TIMER_FREQUENCY_HZ), 1_000.0);
+        assert_eq!(ticks_to(16_000, TimeUnit::Milliseconds, TIMER_FREQUENCY_HZ), 1.0);
+        assert_eq!(ticks_to(8, TimeUnit::Microseconds, 8_000_000), 1.0);
+        
+        let metrics = PerformanceMetrics {
+            execution_time: 4_000,
+            memory_usage: 0,
+            stack_usage: 0,
+            binary_size: 0,
+        };
+        assert_eq!(metrics.execution_time_in(TimeUnit::Microseconds), 250.0);
+        assert_eq!(metrics.execution_time_in(TimeUnit::Milliseconds), 0.25);
+        assert_eq!(TimeUnit::Microseconds.suffix(), "µs");
+    }
+    
+    fn metrics(execution_time: u32, memory_usage: usize, stack_usage: usize, binary_size: usize) -> PerformanceMetrics {
+        PerformanceMetrics { execution_time, memory_usage, stack_usage, binary_size }
+    }
+    
+    fn report(times: [u32; 4]) -> BenchmarkReport {
+        BenchmarkReport {
+            sorting: metrics(times[0], 64, 32, 1024),
+            math: metrics(times[1], 32, 16, 512),
+            strings: metrics(times[2], 128, 48, 768),
+            memory: metrics(times[3], 64, 24, 256),
+        }
+    }
+    
+    #[test]
+    fn markdown_table_has_header_separator_and_rows() {
+        let table = report([120, 80, 60, 40]).to_markdown();
+        let lines: Vec<&str> = table.lines().collect();
+        
+        assert_eq!(lines.len(), 6);
+        assert_eq!(
+            lines[0],
+            "| Benchmark | Tempo (ticks, simulado) | Memória (bytes) | Stack (bytes) | Binário (bytes) |"
+        );
+        assert_eq!(lines[1], "|---|---:|---:|---:|---:|");
+        assert_eq!(lines[2], "| sorting | 120 | 64 | 32 | 1024 |");
+        assert_eq!(lines[3], "| math | 80 | 32 | 16 | 512 |");
+        assert_eq!(lines[4], "| strings | 60 | 128 | 48 | 768 |");
+        assert_eq!(lines[5], "| memory | 40 | 64 | 24 | 256 |");
+        // Mesmo número de colunas em todas as linhas
+        assert!(lines.iter().all(|line| line.matches('|').count() == 6));
+    }
+    
+    
+    #[test]
+    fn implementations_are_ranked_best_to_worst() {
+        let mut analysis = ComparativeAnalysis::new();
+        let zig = ImplementationMetrics {
+            name: "Zig",
+            execution_time: 80.0,
+            memory_usage: 256,
+            safety_score: 80.0,
+        };
+        assert!(analysis.add_implementation(zig).is_ok());
+        
+        let ranking = analysis.ranked_scores();
+        let names: Vec<&str> = ranking.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["Rust", "Zig", "C"]);
+        assert!(ranking.windows(2).all(|pair| pair[0].1 > pair[1].1));
+        
+        // Rust: 75 ticks em média, 288 bytes e segurança 100
+        assert!((ranking[0].1 - 53.82).abs() < 0.01, "{}", ranking[0].1);
+    }
+    
+    #[test]
+    fn speedup_factors_per_benchmark() {
+        let baseline = report([200, 80, 60, 0]);
+        let current = report([100, 160, 60, 0]);
+        
+        let speedup = current.relative_to(&baseline);
+        assert_eq!(speedup, SpeedupReport { sorting: 2.0, math: 0.5, strings: 1.0, memory: 1.0 });
+        
+        // Inverter os papéis inverte os fatores
+        let inverse = baseline.relative_to(&current);
+        assert_eq!(inverse.sorting, 0.5);
+        assert_eq!(inverse.math, 2.0);
+        
+        let text = speedup.to_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "sorting 2.00x mais rápido que o baseline");
+        assert_eq!(lines[1], "math 2.00x mais lento que o baseline");
+        assert_eq!(lines[2], "strings 1.00x mais rápido que o baseline");
+    }
+}
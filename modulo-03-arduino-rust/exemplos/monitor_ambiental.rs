@@ -2,10 +2,13 @@
 // Sistema de monitoramento ambiental com Arduino e Rust
 // Projeto acadêmico para análise de qualidade do ar
 
-#![no_std]
-#![no_main]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 
 use arduino_hal::prelude::*;
+use arduino_hal::simple_pwm::{IntoPwmPin, Prescaler, Timer1Pwm, Timer2Pwm};
+use core::fmt::Write;
+#[cfg(not(test))]
 use panic_halt as _;
 
 // Estruturas de dados para monitoramento
@@ -18,514 +21,9122 @@ pub struct EnvironmentalData {
     pub timestamp: u32,
 }
 
+impl EnvironmentalData {
+    // Amostra vazia para inicializar buffers; um `const` pode ser repetido
+    // em `[EnvironmentalData::ZEROED; N]` mesmo sem o tipo ser Copy
+    pub const ZEROED: Self = Self {
+        temperature: 0.0,
+        humidity: 0.0,
+        air_quality: 0.0,
+        pressure: 0.0,
+        timestamp: 0,
+    };
+    
+    // Representação binária little-endian (4 x f32 + u32)
+    pub fn to_bytes(&self) -> [u8; 20] {
+        let mut bytes = [0u8; 20];
+        bytes[0..4].copy_from_slice(&self.temperature.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.humidity.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.air_quality.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.pressure.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes
+    }
+    
+    pub fn value(&self, sensor: SensorType) -> f32 {
+        match sensor {
+            SensorType::Temperature => self.temperature,
+            SensorType::Humidity => self.humidity,
+            SensorType::AirQuality => self.air_quality,
+            SensorType::Pressure => self.pressure,
+        }
+    }
+    
+    // Pacote binário de tamanho fixo: 0xAA, to_bytes() (20 bytes) e um
+    // CRC-8 sobre os 21 bytes anteriores
+    pub fn to_binary(&self) -> [u8; BINARY_PACKET_SIZE] {
+        let mut packet = [0u8; BINARY_PACKET_SIZE];
+        packet[0] = BINARY_START_BYTE;
+        packet[1..21].copy_from_slice(&self.to_bytes());
+        packet[21] = crc8(&packet[..21]);
+        packet
+    }
+    
+    pub fn from_binary(buf: &[u8; BINARY_PACKET_SIZE]) -> Result<Self, SensorError> {
+        if buf[0] != BINARY_START_BYTE || crc8(&buf[..21]) != buf[21] {
+            return Err(SensorError::CommunicationError);
+        }
+        
+        let mut bytes = [0u8; 20];
+        bytes.copy_from_slice(&buf[1..21]);
+        Ok(Self::from_bytes(&bytes))
+    }
+    
+    pub fn from_bytes(bytes: &[u8; 20]) -> Self {
+        let f32_at = |i: usize| f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Self {
+            temperature: f32_at(0),
+            humidity: f32_at(4),
+            air_quality: f32_at(8),
+            pressure: f32_at(12),
+            timestamp: u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]),
+        }
+    }
+    
+    // Pressão reduzida ao nível do mar (QNH) em kPa, pela fórmula barométrica
+    // padrão usando a temperatura medida no local
+    pub fn sea_level_pressure(&self, altitude_m: f32) -> f32 {
+        let lapse = 0.0065 * altitude_m; // Gradiente térmico padrão: 6.5 K/km
+        let ratio = 1.0 - lapse / (self.temperature + lapse + 273.15);
+        self.pressure * ratio.powf(-5.257)
+    }
+    
+    // Ponto de orvalho (°C) pela fórmula de Magnus
+    pub fn dew_point(&self) -> f32 {
+        let (a, b) = (17.62, 243.12);
+        let gamma = (self.humidity.max(0.1) / 100.0).ln() + a * self.temperature / (b + self.temperature);
+        b * gamma / (a - gamma)
+    }
+    
+    // Índice de calor (°C) da NOAA: fórmula simples abaixo de 80°F,
+    // regressão de Rothfusz acima
+    pub fn heat_index(&self) -> f32 {
+        let t = self.temperature * 9.0 / 5.0 + 32.0;
+        let rh = self.humidity;
+        
+        let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+        let fahrenheit = if (simple + t) / 2.0 < 80.0 {
+            simple
+        } else {
+            -42.379 + 2.04901523 * t + 10.14333127 * rh
+                - 0.22475541 * t * rh
+                - 0.00683783 * t * t
+                - 0.05481717 * rh * rh
+                + 0.00122874 * t * t * rh
+                + 0.00085282 * t * rh * rh
+                - 0.00000199 * t * t * rh * rh
+        };
+        
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+    
+    // Umidade absoluta (g/m³)
+    pub fn absolute_humidity(&self) -> f32 {
+        let t = self.temperature;
+        let saturation_hpa = 6.112 * (17.67 * t / (t + 243.5)).exp();
+        saturation_hpa * self.humidity * 2.1674 / (273.15 + t)
+    }
+    
+    // Altitude (m) pela fórmula barométrica, dada a pressão ao nível do mar (kPa)
+    pub fn altitude(&self, sea_level_kpa: f32) -> f32 {
+        44_330.0 * (1.0 - (self.pressure / sea_level_kpa).powf(1.0 / 5.255))
+    }
+    
+    // Todas as grandezas derivadas numa chamada; o AQI vem da medição de
+    // partículas em vigor (não fica guardada em cada amostra)
+    pub fn derived(&self, config: &SystemConfig, particulates: Option<pms5003::ParticulateMatter>) -> DerivedMetrics {
+        DerivedMetrics {
+            dew_point: self.dew_point(),
+            heat_index: self.heat_index(),
+            absolute_humidity: self.absolute_humidity(),
+            aqi: particulates.map(|pm| pm25_aqi(pm.pm2_5)),
+            altitude_m: self.altitude(config.sea_level_pressure_kpa),
+        }
+    }
+}
+
+// Grandezas calculadas a partir de uma leitura (as brutas ficam em EnvironmentalData)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DerivedMetrics {
+    pub dew_point: f32,         // °C
+    pub heat_index: f32,        // °C
+    pub absolute_humidity: f32, // g/m³
+    pub aqi: Option<u16>,       // EPA, pelo PM2.5
+    pub altitude_m: f32,
+}
+
+// Faixas de PM2.5 (µg/m³) e do índice correspondente (EPA)
+const PM25_AQI_BREAKPOINTS: [(f32, f32, u16, u16); 6] = [
+    (0.0, 12.0, 0, 50),
+    (12.1, 35.4, 51, 100),
+    (35.5, 55.4, 101, 150),
+    (55.5, 150.4, 151, 200),
+    (150.5, 250.4, 201, 300),
+    (250.5, 500.4, 301, 500),
+];
+
+// Interpolação linear dentro da faixa; acima da tabela satura em 500
+pub fn pm25_aqi(pm2_5: u16) -> u16 {
+    let concentration = pm2_5 as f32;
+    for (c_low, c_high, i_low, i_high) in PM25_AQI_BREAKPOINTS {
+        if concentration <= c_high {
+            let fraction = (concentration - c_low).max(0.0) / (c_high - c_low);
+            return i_low + (fraction * (i_high - i_low) as f32).round() as u16;
+        }
+    }
+    500
+}
+
 #[derive(Debug)]
 pub enum SensorError {
     ReadError,
     CalibrationError,
     CommunicationError,
+    ConfigError,
 }
 
 // Configurações do sistema
+#[derive(Debug, Clone)]
 pub struct SystemConfig {
     pub reading_interval: u32,    // Intervalo entre leituras (ms)
-    pub alert_threshold: f32,     // Limite para alertas
-    pub calibration_factor: f32,  // Fator de calibração
+    pub transmit_interval_ms: u32, // Intervalo mínimo entre transmissões (0 = a cada leitura)
+    pub transmit_backoff_max_ms: u32, // Teto do intervalo em estabilidade (0 = sem backoff)
+    pub thresholds: ThresholdConfig, // Limites de alerta por métrica
+    pub alert_hysteresis: [f32; 4], // Banda para rearmar cada canal já disparado, por AlertKind (0 = alerta a cada ciclo)
+    pub calibration_factor: [f32; 4], // Fator multiplicativo final de cada sensor (por SensorType)
+    pub alert_max_per_window: u8, // Máximo de alertas por tipo na janela
+    pub alert_window_ms: u32,     // Janela de throttling de alertas (ms)
+    pub alert_storm_threshold: u8, // Alertas por ciclo que caracterizam tempestade (0 = desligado)
+    pub alert_storm_cycles: u8,   // Ciclos seguidos para entrar (e sair) do modo resumo
+    pub hash_block_size: u16,     // Amostras por checkpoint de integridade
+    pub baseline_margin: f32,     // Desvio relativo tolerado sobre a linha de base
+    pub pressure_temp_coeff: f32, // Deriva térmica do sensor de pressão (kPa/°C)
+    pub humidity_temp_coeff: f32, // Deriva térmica residual do sensor de umidade (%RH/°C)
+    pub heartbeat_interval_ms: u32, // Intervalo do sinal de vida (ms)
+    pub cycle_budget_ms: u32,     // Tempo máximo de um ciclo de monitoramento (ms)
+    pub samples_per_cycle: u8,    // Leituras rápidas mediadas em cada amostra
+    pub mcu_temp_max: f32,        // Temperatura máxima do próprio MCU (°C)
+    pub gas_sensor_lifetime_hours: u32, // Vida útil típica do sensor MQ (h)
+    pub deadbands: [AsymmetricDeadband; 4], // Variação mínima para transmitir (por SensorType)
+    pub warmup_ms: [u32; 4],      // Aquecimento de cada sensor após o boot (por SensorType)
+    pub prealert_horizon_ms: u32, // Pré-alerta se a tendência cruzar o limite nesse prazo (0 = desligado)
+    pub outlier_tolerance: [f32; 4], // Desvio relativo máximo da média recente (por SensorType, 0 = desligado)
+    pub dead_after_failures: u8,  // Falhas seguidas até o sensor ser marcado como morto
+    pub recovery_readings: u8,    // Leituras boas seguidas para reabilitar um sensor morto
+    pub diurnal_sigma: f32,       // Desvios padrão tolerados sobre o perfil da hora (0 = desligado)
+    pub sea_level_pressure_kpa: f32, // Referência para a altitude estimada
+    pub vref_compensation: bool,  // Medir VCC pelo bandgap a cada leitura e usá-lo na escala do ADC
+    pub load_resistance_kohm: f32, // Resistor de carga (RL) do módulo MQ-135 (kΩ)
+    pub mq_r0_kohm: f32,          // Resistência do MQ-135 em ar limpo de referência (kΩ)
 }
 
 impl Default for SystemConfig {
     fn default() -> Self {
         Self {
             reading_interval: 5000,  // 5 segundos
-            alert_threshold: 100.0,  // 100 ppm
-            calibration_factor: 1.0,
+            transmit_interval_ms: 0,
+            transmit_backoff_max_ms: 0,
+            thresholds: ThresholdConfig::default(),
+            alert_hysteresis: [0.0; 4],
+            calibration_factor: [1.0; 4],
+            alert_max_per_window: 3,
+            alert_window_ms: 60_000, // 1 minuto
+            alert_storm_threshold: 0,
+            alert_storm_cycles: 3,
+            hash_block_size: 10,
+            baseline_margin: 0.25, // 25%
+            pressure_temp_coeff: 0.0, // Sem compensação
+            humidity_temp_coeff: 0.0, // Sem compensação
+            heartbeat_interval_ms: 30_000, // 30 segundos
+            cycle_budget_ms: 1000,
+            samples_per_cycle: 1,
+            mcu_temp_max: 70.0,
+            gas_sensor_lifetime_hours: 17_520, // ~2 anos de operação contínua
+            deadbands: [AsymmetricDeadband::NONE; 4], // Transmite toda leitura
+            warmup_ms: [0, 2_000, 180_000, 0], // LM35 imediato, DHT22 2s, MQ-135 3min, BMP280 imediato
+            prealert_horizon_ms: 0,
+            outlier_tolerance: [0.0; 4],
+            dead_after_failures: 5,
+            recovery_readings: 3,
+            diurnal_sigma: 0.0,
+            sea_level_pressure_kpa: 101.325,
+            vref_compensation: false,
+            load_resistance_kohm: 10.0,
+            mq_r0_kohm: 10.0, // Igual a RL até calibrate_r0: Rs/R0 = (Vc - V)/V
         }
     }
 }
 
-// Gerenciador de sensores
-pub struct SensorManager {
-    temperature_sensor: arduino_hal::adc::AdcChannel,
-    humidity_sensor: arduino_hal::adc::AdcChannel,
-    air_quality_sensor: arduino_hal::adc::AdcChannel,
-    pressure_sensor: arduino_hal::adc::AdcChannel,
-    config: SystemConfig,
+// Limites de alerta; o padrão reproduz os valores fixos das versões anteriores
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdConfig {
+    pub temp_min: f32,        // °C
+    pub temp_max: f32,
+    pub humidity_min: f32,    // %RH
+    pub humidity_max: f32,
+    pub air_quality_max: f32, // ppm
+    pub pressure_min: f32,    // kPa
+    pub pressure_max: f32,
 }
 
-impl SensorManager {
-    pub fn new() -> Result<Self, SensorError> {
-        let dp = arduino_hal::Peripherals::take().map_err(|_| SensorError::ReadError)?;
-        let pins = arduino_hal::pins!(dp);
-        
-        let mut adc = arduino_hal::Adc::new(dp.ADC, arduino_hal::DefaultClock);
-        
-        let temperature_sensor = pins.a0.into_analog_input(&mut adc);
-        let humidity_sensor = pins.a1.into_analog_input(&mut adc);
-        let air_quality_sensor = pins.a2.into_analog_input(&mut adc);
-        let pressure_sensor = pins.a3.into_analog_input(&mut adc);
-        
-        Ok(Self {
-            temperature_sensor,
-            humidity_sensor,
-            air_quality_sensor,
-            pressure_sensor,
-            config: SystemConfig::default(),
-        })
-    }
-    
-    pub fn read_all_sensors(&mut self) -> Result<EnvironmentalData, SensorError> {
-        let temp_raw = self.temperature_sensor.analog_read(&mut adc);
-        let humidity_raw = self.humidity_sensor.analog_read(&mut adc);
-        let air_quality_raw = self.air_quality_sensor.analog_read(&mut adc);
-        let pressure_raw = self.pressure_sensor.analog_read(&mut adc);
-        
-        Ok(EnvironmentalData {
-            temperature: self.convert_temperature(temp_raw)?,
-            humidity: self.convert_humidity(humidity_raw)?,
-            air_quality: self.convert_air_quality(air_quality_raw)?,
-            pressure: self.convert_pressure(pressure_raw)?,
-            timestamp: arduino_hal::time::millis(),
-        })
-    }
-    
-    fn convert_temperature(&self, raw: u16) -> Result<f32, SensorError> {
-        // Conversão para sensor LM35 (10mV/°C)
-        let voltage = (raw as f32 * 5.0) / 1024.0;
-        let temperature = voltage * 100.0; // LM35: 10mV/°C
-        
-        if temperature < -40.0 || temperature > 125.0 {
-            return Err(SensorError::ReadError);
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self {
+            temp_min: 5.0,
+            temp_max: 35.0,
+            humidity_min: 10.0,
+            humidity_max: 90.0,
+            air_quality_max: 100.0, // 100 ppm
+            // Faixa inteira do sensor: sem alerta de pressão por padrão
+            pressure_min: 30.0,
+            pressure_max: 110.0,
         }
-        
-        Ok(temperature)
     }
-    
-    fn convert_humidity(&self, raw: u16) -> Result<f32, SensorError> {
-        // Conversão para sensor DHT22
-        let humidity = (raw as f32 * 100.0) / 1024.0;
-        
-        if humidity < 0.0 || humidity > 100.0 {
-            return Err(SensorError::ReadError);
+}
+
+impl ThresholdConfig {
+    pub fn get(&self, metric: ThresholdMetric) -> f32 {
+        match metric {
+            ThresholdMetric::TempMin => self.temp_min,
+            ThresholdMetric::TempMax => self.temp_max,
+            ThresholdMetric::HumidityMin => self.humidity_min,
+            ThresholdMetric::HumidityMax => self.humidity_max,
+            ThresholdMetric::AirQualityMax => self.air_quality_max,
+            ThresholdMetric::PressureMin => self.pressure_min,
+            ThresholdMetric::PressureMax => self.pressure_max,
         }
-        
-        Ok(humidity)
     }
     
-    fn convert_air_quality(&self, raw: u16) -> Result<f32, SensorError> {
-        // Conversão para sensor MQ-135 (CO2)
-        let voltage = (raw as f32 * 5.0) / 1024.0;
-        let resistance = (5.0 - voltage) / voltage;
-        let ppm = 116.6020682 * resistance.powf(-2.769034857);
-        
-        if ppm < 0.0 || ppm > 10000.0 {
-            return Err(SensorError::ReadError);
-        }
-        
-        Ok(ppm)
+    pub fn set(&mut self, metric: ThresholdMetric, value: f32) {
+        let field = match metric {
+            ThresholdMetric::TempMin => &mut self.temp_min,
+            ThresholdMetric::TempMax => &mut self.temp_max,
+            ThresholdMetric::HumidityMin => &mut self.humidity_min,
+            ThresholdMetric::HumidityMax => &mut self.humidity_max,
+            ThresholdMetric::AirQualityMax => &mut self.air_quality_max,
+            ThresholdMetric::PressureMin => &mut self.pressure_min,
+            ThresholdMetric::PressureMax => &mut self.pressure_max,
+        };
+        *field = value;
     }
     
-    fn convert_pressure(&self, raw: u16) -> Result<f32, SensorError> {
-        // Conversão para sensor BMP280
-        let voltage = (raw as f32 * 5.0) / 1024.0;
-        let pressure = (voltage - 0.5) * 400.0; // kPa
-        
-        if pressure < 30.0 || pressure > 110.0 {
-            return Err(SensorError::ReadError);
+    // Cada limite dentro da faixa do sensor e mínimos abaixo dos máximos
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for metric in ThresholdMetric::ALL {
+            let (min, max) = metric.valid_range();
+            if !(min..=max).contains(&self.get(metric)) {
+                return Err("limite fora da faixa do sensor");
+            }
+        }
+        if self.temp_min >= self.temp_max
+            || self.humidity_min >= self.humidity_max
+            || self.pressure_min >= self.pressure_max
+        {
+            return Err("limite mínimo deve ficar abaixo do máximo");
         }
         
-        Ok(pressure)
+        Ok(())
     }
-    
-    pub fn calibrate_sensor(&mut self, sensor_type: SensorType) -> Result<(), SensorError> {
-        match sensor_type {
-            SensorType::Temperature => {
-                // Implementar calibração de temperatura
-                self.config.calibration_factor = 1.0;
-            }
-            SensorType::Humidity => {
-                // Implementar calibração de umidade
-                self.config.calibration_factor = 1.0;
-            }
-            SensorType::AirQuality => {
-                // Implementar calibração de qualidade do ar
-                self.config.calibration_factor = 1.0;
-            }
-            SensorType::Pressure => {
-                // Implementar calibração de pressão
-                self.config.calibration_factor = 1.0;
-            }
+}
+
+impl SystemConfig {
+    // Rejeita combinações que deixariam alertas ou leituras sem efeito
+    pub fn validate(&self) -> Result<(), &'static str> {
+        self.thresholds.validate()?;
+        if !(MIN_READING_INTERVAL..=MAX_READING_INTERVAL).contains(&self.reading_interval) {
+            return Err("intervalo fora da faixa");
+        }
+        if self.samples_per_cycle == 0 {
+            return Err("samples_per_cycle deve ser pelo menos 1");
+        }
+        if self.deadbands.iter().any(|band| band.up < 0.0 || band.down < 0.0) {
+            return Err("banda morta negativa");
+        }
+        if self.outlier_tolerance.iter().any(|tolerance| !(*tolerance >= 0.0) || !tolerance.is_finite()) {
+            return Err("tolerância de outlier inválida");
         }
+        if self.alert_hysteresis.iter().any(|band| !(*band >= 0.0) || !band.is_finite()) {
+            return Err("histerese de alerta inválida");
+        }
+        if self.calibration_factor.iter().any(|factor| !(*factor > 0.0) || !factor.is_finite()) {
+            return Err("fator de calibração deve ser positivo");
+        }
+        if !(self.load_resistance_kohm > 0.0) || !(self.mq_r0_kohm > 0.0) {
+            return Err("resistências do MQ-135 devem ser positivas");
+        }
+        
         Ok(())
     }
 }
 
-#[derive(Debug)]
-pub enum SensorType {
-    Temperature,
-    Humidity,
-    AirQuality,
-    Pressure,
-}
+// Referência e resolução do ADC de 10 bits
+pub const ADC_VREF: f32 = 5.0;
+pub const ADC_RESOLUTION: f32 = 1024.0;
 
-// Sistema de alertas
-pub struct AlertSystem {
-    config: SystemConfig,
-    alert_history: [bool; 10],
-    alert_count: usize,
+// Leituras a até ADC_SATURATION_MARGIN contagens do fundo de escala são
+// suspeitas (entrada em clamp, fio aberto com pull-up, ruído no topo)
+pub const ADC_MAX_RAW: u16 = 1023;
+pub const ADC_SATURATION_MARGIN: u16 = 2;
+
+pub fn is_adc_saturated(raw: u16) -> bool {
+    raw >= ADC_MAX_RAW - ADC_SATURATION_MARGIN
 }
 
-impl AlertSystem {
-    pub fn new(config: SystemConfig) -> Self {
-        Self {
-            config,
-            alert_history: [false; 10],
-            alert_count: 0,
-        }
+// Umidade de equilíbrio das soluções salinas de referência a 25°C (%RH)
+pub const SALT_MGCL2_RH: f32 = 33.0;
+pub const SALT_NACL_RH: f32 = 75.0;
+
+// Curva do MQ-135 para CO2 (datasheet): ppm = A * (Rs/R0)^B
+pub const MQ135_CURVE_A: f32 = 116.6020682;
+pub const MQ135_CURVE_B: f32 = -2.769034857;
+
+// VCC aceitável para o ATmega328P a 16 MHz e desvio tolerado da referência nominal
+pub const VCC_MIN: f32 = 2.7;
+pub const VCC_MAX: f32 = 5.5;
+pub const VREF_TOLERANCE: f32 = 0.25;
+
+// Abstrações de hardware: a lógica do sistema depende apenas destes traits,
+// o que permite substituir o arduino_hal por simulações ao rodar no host
+
+// Leituras brutas do ADC de 10 bits
+pub trait SensorSource {
+    // Os quatro canais na ordem de SensorType::index()
+    fn read_raw(&mut self) -> [u16; 4];
+    fn read_channel(&mut self, sensor: SensorType) -> u16;
+    // (sensor de temperatura interno, referência bandgap de 1.1V)
+    fn read_device_raw(&mut self) -> (u16, u16);
+    // Reconfigura o canal/driver de um sensor travado (ex.: I2C);
+    // canais analógicos não guardam estado, então o padrão não faz nada
+    fn reinit(&mut self, _sensor: SensorType) -> Result<(), SensorError> {
+        Ok(())
     }
-    
-    pub fn check_alerts(&mut self, data: &EnvironmentalData) -> Vec<Alert> {
-        let mut alerts = Vec::new();
-        
-        // Verificar qualidade do ar
-        if data.air_quality > self.config.alert_threshold {
-            alerts.push(Alert {
-                level: AlertLevel::Warning,
-                message: "Qualidade do ar crítica",
-                value: data.air_quality,
-                timestamp: data.timestamp,
-            });
-        }
-        
-        // Verificar temperatura
-        if data.temperature > 35.0 || data.temperature < 5.0 {
-            alerts.push(Alert {
-                level: AlertLevel::Critical,
-                message: "Temperatura fora da faixa normal",
-                value: data.temperature,
-                timestamp: data.timestamp,
-            });
-        }
-        
-        // Verificar umidade
-        if data.humidity > 90.0 || data.humidity < 10.0 {
-            alerts.push(Alert {
-                level: AlertLevel::Warning,
-                message: "Umidade fora da faixa normal",
-                value: data.humidity,
-                timestamp: data.timestamp,
-            });
-        }
-        
-        self.update_alert_history(alerts.len() > 0);
-        alerts
+    // Próximo byte da UART do PMS5003, se houver sensor de partículas
+    fn read_particulate_byte(&mut self) -> Option<u8> {
+        None
     }
-    
-    fn update_alert_history(&mut self, has_alert: bool) {
-        self.alert_history[self.alert_count % 10] = has_alert;
-        self.alert_count += 1;
+}
+
+// Canal de bytes com o gateway
+pub trait DataTransport {
+    fn write_byte(&mut self, byte: u8) -> Result<(), SensorError>;
+    // Não bloqueia: WouldBlock enquanto o registrador de saída está ocupado
+    fn try_write_byte(&mut self, byte: u8) -> nb::Result<(), SensorError> {
+        self.write_byte(byte).map_err(nb::Error::Other)
     }
-    
-    pub fn get_alert_frequency(&self) -> f32 {
-        let alert_count = self.alert_history.iter().filter(|&&x| x).count();
-        (alert_count as f32) / 10.0 * 100.0
+    // Não bloqueia: None quando não há byte disponível
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+// Base de tempo monotônica desde o boot
+pub trait Clock {
+    fn now_ms(&self) -> u32;
+    fn now_us(&self) -> u32;
+    // true quando os timestamps perderam a referência absoluta
+    fn is_degraded(&self) -> bool {
+        false
+    }
+    // Hora de parede (segundos Unix, hora local); None sem RTC funcionando
+    fn unix_seconds(&self) -> Option<u32> {
+        None
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Alert {
-    pub level: AlertLevel,
-    pub message: &'static str,
-    pub value: f32,
-    pub timestamp: u32,
+// Relógio de tempo real externo (ex.: DS3231 no I2C)
+pub trait RealTimeClock {
+    fn unix_seconds(&self) -> Result<u32, SensorError>;
 }
 
-#[derive(Debug, Clone)]
-pub enum AlertLevel {
-    Info,
-    Warning,
-    Critical,
+// Painel do dispositivo: LEDs, buzzer, LED RGB e botão
+pub trait StatusIndicator {
+    fn set_status_led(&mut self, on: bool);
+    fn set_alert_led(&mut self, on: bool);
+    fn set_buzzer(&mut self, on: bool);
+    fn set_rgb(&mut self, red: u8, green: u8, blue: u8);
+    fn is_button_pressed(&self) -> bool;
 }
 
-// Sistema de comunicação
-pub struct CommunicationSystem {
-    serial: arduino_hal::Usart<arduino_hal::pac::USART0>,
-    led_status: arduino_hal::port::Pin<arduino_hal::port::mode::Output>,
-    led_alert: arduino_hal::port::Pin<arduino_hal::port::mode::Output>,
+// Memória não volátil para o log de alertas e contadores persistentes
+pub trait NonVolatileStorage {
+    fn read(&self, address: u16, buffer: &mut [u8]) -> Result<(), SensorError>;
+    fn write(&mut self, address: u16, bytes: &[u8]) -> Result<(), SensorError>;
 }
 
-impl CommunicationSystem {
-    pub fn new() -> Result<Self, SensorError> {
-        let dp = arduino_hal::Peripherals::take().map_err(|_| SensorError::CommunicationError)?;
-        let pins = arduino_hal::pins!(dp);
-        
-        let serial = arduino_hal::Usart::new(
-            dp.USART0,
-            pins.d0,
-            pins.d1.into_output(),
-            9600.into_baudrate(),
-        );
-        
-        let led_status = pins.d13.into_output();
-        let led_alert = pins.d12.into_output();
-        
-        Ok(Self {
-            serial,
-            led_status,
-            led_alert,
-        })
+// Política de erro definida pelo usuário (log, reset, novas tentativas...)
+// Chamada a cada ciclo de monitoramento que falha, com o status no momento do
+// erro; a ação devolvida é executada pelo próprio sistema
+pub trait ErrorHandler {
+    fn on_error(&mut self, err: &SensorError, status: &SystemStatus) -> ErrorAction;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorAction {
+    Continue,
+    Recalibrate,
+}
+
+// Padrão: o erro só volta para quem chamou run_monitoring_cycle
+pub struct NoopHandler;
+
+impl ErrorHandler for NoopHandler {
+    fn on_error(&mut self, _err: &SensorError, _status: &SystemStatus) -> ErrorAction {
+        ErrorAction::Continue
     }
-    
-    pub fn send_data(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
-        let message = format!(
-            "T:{:.1}C,H:{:.1}%,AQ:{:.1}ppm,P:{:.1}kPa,T:{}\n",
-            data.temperature,
-            data.humidity,
-            data.air_quality,
-            data.pressure,
-            data.timestamp
-        );
-        
-        for byte in message.bytes() {
-            nb::block!(self.serial.write(byte))
-                .map_err(|_| SensorError::CommunicationError)?;
-        }
+}
+
+// Implementações sobre o arduino_hal
+pub struct AdcSensorSource {
+    adc: arduino_hal::Adc<arduino_hal::DefaultClock>, // Conversor compartilhado pelos quatro canais
+    temperature_sensor: arduino_hal::adc::AdcChannel,
+    humidity_sensor: arduino_hal::adc::AdcChannel,
+    air_quality_sensor: arduino_hal::adc::AdcChannel,
+    pressure_sensor: arduino_hal::adc::AdcChannel,
+}
+
+impl SensorSource for AdcSensorSource {
+    fn read_raw(&mut self) -> [u16; 4] {
+        let temp_raw = self.adc.read_blocking(&self.temperature_sensor);
+        let humidity_raw = self.adc.read_blocking(&self.humidity_sensor);
+        let air_quality_raw = self.adc.read_blocking(&self.air_quality_sensor);
+        let pressure_raw = self.adc.read_blocking(&self.pressure_sensor);
         
-        Ok(())
+        [temp_raw, humidity_raw, air_quality_raw, pressure_raw]
     }
     
-    pub fn send_alert(&mut self, alert: &Alert) -> Result<(), SensorError> {
-        let level_str = match alert.level {
-            AlertLevel::Info => "INFO",
-            AlertLevel::Warning => "WARNING",
-            AlertLevel::Critical => "CRITICAL",
-        };
-        
-        let message = format!(
-            "ALERT[{}]: {} - Value: {:.1} at {}\n",
-            level_str, alert.message, alert.value, alert.timestamp
-        );
-        
-        for byte in message.bytes() {
-            nb::block!(self.serial.write(byte))
-                .map_err(|_| SensorError::CommunicationError)?;
+    fn read_channel(&mut self, sensor: SensorType) -> u16 {
+        match sensor {
+            SensorType::Temperature => self.adc.read_blocking(&self.temperature_sensor),
+            SensorType::Humidity => self.adc.read_blocking(&self.humidity_sensor),
+            SensorType::AirQuality => self.adc.read_blocking(&self.air_quality_sensor),
+            SensorType::Pressure => self.adc.read_blocking(&self.pressure_sensor),
         }
-        
-        Ok(())
     }
     
-    pub fn update_status_leds(&mut self, status: bool, alert: bool) {
-        if status {
-            self.led_status.set_high();
-        } else {
-            self.led_status.set_low();
-        }
+    // O sensor interno só é medido contra a referência de 1.1V; o bandgap,
+    // contra AVcc (é assim que se descobre o VCC). A cada troca a tensão de
+    // referência precisa assentar: espera e descarta a primeira conversão
+    fn read_device_raw(&mut self) -> (u16, u16) {
+        set_adc_reference(AdcReference::Internal1V1);
+        arduino_hal::delay_ms(ADC_REFERENCE_SETTLE_MS);
+        let _ = self.adc.read_blocking(&arduino_hal::adc::channel::Temperature);
+        let temperature_raw = self.adc.read_blocking(&arduino_hal::adc::channel::Temperature);
         
-        if alert {
-            self.led_alert.set_high();
-        } else {
-            self.led_alert.set_low();
-        }
+        // Volta para AVcc, a referência dos sensores externos
+        set_adc_reference(AdcReference::AVcc);
+        arduino_hal::delay_ms(ADC_REFERENCE_SETTLE_MS);
+        let _ = self.adc.read_blocking(&arduino_hal::adc::channel::Vbg);
+        let bandgap_raw = self.adc.read_blocking(&arduino_hal::adc::channel::Vbg);
+        (temperature_raw, bandgap_raw)
     }
 }
 
-// Sistema de armazenamento de dados
-pub struct DataStorage {
-    data_buffer: [EnvironmentalData; 50],
-    write_index: usize,
-    is_full: bool,
+// Espera após trocar a referência do ADC (capacitor do pino AREF)
+const ADC_REFERENCE_SETTLE_MS: u32 = 2;
+
+enum AdcReference {
+    AVcc,
+    Internal1V1,
 }
 
-impl DataStorage {
-    pub fn new() -> Self {
-        Self {
-            data_buffer: unsafe { core::mem::zeroed() },
-            write_index: 0,
-            is_full: false,
-        }
+// O HAL fixa a referência na criação do Adc; a seleção de canal só mexe
+// nos bits MUX, então os bits REFS trocados aqui são preservados
+fn set_adc_reference(reference: AdcReference) {
+    // SAFETY: só os bits REFS do ADMUX; o Adc é usado no mesmo contexto
+    let adc = unsafe { &*arduino_hal::pac::ADC::ptr() };
+    adc.admux.modify(|_, w| match reference {
+        AdcReference::AVcc => w.refs().avcc(),
+        AdcReference::Internal1V1 => w.refs().internal(),
+    });
+}
+
+pub struct SerialTransport {
+    serial: arduino_hal::Usart<arduino_hal::pac::USART0>,
+}
+
+impl DataTransport for SerialTransport {
+    fn write_byte(&mut self, byte: u8) -> Result<(), SensorError> {
+        nb::block!(self.serial.write(byte)).map_err(|_| SensorError::CommunicationError)
     }
     
-    pub fn store_data(&mut self, data: EnvironmentalData) {
-        self.data_buffer[self.write_index] = data;
-        self.write_index = (self.write_index + 1) % 50;
-        
-        if self.write_index == 0 {
-            self.is_full = true;
+    fn try_write_byte(&mut self, byte: u8) -> nb::Result<(), SensorError> {
+        self.serial.write(byte).map_err(|e| match e {
+            nb::Error::WouldBlock => nb::Error::WouldBlock,
+            nb::Error::Other(_) => nb::Error::Other(SensorError::CommunicationError),
+        })
+    }
+    
+    fn read_byte(&mut self) -> Option<u8> {
+        self.serial.read().ok()
+    }
+}
+
+pub struct HalClock;
+
+impl Clock for HalClock {
+    fn now_ms(&self) -> u32 {
+        arduino_hal::time::millis()
+    }
+    
+    fn now_us(&self) -> u32 {
+        arduino_hal::time::micros()
+    }
+}
+
+// Hora de parede do RTC, com millis como base de tempo. now_ms é sempre o
+// millis: monotônico (intervalos e agendador) e sem acessar o barramento do
+// RTC a cada chamada; a hora real só sai por unix_seconds
+pub struct FallbackClock<R, F = HalClock> {
+    rtc: R,
+    fallback: F,
+    degraded: core::cell::Cell<bool>, // Última leitura do RTC falhou
+}
+
+impl<R: RealTimeClock, F: Clock> FallbackClock<R, F> {
+    pub fn new(rtc: R, fallback: F) -> Self {
+        Self {
+            rtc,
+            fallback,
+            degraded: core::cell::Cell::new(false),
         }
     }
+}
+
+impl<R: RealTimeClock, F: Clock> Clock for FallbackClock<R, F> {
+    fn now_ms(&self) -> u32 {
+        self.fallback.now_ms()
+    }
     
-    pub fn get_latest_data(&self) -> Option<&EnvironmentalData> {
-        if self.write_index == 0 && !self.is_full {
-            return None;
+    fn now_us(&self) -> u32 {
+        self.fallback.now_us()
+    }
+    
+    fn is_degraded(&self) -> bool {
+        self.degraded.get()
+    }
+    
+    // Segundos Unix do RTC; None enquanto ele estiver falhando
+    fn unix_seconds(&self) -> Option<u32> {
+        let seconds = self.rtc.unix_seconds().ok();
+        self.degraded.set(seconds.is_none());
+        seconds
+    }
+}
+
+pub struct BoardIndicators {
+    led_status: arduino_hal::port::Pin<arduino_hal::port::mode::Output>,
+    led_alert: arduino_hal::port::Pin<arduino_hal::port::mode::Output>,
+    buzzer: arduino_hal::port::Pin<arduino_hal::port::mode::Output>,
+    rgb_red: arduino_hal::port::Pin<arduino_hal::port::mode::PwmOutput<Timer1Pwm>, arduino_hal::hal::port::PB1>,
+    rgb_green: arduino_hal::port::Pin<arduino_hal::port::mode::PwmOutput<Timer1Pwm>, arduino_hal::hal::port::PB2>,
+    rgb_blue: arduino_hal::port::Pin<arduino_hal::port::mode::PwmOutput<Timer2Pwm>, arduino_hal::hal::port::PB3>,
+    button: arduino_hal::port::Pin<arduino_hal::port::mode::Input<arduino_hal::port::mode::PullUp>>,
+}
+
+impl StatusIndicator for BoardIndicators {
+    fn set_status_led(&mut self, on: bool) {
+        if on {
+            self.led_status.set_high();
+        } else {
+            self.led_status.set_low();
         }
-        
-        let index = if self.write_index == 0 { 49 } else { self.write_index - 1 };
-        Some(&self.data_buffer[index])
     }
     
-    pub fn get_average_data(&self, count: usize) -> Option<EnvironmentalData> {
-        if count == 0 || count > 50 {
-            return None;
+    fn set_alert_led(&mut self, on: bool) {
+        if on {
+            self.led_alert.set_high();
+        } else {
+            self.led_alert.set_low();
         }
-        
-        let mut sum_temp = 0.0;
-        let mut sum_humidity = 0.0;
-        let mut sum_air_quality = 0.0;
-        let mut sum_pressure = 0.0;
-        
-        let start_index = if self.is_full {
-            (self.write_index + 50 - count) % 50
+    }
+    
+    fn set_buzzer(&mut self, on: bool) {
+        if on {
+            self.buzzer.set_high();
         } else {
-            0
-        };
+            self.buzzer.set_low();
+        }
+    }
+    
+    fn set_rgb(&mut self, red: u8, green: u8, blue: u8) {
+        self.rgb_red.set_duty(red);
+        self.rgb_green.set_duty(green);
+        self.rgb_blue.set_duty(blue);
+    }
+    
+    // Botão ligado ao GND com pull-up interno
+    fn is_button_pressed(&self) -> bool {
+        self.button.is_low()
+    }
+}
+
+impl NonVolatileStorage for arduino_hal::Eeprom {
+    fn read(&self, address: u16, buffer: &mut [u8]) -> Result<(), SensorError> {
+        arduino_hal::Eeprom::read(self, address, buffer).map_err(|_| SensorError::CommunicationError)
+    }
+    
+    fn write(&mut self, address: u16, bytes: &[u8]) -> Result<(), SensorError> {
+        arduino_hal::Eeprom::write(self, address, bytes).map_err(|_| SensorError::CommunicationError)
+    }
+}
+
+// Driver do sensor de partículas Plantower PMS5003 (UART, 9600 baud)
+// Frame de 32 bytes: 0x42 0x4D | tamanho (28) | 13 campos u16 big-endian | checksum
+pub mod pms5003 {
+    pub const FRAME_SIZE: usize = 32;
+    const START_1: u8 = 0x42;
+    const START_2: u8 = 0x4D;
+    const FRAME_LENGTH: u16 = 28;
+    
+    // Concentrações em µg/m³ (valores "atmosféricos", não os de fábrica CF=1)
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ParticulateMatter {
+        pub pm1_0: u16,
+        pub pm2_5: u16,
+        pub pm10: u16,
+    }
+    
+    #[derive(Debug, PartialEq)]
+    pub enum FrameError {
+        BadHeader,
+        BadLength,
+        ChecksumMismatch,
+    }
+    
+    fn word(frame: &[u8; FRAME_SIZE], offset: usize) -> u16 {
+        u16::from_be_bytes([frame[offset], frame[offset + 1]])
+    }
+    
+    pub fn parse_frame(frame: &[u8; FRAME_SIZE]) -> Result<ParticulateMatter, FrameError> {
+        if frame[0] != START_1 || frame[1] != START_2 {
+            return Err(FrameError::BadHeader);
+        }
+        if word(frame, 2) != FRAME_LENGTH {
+            return Err(FrameError::BadLength);
+        }
         
-        for i in 0..count {
-            let index = (start_index + i) % 50;
-            let data = &self.data_buffer[index];
-            
-            sum_temp += data.temperature;
-            sum_humidity += data.humidity;
-            sum_air_quality += data.air_quality;
-            sum_pressure += data.pressure;
+        // Checksum: soma simples de todos os bytes anteriores a ele
+        let sum = frame[..30].iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+        if sum != word(frame, 30) {
+            return Err(FrameError::ChecksumMismatch);
         }
         
-        Some(EnvironmentalData {
-            temperature: sum_temp / count as f32,
-            humidity: sum_humidity / count as f32,
-            air_quality: sum_air_quality / count as f32,
-            pressure: sum_pressure / count as f32,
-            timestamp: arduino_hal::time::millis(),
+        Ok(ParticulateMatter {
+            pm1_0: word(frame, 10),
+            pm2_5: word(frame, 12),
+            pm10: word(frame, 14),
         })
     }
+    
+    // Remonta frames a partir dos bytes da UART, ressincronizando no cabeçalho
+    pub struct FrameReader {
+        buffer: heapless::Vec<u8, FRAME_SIZE>,
+    }
+    
+    impl FrameReader {
+        pub fn new() -> Self {
+            Self {
+                buffer: heapless::Vec::new(),
+            }
+        }
+        
+        pub fn push(&mut self, byte: u8) -> Option<Result<ParticulateMatter, FrameError>> {
+            let expected = match self.buffer.len() {
+                0 => Some(START_1),
+                1 => Some(START_2),
+                _ => None,
+            };
+            if matches!(expected, Some(start) if start != byte) {
+                self.buffer.clear();
+                // 0x42 0x42 0x4D: o segundo 0x42 pode ser o início do frame
+                if byte == START_1 {
+                    let _ = self.buffer.push(byte);
+                }
+                return None;
+            }
+            
+            let _ = self.buffer.push(byte);
+            if self.buffer.len() < FRAME_SIZE {
+                return None;
+            }
+            
+            let mut frame = [0u8; FRAME_SIZE];
+            frame.copy_from_slice(&self.buffer);
+            self.buffer.clear();
+            Some(parse_frame(&frame))
+        }
+    }
 }
 
-// Sistema principal de monitoramento
-pub struct EnvironmentalMonitoringSystem {
-    sensor_manager: SensorManager,
-    alert_system: AlertSystem,
-    communication: CommunicationSystem,
-    data_storage: DataStorage,
-    last_reading_time: u32,
-    system_status: SystemStatus,
+// Modo demo (sala de aula): roteiro de leituras brutas temporizadas no lugar
+// dos sensores reais, para mostrar LEDs, alertas e serial sem montar o circuito
+#[derive(Debug, Clone, Copy)]
+pub struct DemoStep {
+    pub label: &'static str,
+    pub duration_ms: u32,
+    pub raw: [u16; 4], // Na ordem de SensorType::index()
 }
 
-#[derive(Debug)]
-pub enum SystemStatus {
-    Running,
-    Calibrating,
-    Error,
+// Valores brutos equivalentes: 25°C, 50%, ~200/400/2000 ppm, 101.3 kPa
+pub const DEMO_STEPS: [DemoStep; 4] = [
+    DemoStep { label: "AQUECIMENTO", duration_ms: 10_000, raw: [51, 512, 562, 154] },
+    DemoStep { label: "NORMAL", duration_ms: 20_000, raw: [51, 512, 624, 154] },
+    DemoStep { label: "PICO_AQ", duration_ms: 15_000, raw: [51, 512, 754, 154] },
+    DemoStep { label: "RECUPERACAO", duration_ms: 20_000, raw: [51, 512, 624, 154] },
+];
+
+// Roteiro cíclico: ao terminar o último passo recomeça do primeiro
+pub struct DemoScript {
+    steps: &'static [DemoStep],
+    total_ms: u32,
 }
 
-impl EnvironmentalMonitoringSystem {
+impl DemoScript {
+    pub fn new(steps: &'static [DemoStep]) -> Self {
+        Self {
+            steps,
+            total_ms: steps.iter().map(|step| step.duration_ms).sum(),
+        }
+    }
+    
+    pub fn step_at(&self, elapsed_ms: u32) -> &DemoStep {
+        let mut position = if self.total_ms == 0 { 0 } else { elapsed_ms % self.total_ms };
+        for step in self.steps {
+            if position < step.duration_ms {
+                return step;
+            }
+            position -= step.duration_ms;
+        }
+        &self.steps[self.steps.len() - 1]
+    }
+}
+
+pub struct DemoSensorSource<C> {
+    script: DemoScript,
+    clock: C,
+    start: u32,
+}
+
+impl<C: Clock> DemoSensorSource<C> {
+    pub fn new(script: DemoScript, clock: C) -> Self {
+        let start = clock.now_ms();
+        Self { script, clock, start }
+    }
+    
+    pub fn current_step(&self) -> &DemoStep {
+        self.script.step_at(self.clock.now_ms().wrapping_sub(self.start))
+    }
+}
+
+impl<C: Clock> SensorSource for DemoSensorSource<C> {
+    fn read_raw(&mut self) -> [u16; 4] {
+        self.current_step().raw
+    }
+    
+    fn read_channel(&mut self, sensor: SensorType) -> u16 {
+        self.current_step().raw[sensor.index()]
+    }
+    
+    // MCU a 25°C alimentado com 5V
+    fn read_device_raw(&mut self) -> (u16, u16) {
+        (355, 225)
+    }
+}
+
+// Gerenciador de sensores
+pub struct SensorManager<S = AdcSensorSource> {
+    source: S,
+    config: SystemConfig,
+    gains: [f32; 4],
+    offsets: [f32; 4],
+    last_calibration: [Option<u32>; 4], // Timestamp da última calibração por canal
+    lookup_tables: [Option<LookupTable<SENSOR_LUT_POINTS>>; 4], // Substituem a fórmula do sensor
+    splines: [Option<CubicSpline<SENSOR_LUT_POINTS>>; 4], // Têm prioridade sobre as tabelas
+    adc_read_time_us: Option<u32>, // Leitura + conversão de um canal, medida no último ciclo
+    channel_ok: [bool; 4],         // Conversão de cada canal no último ciclo
+    vref: f32,                     // Referência do ADC (VCC) usada nas conversões (V)
+    adc_saturated: [bool; 4],      // Canal no fundo de escala no último ciclo
+}
+
+// Resultado da conversão de cada canal, na ordem de SensorType::index()
+pub type ChannelReadings = [Result<f32, SensorError>; 4];
+
+// Construtores sobre os periféricos da placa: os testes do host montam
+// tudo com with_source/with_parts/from_parts
+#[cfg_attr(test, allow(dead_code))]
+impl SensorManager {
     pub fn new() -> Result<Self, SensorError> {
-        let config = SystemConfig::default();
-        let sensor_manager = SensorManager::new()?;
-        let alert_system = AlertSystem::new(config.clone());
-        let communication = CommunicationSystem::new()?;
-        let data_storage = DataStorage::new();
+        Self::with_config(SystemConfig::default())
+    }
+    
+    pub fn with_config(config: SystemConfig) -> Result<Self, SensorError> {
+        let dp = arduino_hal::Peripherals::take().map_err(|_| SensorError::ReadError)?;
+        let pins = arduino_hal::pins!(dp);
         
-        Ok(Self {
-            sensor_manager,
-            alert_system,
-            communication,
-            data_storage,
-            last_reading_time: 0,
-            system_status: SystemStatus::Running,
-        })
+        let mut adc = arduino_hal::Adc::new(dp.ADC, arduino_hal::DefaultClock);
+        
+        // Os empréstimos de `adc` terminam aqui; depois ele passa para a fonte
+        let temperature_sensor = pins.a0.into_analog_input(&mut adc).into_channel();
+        let humidity_sensor = pins.a1.into_analog_input(&mut adc).into_channel();
+        let air_quality_sensor = pins.a2.into_analog_input(&mut adc).into_channel();
+        let pressure_sensor = pins.a3.into_analog_input(&mut adc).into_channel();
+        
+        let source = AdcSensorSource {
+            adc,
+            temperature_sensor,
+            humidity_sensor,
+            air_quality_sensor,
+            pressure_sensor,
+        };
+        
+        Ok(SensorManager::with_source(source, config))
+    }
+}
+
+impl<S: SensorSource> SensorManager<S> {
+    pub fn with_source(source: S, config: SystemConfig) -> Self {
+        Self {
+            source,
+            config,
+            gains: [1.0; 4],
+            offsets: [0.0; 4],
+            last_calibration: [None; 4],
+            lookup_tables: [None; 4],
+            splines: [None; 4],
+            adc_read_time_us: None,
+            channel_ok: [true; 4],
+            adc_saturated: [false; 4],
+            vref: ADC_VREF,
+        }
     }
     
-    pub fn run_monitoring_cycle(&mut self) -> Result<(), SensorError> {
-        let current_time = arduino_hal::time::millis();
+    pub fn read_all_sensors<C: Clock>(&mut self, clock: &C) -> Result<EnvironmentalData, SensorError> {
+        // N leituras rápidas por canal viram uma única amostra (menos ruído)
+        let samples = self.config.samples_per_cycle.max(1);
+        if self.config.vref_compensation {
+            self.measure_vcc();
+        }
+        let start = clock.now_us();
+        let raw = match average_raw_readings((0..samples).map(|_| self.read_raw())) {
+            Some(raw) => raw,
+            None => {
+                self.channel_ok = [false; 4];
+                return Err(SensorError::ReadError);
+            }
+        };
+        self.adc_saturated = raw.map(is_adc_saturated);
+        let readings = self.convert_batch(raw);
+        let data = self.assemble_sample(readings, clock.now_ms());
         
-        // Verificar se é hora de fazer nova leitura
-        if current_time - self.last_reading_time >= self.sensor_manager.config.reading_interval {
-            match self.sensor_manager.read_all_sensors() {
-                Ok(data) => {
-                    // Armazenar dados
-                    self.data_storage.store_data(data.clone());
-                    
-                    // Enviar dados
-                    self.communication.send_data(&data)?;
-                    
-                    // Verificar alertas
-                    let alerts = self.alert_system.check_alerts(&data);
-                    for alert in alerts {
-                        self.communication.send_alert(&alert)?;
-                    }
-                    
-                    // Atualizar LEDs de status
-                    let has_alerts = !alerts.is_empty();
-                    self.communication.update_status_leds(true, has_alerts);
-                    
-                    self.last_reading_time = current_time;
-                }
-                Err(e) => {
-                    self.system_status = SystemStatus::Error;
-                    return Err(e);
-                }
+        // Tempo por canal: 4 canais x N amostras, conversão incluída
+        let elapsed = clock.now_us().wrapping_sub(start);
+        self.adc_read_time_us = Some(elapsed / (4 * samples as u32));
+        
+        data
+    }
+    
+    // Mediana de N leituras brutas por canal: um pico isolado do MQ-135 ou
+    // do LM35 não chega à conversão (a média de read_all_sensors o diluiria)
+    pub fn read_all_sensors_filtered<C: Clock>(&mut self, samples: usize, clock: &C) -> Result<EnvironmentalData, SensorError> {
+        // Ímpar entre 3 e 9: a mediana é sempre uma leitura real
+        let count = samples.clamp(MIN_MEDIAN_SAMPLES, MAX_MEDIAN_SAMPLES) | 1;
+        
+        let mut channels = [[0u16; MAX_MEDIAN_SAMPLES]; 4];
+        for i in 0..count {
+            let raw = self.read_raw();
+            for (channel, value) in channels.iter_mut().zip(raw) {
+                channel[i] = value;
             }
         }
         
-        Ok(())
+        let raw = channels.map(|mut channel| {
+            channel[..count].sort_unstable();
+            channel[count / 2]
+        });
+        
+        self.adc_saturated = raw.map(is_adc_saturated);
+        let readings = self.convert_batch(raw);
+        self.assemble_sample(readings, clock.now_ms())
     }
     
-    pub fn calibrate_all_sensors(&mut self) -> Result<(), SensorError> {
-        self.system_status = SystemStatus::Calibrating;
+    pub fn adc_read_time_us(&self) -> Option<u32> {
+        self.adc_read_time_us
+    }
+    
+    // Quais canais converteram sem erro na última leitura (por SensorType::index())
+    pub fn channel_status(&self) -> [bool; 4] {
+        self.channel_ok
+    }
+    
+    // Independe do range físico: a leitura pode estar saturada mesmo que a
+    // calibração leve o valor convertido para dentro dos limites
+    pub fn is_saturated(&self, sensor: SensorType) -> bool {
+        self.adc_saturated[sensor.index()]
+    }
+    
+    // Canais avaliados um a um: channel_status() aponta qual sensor falhou,
+    // mas qualquer canal com erro ainda descarta a amostra inteira
+    fn assemble_sample(&mut self, readings: ChannelReadings, timestamp: u32) -> Result<EnvironmentalData, SensorError> {
+        self.channel_ok = readings.each_ref().map(Result::is_ok);
         
-        let sensors = [
-            SensorType::Temperature,
-            SensorType::Humidity,
-            SensorType::AirQuality,
-            SensorType::Pressure,
-        ];
+        let [temperature, humidity, air_quality, pressure] = readings;
+        Ok(EnvironmentalData {
+            temperature: temperature?,
+            humidity: humidity?,
+            air_quality: air_quality?,
+            pressure: pressure?,
+            timestamp,
+        })
+    }
+    
+    pub fn read_particulate_byte(&mut self) -> Option<u8> {
+        self.source.read_particulate_byte()
+    }
+    
+    // Leituras brutas na ordem de SensorType::index()
+    pub fn read_raw(&mut self) -> [u16; 4] {
+        self.source.read_raw()
+    }
+    
+    pub fn read_channel(&mut self, sensor: SensorType) -> u16 {
+        self.source.read_channel(sensor)
+    }
+    
+    // Reinicia só um sensor e confirma com uma leitura válida do canal
+    pub fn reinit_sensor(&mut self, sensor: SensorType) -> Result<(), SensorError> {
+        self.source.reinit(sensor)?;
         
-        for sensor in &sensors {
-            self.sensor_manager.calibrate_sensor(sensor.clone())?;
-        }
+        let raw = self.read_channel(sensor);
+        match sensor {
+            SensorType::Temperature => self.convert_temperature(raw),
+            SensorType::Humidity => self.convert_humidity(raw),
+            SensorType::AirQuality => self.convert_air_quality(raw),
+            SensorType::Pressure => self.convert_pressure(raw, 25.0),
+        }?;
         
-        self.system_status = SystemStatus::Running;
+        self.channel_ok[sensor.index()] = true;
         Ok(())
     }
     
-    pub fn get_system_status(&self) -> &SystemStatus {
-        &self.system_status
+    // Diagnóstico de ruído/mau contato: N leituras brutas seguidas do canal
+    pub fn measure_noise(&mut self, sensor: SensorType, samples: u16) -> Option<NoiseStats> {
+        noise_stats((0..samples).map(|_| self.read_channel(sensor)))
     }
     
-    pub fn get_alert_frequency(&self) -> f32 {
-        self.alert_system.get_alert_frequency()
+    // VCC real pelo bandgap interno; com vref_compensation as conversões
+    // passam a usá-lo como referência (LM35 e BMP280 não são ratiométricos)
+    pub fn measure_vcc(&mut self) -> f32 {
+        let (_, bandgap_raw) = self.source.read_device_raw();
+        let vcc = vcc_from_bandgap(bandgap_raw);
+        
+        // Leitura absurda (bandgap não estabilizado): mantém a referência anterior
+        if self.config.vref_compensation && (VCC_MIN..=VCC_MAX).contains(&vcc) {
+            self.vref = vcc;
+        }
+        vcc
     }
-}
-
-// Função principal
-#[arduino_hal::entry]
-fn main() -> ! {
-    let mut monitoring_system = EnvironmentalMonitoringSystem::new()
-        .expect("Falha ao inicializar sistema de monitoramento");
     
-    // Calibrar sensores na inicialização
-    monitoring_system.calibrate_all_sensors()
-        .expect("Falha na calibração dos sensores");
+    pub fn vref(&self) -> f32 {
+        self.vref
+    }
     
-    loop {
-        match monitoring_system.run_monitoring_cycle() {
-            Ok(_) => {
-                // Sistema funcionando normalmente
-            }
-            Err(e) => {
-                // Tratar erro
-                match e {
-                    SensorError::ReadError => {
-                        // Tentar recalibrar
-                        let _ = monitoring_system.calibrate_all_sensors();
-                    }
-                    SensorError::CommunicationError => {
-                        // Tentar reenviar dados
-                    }
-                    _ => {
-                        // Outros erros
-                    }
-                }
-            }
+    // Referência fora da tolerância: leituras escaladas erradamente sem compensação
+    pub fn is_vref_out_of_range(&self, vcc: f32) -> bool {
+        (vcc - ADC_VREF).abs() > VREF_TOLERANCE
+    }
+    
+    fn adc_scale(&self) -> f32 {
+        self.vref / ADC_RESOLUTION
+    }
+    
+    fn raw_to_voltage(&self, raw: u16) -> f32 {
+        raw as f32 * self.adc_scale()
+    }
+    
+    pub fn read_device_health(&mut self) -> DeviceHealth {
+        let (temperature_raw, bandgap_raw) = self.source.read_device_raw();
+        
+        DeviceHealth {
+            mcu_temperature: mcu_temperature_from_raw(temperature_raw),
+            supply_voltage: vcc_from_bandgap(bandgap_raw),
         }
+    }
+    
+    // Heurística de arranque: canais cuja leitura está fora da faixa típica
+    // do próprio sensor indicam possível troca de cabos
+    pub fn detect_miswiring(&mut self) -> heapless::Vec<SensorType, 4> {
+        check_wiring(self.read_raw())
+    }
+    
+    // Converte os quatro canais numa única passada, cada um com seu resultado
+    // A escala do ADC é calculada uma vez e reaproveitada por todos
+    pub fn convert_batch(&self, raw: [u16; 4]) -> ChannelReadings {
+        let scale = self.adc_scale();
+        let voltages = raw.map(|value| value as f32 * scale);
+        
+        // LM35 no fundo de escala não é uma temperatura confiável
+        let temperature = if is_adc_saturated(raw[0]) {
+            Err(SensorError::ReadError)
+        } else {
+            self.temperature_from_voltage(voltages[0])
+        };
+        // Sem temperatura, os outros canais são avaliados com a referência de 25°C
+        let reference = *temperature.as_ref().unwrap_or(&25.0);
         
-        arduino_hal::delay_ms(100);
+        [
+            temperature,
+            self.humidity_from_voltage(voltages[1], reference),
+            self.air_quality_from_voltage(voltages[2]),
+            self.pressure_from_voltage(voltages[3], reference),
+        ]
+    }
+    
+    pub fn convert_temperature(&self, raw: u16) -> Result<f32, SensorError> {
+        if is_adc_saturated(raw) {
+            return Err(SensorError::ReadError);
+        }
+        self.temperature_from_voltage(self.raw_to_voltage(raw))
+    }
+    
+    // Sem temperatura: assume a referência de 25°C (nenhuma correção)
+    pub fn convert_humidity(&self, raw: u16) -> Result<f32, SensorError> {
+        self.humidity_from_voltage(self.raw_to_voltage(raw), 25.0)
+    }
+    
+    pub fn convert_humidity_compensated(&self, raw: u16, temperature: f32) -> Result<f32, SensorError> {
+        self.humidity_from_voltage(self.raw_to_voltage(raw), temperature)
+    }
+    
+    pub fn convert_air_quality(&self, raw: u16) -> Result<f32, SensorError> {
+        self.air_quality_from_voltage(self.raw_to_voltage(raw))
+    }
+    
+    // (ppm, ± ppm): o desvio padrão das leituras brutas propagado pela
+    // inclinação local da curva do MQ-135 (aproximação de primeira ordem)
+    pub fn convert_air_quality_with_uncertainty(&self, raw_samples: &[u16]) -> Result<(f32, f32), SensorError> {
+        let stats = noise_stats(raw_samples.iter().copied()).ok_or(SensorError::ReadError)?;
+        let scale = self.adc_scale();
+        let voltage = stats.mean * scale;
+        let ppm = self.air_quality_from_voltage(voltage)?;
+        
+        // Derivada por diferença central de meio LSB em torno da média
+        let step = 0.5 * scale;
+        let slope = match (
+            self.air_quality_from_voltage(voltage + step),
+            self.air_quality_from_voltage(voltage - step),
+        ) {
+            (Ok(above), Ok(below)) => (above - below) / (2.0 * step),
+            (Ok(above), Err(_)) => (above - ppm) / step,
+            (Err(_), Ok(below)) => (ppm - below) / step,
+            (Err(_), Err(_)) => return Err(SensorError::ReadError),
+        };
+        
+        Ok((ppm, (slope * stats.std_dev * scale).abs()))
+    }
+    
+    pub fn convert_pressure(&self, raw: u16, temperature: f32) -> Result<f32, SensorError> {
+        self.pressure_from_voltage(self.raw_to_voltage(raw), temperature)
+    }
+    
+    fn temperature_from_voltage(&self, voltage: f32) -> Result<f32, SensorError> {
+        let temperature = self.temperature_uncalibrated(voltage);
+        let temperature = self.apply_calibration(SensorType::Temperature, temperature);
+        
+        if temperature < -40.0 || temperature > 125.0 {
+            return Err(SensorError::ReadError);
+        }
+        
+        Ok(temperature)
+    }
+    
+    fn humidity_from_voltage(&self, voltage: f32, temperature: f32) -> Result<f32, SensorError> {
+        let humidity = self.humidity_uncalibrated(voltage, temperature);
+        let humidity = self.apply_calibration(SensorType::Humidity, humidity);
+        
+        if humidity < 0.0 || humidity > 100.0 {
+            return Err(SensorError::ReadError);
+        }
+        
+        Ok(humidity)
+    }
+    
+    fn temperature_uncalibrated(&self, voltage: f32) -> f32 {
+        // Conversão para sensor LM35 (10mV/°C)
+        self.lookup(SensorType::Temperature, voltage)
+            .unwrap_or(voltage * 100.0) // LM35: 10mV/°C
+    }
+    
+    fn humidity_uncalibrated(&self, voltage: f32, temperature: f32) -> f32 {
+        // Conversão para sensor DHT22
+        let humidity = self.lookup(SensorType::Humidity, voltage)
+            .unwrap_or((voltage / self.vref) * 100.0);
+        
+        // Compensação de temperatura em relação à referência de 25°C
+        humidity - self.config.humidity_temp_coeff * (temperature - 25.0)
+    }
+    
+    fn air_quality_from_voltage(&self, voltage: f32) -> Result<f32, SensorError> {
+        let ppm = self.air_quality_uncalibrated(voltage);
+        let ppm = self.apply_calibration(SensorType::AirQuality, ppm);
+        
+        if ppm < 0.0 || ppm > 10000.0 {
+            return Err(SensorError::ReadError);
+        }
+        
+        Ok(ppm)
+    }
+    
+    fn air_quality_uncalibrated(&self, voltage: f32) -> f32 {
+        // Conversão para sensor MQ-135 (CO2)
+        match self.lookup(SensorType::AirQuality, voltage) {
+            Some(ppm) => ppm,
+            None => {
+                let ratio = self.gas_sensor_resistance_kohm(voltage) / self.config.mq_r0_kohm;
+                MQ135_CURVE_A * ratio.powf(MQ135_CURVE_B)
+            }
+        }
+    }
+    
+    // Divisor do módulo: Vout = Vc * RL / (Rs + RL)  =>  Rs = RL * (Vc - Vout) / Vout
+    pub fn gas_sensor_resistance_kohm(&self, voltage: f32) -> f32 {
+        self.config.load_resistance_kohm * (self.vref - voltage) / voltage
+    }
+    
+    // Em ar com concentração conhecida (ex.: ~400 ppm de CO2 ao ar livre),
+    // ajusta R0 para que a curva do MQ-135 devolva essa concentração
+    pub fn calibrate_r0(&mut self, raw: u16, reference_ppm: f32) -> Result<f32, SensorError> {
+        let voltage = self.raw_to_voltage(raw);
+        if voltage <= 0.0 || voltage >= self.vref || !(reference_ppm > 0.0) {
+            return Err(SensorError::CalibrationError);
+        }
+        
+        let ratio = (reference_ppm / MQ135_CURVE_A).powf(1.0 / MQ135_CURVE_B);
+        let r0 = self.gas_sensor_resistance_kohm(voltage) / ratio;
+        if !r0.is_finite() || r0 <= 0.0 {
+            return Err(SensorError::CalibrationError);
+        }
+        
+        self.config.mq_r0_kohm = r0;
+        Ok(r0)
+    }
+    
+    fn pressure_from_voltage(&self, voltage: f32, temperature: f32) -> Result<f32, SensorError> {
+        let pressure = self.pressure_uncalibrated(voltage, temperature);
+        let pressure = self.apply_calibration(SensorType::Pressure, pressure);
+        
+        if pressure < 30.0 || pressure > 110.0 {
+            return Err(SensorError::ReadError);
+        }
+        
+        Ok(pressure)
+    }
+    
+    fn pressure_uncalibrated(&self, voltage: f32, temperature: f32) -> f32 {
+        // Conversão para sensor BMP280
+        let pressure = self.lookup(SensorType::Pressure, voltage)
+            .unwrap_or((voltage - 0.5) * 400.0); // kPa
+        
+        // Compensação de temperatura em relação à referência de 25°C
+        pressure - self.config.pressure_temp_coeff * (temperature - 25.0)
+    }
+    
+    // Grandeza antes de ganho/offset/fator, com referência de 25°C
+    fn uncalibrated_reading(&self, sensor: SensorType, raw: u16) -> f32 {
+        let voltage = self.raw_to_voltage(raw);
+        match sensor {
+            SensorType::Temperature => self.temperature_uncalibrated(voltage),
+            SensorType::Humidity => self.humidity_uncalibrated(voltage, 25.0),
+            SensorType::AirQuality => self.air_quality_uncalibrated(voltage),
+            SensorType::Pressure => self.pressure_uncalibrated(voltage, 25.0),
+        }
+    }
+    
+    // Tabela tensão -> grandeza do canal, se configurada
+    fn lookup(&self, sensor: SensorType, voltage: f32) -> Option<f32> {
+        if let Some(spline) = &self.splines[sensor.index()] {
+            return Some(spline.evaluate(voltage));
+        }
+        
+        self.lookup_tables[sensor.index()]
+            .as_ref()
+            .map(|table| table.lookup(voltage))
+    }
+    
+    pub fn set_lookup_table(&mut self, sensor: SensorType, table: Option<LookupTable<SENSOR_LUT_POINTS>>) {
+        self.lookup_tables[sensor.index()] = table;
+    }
+    
+    pub fn set_spline(&mut self, sensor: SensorType, spline: Option<CubicSpline<SENSOR_LUT_POINTS>>) {
+        self.splines[sensor.index()] = spline;
+    }
+    
+    // (valor * ganho + offset) por canal e, por último, o fator de calibração do sensor
+    fn apply_calibration(&self, sensor: SensorType, value: f32) -> f32 {
+        let index = sensor.index();
+        (value * self.gains[index] + self.offsets[index]) * self.config.calibration_factor[index]
+    }
+    
+    // Valor antes da correção linear (para ajustar novos coeficientes)
+    pub fn uncalibrated(&self, sensor: SensorType, value: f32) -> f32 {
+        let index = sensor.index();
+        (value / self.config.calibration_factor[index] - self.offsets[index]) / self.gains[index]
+    }
+    
+    pub fn calibration_factor(&self, sensor: SensorType) -> f32 {
+        self.config.calibration_factor[sensor.index()]
+    }
+    
+    pub fn calibration_factors(&self) -> [f32; 4] {
+        self.config.calibration_factor
+    }
+    
+    pub fn set_linear_calibration(&mut self, sensor: SensorType, gain: f32, offset: f32, now: u32) {
+        self.gains[sensor.index()] = gain;
+        self.set_offset(sensor, offset, now);
+    }
+    
+    pub fn gain(&self, sensor: SensorType) -> f32 {
+        self.gains[sensor.index()]
+    }
+    
+    pub fn offset(&self, sensor: SensorType) -> f32 {
+        self.offsets[sensor.index()]
+    }
+    
+    // Offset que leva `value` (sem correção) exatamente a `target` depois de
+    // ganho e fator: (value * ganho + offset) * fator = target
+    pub fn zero_offset(&self, sensor: SensorType, value: f32, target: f32) -> f32 {
+        let index = sensor.index();
+        target / self.config.calibration_factor[index] - self.gains[index] * value
+    }
+    
+    pub fn set_offset(&mut self, sensor: SensorType, offset: f32, now: u32) {
+        self.offsets[sensor.index()] = offset;
+        self.last_calibration[sensor.index()] = Some(now);
+    }
+    
+    // Coeficientes atuais de um canal, para auditoria (comando CALINFO)
+    pub fn calibration_info(&self, sensor: SensorType) -> CalibrationInfo {
+        CalibrationInfo {
+            sensor,
+            gain: self.config.calibration_factor[sensor.index()] * self.gains[sensor.index()],
+            offset: self.config.calibration_factor[sensor.index()] * self.offsets[sensor.index()],
+            last_calibration: self.last_calibration[sensor.index()],
+        }
+    }
+    
+    // Sensor estabilizado sobre soluções salinas saturadas a 25°C
+    // (MgCl2 ~33% RH, NaCl ~75% RH); a reta passa pelos dois pontos
+    pub fn calibrate_humidity_two_point(&mut self, raw_33: u16, raw_75: u16, now: u32) -> Result<(f32, f32), SensorError> {
+        let measured_33 = self.uncalibrated_reading(SensorType::Humidity, raw_33);
+        let measured_75 = self.uncalibrated_reading(SensorType::Humidity, raw_75);
+        
+        // Pontos invertidos ou próximos demais não definem uma reta confiável
+        if measured_75 - measured_33 < 1.0 {
+            return Err(SensorError::CalibrationError);
+        }
+        
+        self.calibrate_sensor(SensorType::Humidity, SALT_MGCL2_RH, raw_33, SALT_NACL_RH, raw_75, now)
+    }
+    
+    // Calibração de campo por dois pontos: leituras brutas do sensor sob duas
+    // referências conhecidas definem a reta valor = medido * ganho + offset.
+    // O fator de calibração do canal volta a 1.0 para a reta valer exatamente
+    pub fn calibrate_sensor(
+        &mut self,
+        sensor: SensorType,
+        known_low: f32,
+        raw_low: u16,
+        known_high: f32,
+        raw_high: u16,
+        now: u32,
+    ) -> Result<(f32, f32), SensorError> {
+        if raw_low == raw_high {
+            return Err(SensorError::CalibrationError);
+        }
+        
+        let measured_low = self.uncalibrated_reading(sensor, raw_low);
+        let measured_high = self.uncalibrated_reading(sensor, raw_high);
+        let gain = (known_high - known_low) / (measured_high - measured_low);
+        let offset = known_low - gain * measured_low;
+        if !gain.is_finite() || !offset.is_finite() || gain == 0.0 {
+            return Err(SensorError::CalibrationError);
+        }
+        
+        self.config.calibration_factor[sensor.index()] = 1.0;
+        self.set_linear_calibration(sensor, gain, offset, now);
+        Ok((gain, offset))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationInfo {
+    pub sensor: SensorType,
+    pub gain: f32,
+    pub offset: f32,
+    pub last_calibration: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorType {
+    Temperature,
+    Humidity,
+    AirQuality,
+    Pressure,
+}
+
+impl SensorType {
+    pub fn index(&self) -> usize {
+        match self {
+            SensorType::Temperature => 0,
+            SensorType::Humidity => 1,
+            SensorType::AirQuality => 2,
+            SensorType::Pressure => 3,
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_SENSORS.iter().copied().find(|sensor| sensor.name() == name)
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorType::Temperature => "TEMP",
+            SensorType::Humidity => "HUM",
+            SensorType::AirQuality => "AQ",
+            SensorType::Pressure => "PRES",
+        }
+    }
+}
+
+// Saúde do próprio dispositivo (self-monitoring)
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceHealth {
+    pub mcu_temperature: f32, // Sensor on-die do ATmega328P (°C)
+    pub supply_voltage: f32,  // VCC estimado pelo bandgap de 1.1V (V)
+}
+
+// Sensor interno do AVR (canal 8, referência de 1.1V): ~1.22 LSB/°C
+// Coeficientes típicos; cada chip precisa de ajuste fino de offset
+pub fn mcu_temperature_from_raw(raw: u16) -> f32 {
+    (raw as f32 - 324.31) / 1.22
+}
+
+// Bandgap de 1.1V medido com referência AVcc: VCC = 1.1 * 1024 / leitura
+pub fn vcc_from_bandgap(raw: u16) -> f32 {
+    if raw == 0 {
+        return 0.0;
+    }
+    1.1 * ADC_RESOLUTION / raw as f32
+}
+
+// Faixa de amostras do filtro de mediana (buffer na pilha)
+pub const MIN_MEDIAN_SAMPLES: usize = 3;
+pub const MAX_MEDIAN_SAMPLES: usize = 9;
+
+// Média arredondada, canal a canal, de várias leituras brutas
+pub fn average_raw_readings<I: Iterator<Item = [u16; 4]>>(readings: I) -> Option<[u16; 4]> {
+    let mut sums = [0u32; 4];
+    let mut count = 0u32;
+    
+    for raw in readings {
+        for i in 0..4 {
+            sums[i] += raw[i] as u32;
+        }
+        count += 1;
+    }
+    
+    if count == 0 {
+        return None;
+    }
+    
+    Some(sums.map(|sum| ((sum + count / 2) / count) as u16))
+}
+
+// Faixa bruta típica de cada canal em ambiente interno
+// LM35 5–50°C, umidade 10–95%, MQ-135 em ar comum, pressão 30–110 kPa
+pub const TYPICAL_RAW_RANGES: [(u16, u16); 4] = [(10, 102), (102, 973), (100, 800), (118, 159)];
+
+pub const ALL_SENSORS: [SensorType; 4] = [
+    SensorType::Temperature,
+    SensorType::Humidity,
+    SensorType::AirQuality,
+    SensorType::Pressure,
+];
+
+pub fn check_wiring(raw: [u16; 4]) -> heapless::Vec<SensorType, 4> {
+    let mut suspicious = heapless::Vec::new();
+    
+    for sensor in ALL_SENSORS {
+        let (min, max) = TYPICAL_RAW_RANGES[sensor.index()];
+        let value = raw[sensor.index()];
+        if value < min || value > max {
+            let _ = suspicious.push(sensor);
+        }
+    }
+    
+    suspicious
+}
+
+// Tabela de calibração para sensores não-lineares: pares (x, y) com x
+// estritamente crescente, interpolação linear e saturação nos extremos
+#[derive(Debug, Clone, Copy)]
+pub struct LookupTable<const N: usize> {
+    points: [(f32, f32); N],
+}
+
+// Pontos das tabelas guardadas por canal no SensorManager
+pub const SENSOR_LUT_POINTS: usize = 8;
+
+impl<const N: usize> LookupTable<N> {
+    pub fn new(points: [(f32, f32); N]) -> Option<Self> {
+        if N == 0 || points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return None;
+        }
+        if !points.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return None;
+        }
+        
+        Some(Self { points })
+    }
+    
+    pub fn lookup(&self, x: f32) -> f32 {
+        let (first_x, first_y) = self.points[0];
+        let (last_x, last_y) = self.points[N - 1];
+        if x <= first_x {
+            return first_y;
+        }
+        if x >= last_x {
+            return last_y;
+        }
+        
+        for pair in self.points.windows(2) {
+            let (x0, y0) = pair[0];
+            let (x1, y1) = pair[1];
+            if x <= x1 {
+                return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+            }
+        }
+        
+        last_y
+    }
+}
+
+// Spline cúbica natural sobre os mesmos pontos de uma tabela de calibração:
+// passa exatamente pelos nós com 1ª e 2ª derivadas contínuas (curva suave
+// para sensores muito não-lineares). Satura nos extremos como a LookupTable
+#[derive(Debug, Clone, Copy)]
+pub struct CubicSpline<const N: usize> {
+    points: [(f32, f32); N],
+    second_derivatives: [f32; N], // M[i] = S''(x[i]); natural: M[0] = M[N-1] = 0
+}
+
+impl<const N: usize> CubicSpline<N> {
+    pub fn new(points: [(f32, f32); N]) -> Option<Self> {
+        if N < 2 || points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return None;
+        }
+        if !points.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return None;
+        }
+        
+        // Sistema tridiagonal dos nós internos resolvido pelo algoritmo de
+        // Thomas; c_prime/d_prime são os coeficientes da eliminação
+        let mut c_prime = [0.0f32; N];
+        let mut d_prime = [0.0f32; N];
+        for i in 1..N - 1 {
+            let h_prev = points[i].0 - points[i - 1].0;
+            let h_next = points[i + 1].0 - points[i].0;
+            let slope_prev = (points[i].1 - points[i - 1].1) / h_prev;
+            let slope_next = (points[i + 1].1 - points[i].1) / h_next;
+            
+            let denominator = 2.0 * (h_prev + h_next) - h_prev * c_prime[i - 1];
+            c_prime[i] = h_next / denominator;
+            d_prime[i] = (6.0 * (slope_next - slope_prev) - h_prev * d_prime[i - 1]) / denominator;
+        }
+        
+        let mut second_derivatives = [0.0f32; N];
+        for i in (1..N - 1).rev() {
+            second_derivatives[i] = d_prime[i] - c_prime[i] * second_derivatives[i + 1];
+        }
+        
+        Some(Self { points, second_derivatives })
+    }
+    
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let (first_x, first_y) = self.points[0];
+        let (last_x, last_y) = self.points[N - 1];
+        if x <= first_x {
+            return first_y;
+        }
+        if x >= last_x {
+            return last_y;
+        }
+        
+        let i = self.points.windows(2).position(|pair| x <= pair[1].0).unwrap_or(N - 2);
+        let (x0, y0) = self.points[i];
+        let (x1, y1) = self.points[i + 1];
+        let m0 = self.second_derivatives[i];
+        let m1 = self.second_derivatives[i + 1];
+        let h = x1 - x0;
+        let a = x1 - x;
+        let b = x - x0;
+        
+        (m0 * a * a * a + m1 * b * b * b) / (6.0 * h)
+            + (y0 / h - m0 * h / 6.0) * a
+            + (y1 / h - m1 * h / 6.0) * b
+    }
+}
+
+// Calibração assistida: pares (valor do dispositivo, valor de referência)
+// ajustados por mínimos quadrados para referência = ganho * valor + offset
+pub struct AssistedCalibration {
+    sensor: Option<SensorType>,
+    count: u32,
+    sum_x: f32,
+    sum_y: f32,
+    sum_xx: f32,
+    sum_xy: f32,
+}
+
+impl AssistedCalibration {
+    pub fn new() -> Self {
+        Self {
+            sensor: None,
+            count: 0,
+            sum_x: 0.0,
+            sum_y: 0.0,
+            sum_xx: 0.0,
+            sum_xy: 0.0,
+        }
+    }
+    
+    // Trocar de sensor descarta os pares acumulados do anterior
+    pub fn add_pair(&mut self, sensor: SensorType, measured: f32, reference: f32) {
+        if self.sensor != Some(sensor) {
+            *self = Self::new();
+            self.sensor = Some(sensor);
+        }
+        
+        self.count += 1;
+        self.sum_x += measured;
+        self.sum_y += reference;
+        self.sum_xx += measured * measured;
+        self.sum_xy += measured * reference;
+    }
+    
+    pub fn sensor(&self) -> Option<SensorType> {
+        self.sensor
+    }
+    
+    pub fn pair_count(&self) -> u32 {
+        self.count
+    }
+    
+    // (ganho, offset); None com menos de dois pares ou valores sem variação
+    pub fn solve(&self) -> Option<(f32, f32)> {
+        if self.count < 2 {
+            return None;
+        }
+        
+        let n = self.count as f32;
+        let denominator = n * self.sum_xx - self.sum_x * self.sum_x;
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        
+        let gain = (n * self.sum_xy - self.sum_x * self.sum_y) / denominator;
+        let offset = (self.sum_y - gain * self.sum_x) / n;
+        Some((gain, offset))
+    }
+}
+
+// Outlier relativo: valor a mais de `tolerance` (fração) da média recente
+// Métricas com média perto de zero (ex.: 0°C) não são avaliadas
+pub fn is_relative_outlier(value: f32, mean: f32, tolerance: f32) -> bool {
+    tolerance > 0.0 && mean.abs() > f32::EPSILON && (value - mean).abs() > tolerance * mean.abs()
+}
+
+// Amostras usadas para estimar a taxa efetiva de leitura
+pub const SAMPLE_RATE_WINDOW: usize = 10;
+
+// Sem frame novo do PMS5003 por esse tempo, a medição de partículas é descartada
+pub const PARTICULATES_STALE_MS: u32 = 10_000;
+
+// Após tantas rejeições seguidas a leitura é aceita: o ambiente mudou de fato
+pub const MAX_CONSECUTIVE_OUTLIERS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorHealth {
+    Alive,
+    Dead,
+}
+
+// Estado de cada sensor (por SensorType::index()); um sensor intermitente
+// só volta a valer após `recovery` leituras boas seguidas
+pub struct SensorHealthTracker {
+    health: [SensorHealth; 4],
+    failures: [u8; 4],
+    recovery: [u8; 4],
+}
+
+impl SensorHealthTracker {
+    pub fn new() -> Self {
+        Self {
+            health: [SensorHealth::Alive; 4],
+            failures: [0; 4],
+            recovery: [0; 4],
+        }
+    }
+    
+    // Retorna o novo estado quando o sensor muda de estado
+    pub fn record(&mut self, index: usize, ok: bool, dead_after: u8, recovery: u8) -> Option<SensorHealth> {
+        match (self.health[index], ok) {
+            (SensorHealth::Alive, true) => {
+                self.failures[index] = 0;
+                None
+            }
+            (SensorHealth::Alive, false) => {
+                self.failures[index] = self.failures[index].saturating_add(1);
+                if self.failures[index] >= dead_after.max(1) {
+                    self.health[index] = SensorHealth::Dead;
+                    self.recovery[index] = 0;
+                    Some(SensorHealth::Dead)
+                } else {
+                    None
+                }
+            }
+            (SensorHealth::Dead, true) => {
+                self.recovery[index] = self.recovery[index].saturating_add(1);
+                if self.recovery[index] >= recovery.max(1) {
+                    self.health[index] = SensorHealth::Alive;
+                    self.failures[index] = 0;
+                    Some(SensorHealth::Alive)
+                } else {
+                    None
+                }
+            }
+            (SensorHealth::Dead, false) => {
+                // Qualquer falha reinicia a contagem de recuperação
+                self.recovery[index] = 0;
+                None
+            }
+        }
+    }
+    
+    pub fn health(&self, index: usize) -> SensorHealth {
+        self.health[index]
+    }
+    
+    pub fn is_alive(&self, index: usize) -> bool {
+        self.health[index] == SensorHealth::Alive
+    }
+}
+
+// Estatísticas de ruído das leituras brutas de um canal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseStats {
+    pub min: u16,
+    pub max: u16,
+    pub mean: f32,
+    pub std_dev: f32,
+}
+
+pub fn noise_stats<I: Iterator<Item = u16>>(samples: I) -> Option<NoiseStats> {
+    let mut count = 0u32;
+    let mut min = u16::MAX;
+    let mut max = u16::MIN;
+    let mut mean = 0.0f32;
+    let mut m2 = 0.0f32;
+    
+    // Algoritmo de Welford: média e variância numa passada, sem buffer
+    for sample in samples {
+        count += 1;
+        min = min.min(sample);
+        max = max.max(sample);
+        let delta = sample as f32 - mean;
+        mean += delta / count as f32;
+        m2 += delta * (sample as f32 - mean);
+    }
+    
+    if count == 0 {
+        return None;
+    }
+    
+    Some(NoiseStats {
+        min,
+        max,
+        mean,
+        std_dev: (m2 / count as f32).sqrt(),
+    })
+}
+
+// Auto-zero: corrige a deriva de offset quando o sinal fica estável
+// por uma janela inteira (baixa variância = condição base conhecida).
+// Estabilidade sozinha não prova ar limpo: só vale depois de arm(),
+// com o aparelho colocado de propósito na condição de referência
+pub const AUTO_ZERO_WINDOW: usize = 32;
+
+pub struct AutoZero {
+    reference: f32,
+    max_std_dev: f32,
+    window: [f32; AUTO_ZERO_WINDOW],
+    next: usize,  // Próxima posição da janela circular
+    filled: usize, // Leituras na janela desde arm(), até AUTO_ZERO_WINDOW
+    armed: bool,
+}
+
+impl AutoZero {
+    pub fn new(reference: f32, max_std_dev: f32) -> Self {
+        Self {
+            reference,
+            max_std_dev,
+            window: [0.0; AUTO_ZERO_WINDOW],
+            next: 0,
+            filled: 0,
+            armed: false,
+        }
+    }
+    
+    pub fn reference(&self) -> f32 {
+        self.reference
+    }
+    
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+    
+    // A janela recomeça: só conta o que foi lido já na condição de referência
+    pub fn arm(&mut self) {
+        self.armed = true;
+        self.next = 0;
+        self.filled = 0;
+    }
+    
+    // Recebe o valor convertido sem offset; devolve a média da janela quando
+    // armado e estável (o offset depende do ganho: SensorManager::zero_offset)
+    pub fn update(&mut self, value: f32) -> Option<f32> {
+        if !self.armed {
+            return None;
+        }
+        
+        self.window[self.next] = value;
+        self.next = (self.next + 1) % AUTO_ZERO_WINDOW;
+        self.filled = (self.filled + 1).min(AUTO_ZERO_WINDOW);
+        
+        if self.filled < AUTO_ZERO_WINDOW {
+            return None;
+        }
+        
+        let mean = self.window.iter().sum::<f32>() / AUTO_ZERO_WINDOW as f32;
+        let variance = self.window.iter()
+            .map(|&x| (x - mean) * (x - mean))
+            .sum::<f32>() / AUTO_ZERO_WINDOW as f32;
+        
+        // Um ajuste por arm(); instável, a janela segue deslizando
+        if variance.sqrt() <= self.max_std_dev {
+            self.armed = false;
+            return Some(mean);
+        }
+        
+        None
+    }
+}
+
+// Limites de alerta de um perfil (ex.: escola em dia útil vs. fim de semana)
+pub type AlertProfile = ThresholdConfig;
+
+impl ThresholdConfig {
+    // Condição ativa só é encerrada quando a leitura volta à faixa com folga
+    // `band` (a mesma histerese de rearme), evitando que o painel pisque perto do limite
+    pub fn is_cleared(&self, code: AlertCode, data: &EnvironmentalData, band: f32) -> bool {
+        match code {
+            AlertCode::AirQualityHigh => data.air_quality <= self.air_quality_max - band,
+            AlertCode::TemperatureHigh => data.temperature <= self.temp_max - band,
+            AlertCode::TemperatureLow => data.temperature >= self.temp_min + band,
+            AlertCode::HumidityHigh => data.humidity <= self.humidity_max - band,
+            AlertCode::HumidityLow => data.humidity >= self.humidity_min + band,
+            AlertCode::PressureHigh => data.pressure <= self.pressure_max - band,
+            AlertCode::PressureLow => data.pressure >= self.pressure_min + band,
+            _ => true,
+        }
+    }
+    
+    // Leitura dentro da faixa com folga `band` dos dois lados
+    pub fn is_inside_band(&self, kind: AlertKind, data: &EnvironmentalData, band: f32) -> bool {
+        match kind {
+            AlertKind::AirQuality => data.air_quality <= self.air_quality_max - band,
+            AlertKind::Temperature => {
+                data.temperature >= self.temp_min + band && data.temperature <= self.temp_max - band
+            }
+            AlertKind::Humidity => {
+                data.humidity >= self.humidity_min + band && data.humidity <= self.humidity_max - band
+            }
+            AlertKind::Pressure => {
+                data.pressure >= self.pressure_min + band && data.pressure <= self.pressure_max - band
+            }
+            AlertKind::Device => true,
+        }
+    }
+}
+
+fn metric_value(kind: AlertKind, data: &EnvironmentalData) -> Option<f32> {
+    match kind {
+        AlertKind::AirQuality => Some(data.air_quality),
+        AlertKind::Temperature => Some(data.temperature),
+        AlertKind::Humidity => Some(data.humidity),
+        AlertKind::Pressure => Some(data.pressure),
+        AlertKind::Device => None,
+    }
+}
+
+// Dia da semana (0 = domingo) a partir dos segundos Unix do RTC;
+// 01/01/1970 foi uma quinta-feira
+pub fn day_of_week(unix_seconds: u32) -> u8 {
+    ((unix_seconds / 86_400 + 4) % 7) as u8
+}
+
+pub fn is_weekend(day_of_week: u8) -> bool {
+    day_of_week == 0 || day_of_week == 6
+}
+
+// Capacidade fixa da lista de alertas de um ciclo: qualidade do ar,
+// temperatura, umidade e pressão (uma condição por métrica). Sem alocador, a
+// memória usada é conhecida em tempo de compilação (4 x size_of::<Alert>())
+pub const MAX_SIMULTANEOUS_ALERTS: usize = 4;
+
+// Lista cheia: o alerta só entra no lugar de um menos severo
+fn push_alert(alerts: &mut heapless::Vec<Alert, MAX_SIMULTANEOUS_ALERTS>, alert: Alert) {
+    if let Err(alert) = alerts.push(alert) {
+        let weakest = alerts
+            .iter_mut()
+            .min_by_key(|existing| existing.level)
+            .filter(|existing| existing.level < alert.level);
+        if let Some(slot) = weakest {
+            *slot = alert;
+        }
+    }
+}
+
+// Sistema de alertas
+#[derive(Clone)]
+pub struct AlertSystem {
+    config: SystemConfig,
+    weekday_profile: AlertProfile,
+    weekend_profile: AlertProfile,
+    weekend: bool, // Atualizado pelo RTC; sem RTC vale o perfil de dia útil
+    alert_history: [Option<AlertLevel>; 10], // Maior severidade de cada ciclo
+    alert_count: usize,
+    active: heapless::Vec<Alert, 8>, // Condições vigentes (timestamp = início)
+    hysteresis: Option<[f32; 4]>, // Banda para rearmar cada canal após disparar
+    latched: [bool; 4],      // Canal já alertou e ainda não voltou para dentro da banda
+}
+
+impl AlertSystem {
+    pub fn new(config: SystemConfig) -> Self {
+        let profile = config.thresholds;
+        Self {
+            config,
+            weekday_profile: profile,
+            weekend_profile: profile,
+            weekend: false,
+            alert_history: [None; 10],
+            alert_count: 0,
+            active: heapless::Vec::new(),
+            hysteresis: None,
+            latched: [false; 4],
+        }
+    }
+    
+    // Cada canal (qualidade do ar, temperatura, umidade, pressão) alerta uma vez
+    // e só volta a alertar depois que a leitura entrar bands[kind.index()] dentro
+    // da faixa (ppm, °C, %, kPa); banda 0 = a cada ciclo
+    pub fn with_hysteresis(mut self, bands: [f32; 4]) -> Self {
+        self.set_hysteresis(bands);
+        self
+    }
+    
+    // Mesma configuração, perfis e histerese, sem condições ativas nem histórico
+    pub fn fresh(&self) -> Self {
+        Self {
+            alert_history: [None; 10],
+            alert_count: 0,
+            active: heapless::Vec::new(),
+            latched: [false; 4],
+            ..self.clone()
+        }
+    }
+    
+    pub fn set_profiles(&mut self, weekday: AlertProfile, weekend: AlertProfile) {
+        self.weekday_profile = weekday;
+        self.weekend_profile = weekend;
+    }
+    
+    pub fn set_weekend(&mut self, weekend: bool) {
+        self.weekend = weekend;
+    }
+    
+    pub fn active_profile(&self) -> &AlertProfile {
+        if self.weekend {
+            &self.weekend_profile
+        } else {
+            &self.weekday_profile
+        }
+    }
+    
+    // Ajuste pela serial vale para os dois perfis
+    pub fn set_air_quality_max(&mut self, threshold: f32) {
+        self.config.thresholds.air_quality_max = threshold;
+        self.weekday_profile.air_quality_max = threshold;
+        self.weekend_profile.air_quality_max = threshold;
+    }
+    
+    // Limites base (sem os ajustes de perfil), como exportados no snapshot
+    pub fn thresholds(&self) -> ThresholdConfig {
+        self.config.thresholds
+    }
+    
+    // Substitui os limites dos dois perfis de uma vez (restauração de estado)
+    pub fn set_thresholds(&mut self, thresholds: ThresholdConfig) -> Result<(), &'static str> {
+        thresholds.validate()?;
+        self.config.thresholds = thresholds;
+        self.weekday_profile = thresholds;
+        self.weekend_profile = thresholds;
+        Ok(())
+    }
+    
+    pub fn set_hysteresis(&mut self, bands: [f32; 4]) {
+        self.hysteresis = if bands.iter().any(|band| *band != 0.0) {
+            Some(bands.map(f32::abs))
+        } else {
+            None
+        };
+        self.latched = [false; 4];
+    }
+    
+    // Recusa valores que deixariam um perfil com mínimo acima do máximo
+    pub fn set_threshold(&mut self, metric: ThresholdMetric, value: f32) -> Result<(), &'static str> {
+        let mut weekday = self.weekday_profile;
+        let mut weekend = self.weekend_profile;
+        let mut thresholds = self.config.thresholds;
+        for profile in [&mut weekday, &mut weekend, &mut thresholds] {
+            profile.set(metric, value);
+            profile.validate()?;
+        }
+        
+        self.weekday_profile = weekday;
+        self.weekend_profile = weekend;
+        self.config.thresholds = thresholds;
+        Ok(())
+    }
+    
+    pub fn check_alerts(&mut self, data: &EnvironmentalData) -> heapless::Vec<Alert, MAX_SIMULTANEOUS_ALERTS> {
+        let mut alerts = heapless::Vec::new();
+        let profile = *self.active_profile();
+        
+        // Verificar qualidade do ar
+        if data.air_quality > profile.air_quality_max {
+            let mut message: heapless::String<64> = heapless::String::new();
+            let _ = write!(
+                message,
+                "Qualidade do ar crítica: {:.0} ppm > {:.0} ppm",
+                data.air_quality, profile.air_quality_max
+            );
+            push_alert(&mut alerts, Alert::new(
+                AlertKind::AirQuality,
+                AlertCode::AirQualityHigh,
+                AlertLevel::Warning,
+                &message,
+                data.air_quality,
+                data.timestamp,
+            ));
+        }
+        
+        // Verificar temperatura
+        if data.temperature > profile.temp_max || data.temperature < profile.temp_min {
+            let code = if data.temperature > profile.temp_max {
+                AlertCode::TemperatureHigh
+            } else {
+                AlertCode::TemperatureLow
+            };
+            let mut message: heapless::String<64> = heapless::String::new();
+            let _ = write!(
+                message,
+                "Temperatura {:.1}°C fora de {:.1}..{:.1}°C",
+                data.temperature, profile.temp_min, profile.temp_max
+            );
+            push_alert(&mut alerts, Alert::new(
+                AlertKind::Temperature,
+                code,
+                AlertLevel::Critical,
+                &message,
+                data.temperature,
+                data.timestamp,
+            ));
+        }
+        
+        // Verificar umidade
+        if data.humidity > profile.humidity_max || data.humidity < profile.humidity_min {
+            let code = if data.humidity > profile.humidity_max {
+                AlertCode::HumidityHigh
+            } else {
+                AlertCode::HumidityLow
+            };
+            let mut message: heapless::String<64> = heapless::String::new();
+            let _ = write!(
+                message,
+                "Umidade {:.1}% fora de {:.1}..{:.1}%",
+                data.humidity, profile.humidity_min, profile.humidity_max
+            );
+            push_alert(&mut alerts, Alert::new(
+                AlertKind::Humidity,
+                code,
+                AlertLevel::Warning,
+                &message,
+                data.humidity,
+                data.timestamp,
+            ));
+        }
+        
+        // Verificar pressão
+        if data.pressure > profile.pressure_max || data.pressure < profile.pressure_min {
+            let code = if data.pressure > profile.pressure_max {
+                AlertCode::PressureHigh
+            } else {
+                AlertCode::PressureLow
+            };
+            let mut message: heapless::String<64> = heapless::String::new();
+            let _ = write!(
+                message,
+                "Pressão {:.1} kPa fora de {:.1}..{:.1} kPa",
+                data.pressure, profile.pressure_min, profile.pressure_max
+            );
+            push_alert(&mut alerts, Alert::new(
+                AlertKind::Pressure,
+                code,
+                AlertLevel::Warning,
+                &message,
+                data.pressure,
+                data.timestamp,
+            ));
+        }
+        
+        let highest = alerts.iter().map(|alert| alert.level).max();
+        self.update_alert_history(highest);
+        self.update_active(&alerts, data, &profile);
+        
+        if let Some(bands) = self.hysteresis {
+            self.apply_hysteresis(&mut alerts, data, &profile, bands);
+        }
+        alerts
+    }
+    
+    // Histórico e condições ativas já foram atualizados; aqui só se decide o que é emitido
+    fn apply_hysteresis(
+        &mut self,
+        alerts: &mut heapless::Vec<Alert, MAX_SIMULTANEOUS_ALERTS>,
+        data: &EnvironmentalData,
+        profile: &AlertProfile,
+        bands: [f32; 4],
+    ) {
+        let channels = [AlertKind::AirQuality, AlertKind::Temperature, AlertKind::Humidity, AlertKind::Pressure];
+        for kind in channels {
+            let raised = alerts.iter().any(|alert| alert.kind == kind);
+            if !raised && profile.is_inside_band(kind, data, bands[kind.index()]) {
+                self.latched[kind.index()] = false;
+            }
+        }
+        
+        let latched = &mut self.latched;
+        alerts.retain(|alert| match latched.get_mut(alert.kind.index()) {
+            Some(true) => false,
+            Some(state) if bands[alert.kind.index()] > 0.0 => {
+                *state = true;
+                true
+            }
+            _ => true,
+        });
+    }
+    
+    fn update_active(&mut self, raised: &[Alert], data: &EnvironmentalData, profile: &AlertProfile) {
+        let mut active = heapless::Vec::new();
+        
+        for alert in raised {
+            let mut current = alert.clone();
+            if let Some(previous) = self.active.iter().find(|previous| previous.code == alert.code) {
+                current.timestamp = previous.timestamp;
+            }
+            let _ = active.push(current);
+        }
+        
+        // Condições que não dispararam agora, mas ainda não saíram da histerese
+        for previous in self.active.iter() {
+            let band = self.hysteresis.and_then(|bands| bands.get(previous.kind.index()).copied()).unwrap_or(0.0);
+            if raised.iter().any(|alert| alert.code == previous.code) || profile.is_cleared(previous.code, data, band) {
+                continue;
+            }
+            let mut current = previous.clone();
+            if let Some(value) = metric_value(current.kind, data) {
+                current.value = value;
+            }
+            let _ = active.push(current);
+        }
+        
+        self.active = active;
+    }
+    
+    // Estado atual (não histórico) para o painel ao vivo
+    pub fn active_alerts(&self) -> heapless::Vec<Alert, 8> {
+        self.active.clone()
+    }
+    
+    // Histórico em ordem cronológica como pares (símbolo, repetições)
+    pub fn encode_alert_history_rle(&self) -> heapless::Vec<(u8, u8), 10> {
+        let mut pairs: heapless::Vec<(u8, u8), 10> = heapless::Vec::new();
+        let len = self.alert_count.min(10);
+        let start = if self.alert_count < 10 { 0 } else { self.alert_count % 10 };
+        
+        for i in 0..len {
+            let symbol = history_symbol(self.alert_history[(start + i) % 10]);
+            match pairs.last_mut() {
+                Some((last, count)) if *last == symbol => *count += 1,
+                _ => {
+                    let _ = pairs.push((symbol, 1));
+                }
+            }
+        }
+        
+        pairs
+    }
+    
+    fn update_alert_history(&mut self, highest: Option<AlertLevel>) {
+        self.alert_history[self.alert_count % 10] = highest;
+        self.alert_count += 1;
+    }
+    
+    pub fn get_alert_frequency(&self) -> f32 {
+        self.alert_frequency_for(AlertLevel::Info)
+    }
+    
+    // Frequência considerando apenas ciclos com alerta de severidade >= min_level
+    pub fn alert_frequency_for(&self, min_level: AlertLevel) -> f32 {
+        let alert_count = self.alert_history
+            .iter()
+            .filter(|level| matches!(level, Some(level) if *level >= min_level))
+            .count();
+        (alert_count as f32) / 10.0 * 100.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub code: AlertCode,
+    pub level: AlertLevel,
+    pub message: heapless::String<ALERT_MESSAGE_LEN>,
+    pub value: f32,
+    pub timestamp: u32,
+}
+
+// Tamanho máximo da mensagem de um alerta (bytes UTF-8)
+pub const ALERT_MESSAGE_LEN: usize = 48;
+
+impl Alert {
+    // Mensagens longas são cortadas em ALERT_MESSAGE_LEN bytes,
+    // sempre numa fronteira de caractere
+    pub fn new(kind: AlertKind, code: AlertCode, level: AlertLevel, msg: &str, value: f32, timestamp: u32) -> Self {
+        let mut message = heapless::String::new();
+        for character in msg.chars() {
+            if message.push(character).is_err() {
+                break;
+            }
+        }
+        
+        Self {
+            kind,
+            code,
+            level,
+            message,
+            value,
+            timestamp,
+        }
+    }
+}
+
+// Tipo de condição que gerou o alerta
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertKind {
+    AirQuality,
+    Temperature,
+    Humidity,
+    Pressure,
+    Device,
+}
+
+pub const ALERT_KINDS: usize = 5;
+
+impl AlertKind {
+    pub fn index(&self) -> usize {
+        match self {
+            AlertKind::AirQuality => 0,
+            AlertKind::Temperature => 1,
+            AlertKind::Humidity => 2,
+            AlertKind::Pressure => 3,
+            AlertKind::Device => 4,
+        }
+    }
+    
+    pub fn sensor(&self) -> Option<SensorType> {
+        match self {
+            AlertKind::AirQuality => Some(SensorType::AirQuality),
+            AlertKind::Temperature => Some(SensorType::Temperature),
+            AlertKind::Humidity => Some(SensorType::Humidity),
+            AlertKind::Pressure => Some(SensorType::Pressure),
+            AlertKind::Device => None,
+        }
+    }
+    
+    // Tipo gravado no log de alertas da EEPROM (record[0]). Diferente de
+    // index(): Pressure entrou depois, então Device mantém o 3 dos logs antigos
+    pub fn log_code(&self) -> u8 {
+        match self {
+            AlertKind::AirQuality => 0,
+            AlertKind::Temperature => 1,
+            AlertKind::Humidity => 2,
+            AlertKind::Device => 3,
+            AlertKind::Pressure => 4,
+        }
+    }
+    
+    pub fn from_log_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(AlertKind::AirQuality),
+            1 => Some(AlertKind::Temperature),
+            2 => Some(AlertKind::Humidity),
+            3 => Some(AlertKind::Device),
+            4 => Some(AlertKind::Pressure),
+            _ => None,
+        }
+    }
+}
+
+// Códigos numéricos estáveis enviados no lugar do texto do alerta;
+// o gateway mantém a tabela código -> mensagem. Nunca renumerar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertCode {
+    AirQualityHigh = 1,
+    TemperatureHigh = 2,
+    TemperatureLow = 3,
+    HumidityHigh = 4,
+    HumidityLow = 5,
+    AirQualityDeviation = 6,
+    TemperatureDeviation = 7,
+    HumidityDeviation = 8,
+    McuOverheat = 9,
+    GasSensorExpired = 10,
+    AirQualityRising = 11,
+    SensorDead = 12,
+    AirQualityUnusualForHour = 13,
+    TemperatureUnusualForHour = 14,
+    HumidityUnusualForHour = 15,
+    PressureHigh = 16,
+    PressureLow = 17,
+    SupplyOutOfRange = 18,
+    TemperatureAdcSaturated = 19,
+}
+
+// Como os alertas são enviados pela serial
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertFormat {
+    Text, // "ALERT[WARNING]: Qualidade do ar crítica - Value: 150.0 at 1234"
+    Code, // "AC:1,1,150.0,1234" (código, severidade, valor, timestamp)
+}
+
+// Tempestade de alertas: mais de `threshold` alertas por ciclo durante
+// `cycles` ciclos seguidos. No modo resumo cada alerta deixa de ir para a
+// serial e o ciclo sai como uma contagem agregada; volta ao normal após
+// o mesmo número de ciclos calmos
+pub struct AlertStormDetector {
+    threshold: u8,
+    cycles: u8,
+    in_cycle: u16,
+    streak: u8, // Ciclos seguidos acima (fora do modo) ou abaixo (no modo) do limite
+    active: bool,
+}
+
+impl AlertStormDetector {
+    pub fn new(threshold: u8, cycles: u8) -> Self {
+        Self {
+            threshold,
+            cycles: cycles.max(1),
+            in_cycle: 0,
+            streak: 0,
+            active: false,
+        }
+    }
+    
+    pub fn note_alert(&mut self) {
+        self.in_cycle = self.in_cycle.saturating_add(1);
+    }
+    
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    
+    // Fecha o ciclo; com o modo resumo ativo devolve a contagem a emitir
+    pub fn end_cycle(&mut self) -> Option<u16> {
+        let count = core::mem::replace(&mut self.in_cycle, 0);
+        if self.threshold == 0 {
+            return None;
+        }
+        
+        let stormy = count > self.threshold as u16;
+        if stormy != self.active {
+            self.streak += 1;
+            if self.streak >= self.cycles {
+                self.active = stormy;
+                self.streak = 0;
+            }
+        } else {
+            self.streak = 0;
+        }
+        
+        if self.active { Some(count) } else { None }
+    }
+}
+
+// Limita a K alertas de cada tipo por janela de tempo
+pub struct AlertThrottle {
+    max_per_window: u8,
+    window_ms: u32,
+    window_start: [u32; ALERT_KINDS],
+    sent_in_window: [u8; ALERT_KINDS],
+    suppressed: u32,
+}
+
+impl AlertThrottle {
+    pub fn new(max_per_window: u8, window_ms: u32) -> Self {
+        Self {
+            max_per_window,
+            window_ms,
+            window_start: [0; ALERT_KINDS],
+            sent_in_window: [0; ALERT_KINDS],
+            suppressed: 0,
+        }
+    }
+    
+    pub fn allow(&mut self, kind: AlertKind, now: u32) -> bool {
+        let i = kind.index();
+        
+        // Nova janela começa no primeiro alerta após a anterior expirar
+        if self.sent_in_window[i] == 0 || now.wrapping_sub(self.window_start[i]) >= self.window_ms {
+            self.window_start[i] = now;
+            self.sent_in_window[i] = 0;
+        }
+        
+        if self.sent_in_window[i] < self.max_per_window {
+            self.sent_in_window[i] += 1;
+            true
+        } else {
+            self.suppressed += 1;
+            false
+        }
+    }
+    
+    pub fn suppressed_count(&self) -> u32 {
+        self.suppressed
+    }
+}
+
+// Ordem de severidade: Info < Warning < Critical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlertLevel {
+    // Severidade numérica usada nos formatos compactos (EEPROM, código)
+    pub fn severity(&self) -> u8 {
+        match self {
+            AlertLevel::Info => 0,
+            AlertLevel::Warning => 1,
+            AlertLevel::Critical => 2,
+        }
+    }
+    
+    pub fn from_severity(severity: u8) -> Option<Self> {
+        match severity {
+            0 => Some(AlertLevel::Info),
+            1 => Some(AlertLevel::Warning),
+            2 => Some(AlertLevel::Critical),
+            _ => None,
+        }
+    }
+}
+
+// Símbolo de um ciclo no RLE do histórico: 0 = sem alerta, 1 + severidade
+fn history_symbol(level: Option<AlertLevel>) -> u8 {
+    match level {
+        Some(level) => 1 + level.severity(),
+        None => 0,
+    }
+}
+
+// Inverso de encode_alert_history_rle (lado do gateway)
+// None se algum par tiver símbolo desconhecido ou o total exceder o histórico
+pub fn decode_alert_history_rle(pairs: &[(u8, u8)]) -> Option<heapless::Vec<Option<AlertLevel>, 10>> {
+    let mut history = heapless::Vec::new();
+    for &(symbol, count) in pairs {
+        let level = match symbol {
+            0 => None,
+            _ => Some(AlertLevel::from_severity(symbol - 1)?),
+        };
+        for _ in 0..count {
+            history.push(level).ok()?;
+        }
+    }
+    
+    Some(history)
+}
+
+// Destinos de alerta (bits combináveis)
+pub const ALERT_SINK_SERIAL: u8 = 0x01;
+pub const ALERT_SINK_LED: u8 = 0x02;
+pub const ALERT_SINK_BUZZER: u8 = 0x04;
+pub const ALERT_SINK_EEPROM: u8 = 0x08;
+
+// Tabela de roteamento: quais destinos cada severidade aciona
+#[derive(Debug, Clone, Copy)]
+pub struct AlertRouting {
+    pub info: u8,
+    pub warning: u8,
+    pub critical: u8,
+}
+
+impl Default for AlertRouting {
+    fn default() -> Self {
+        Self {
+            info: ALERT_SINK_SERIAL,
+            warning: ALERT_SINK_SERIAL | ALERT_SINK_LED,
+            critical: ALERT_SINK_SERIAL | ALERT_SINK_LED | ALERT_SINK_BUZZER | ALERT_SINK_EEPROM,
+        }
+    }
+}
+
+impl AlertRouting {
+    pub fn sinks_for(&self, level: &AlertLevel) -> u8 {
+        match level {
+            AlertLevel::Info => self.info,
+            AlertLevel::Warning => self.warning,
+            AlertLevel::Critical => self.critical,
+        }
+    }
+}
+
+// Limites aceitos para o intervalo de leitura (ms)
+// O mínimo deve cobrir 4 canais x samples_per_cycle x adc_read_time_us()
+// com folga para transmissão e alertas
+pub const MIN_READING_INTERVAL: u32 = 100;
+pub const MAX_READING_INTERVAL: u32 = 3_600_000;
+
+// Limiares configuráveis via serial
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMetric {
+    TempMin,
+    TempMax,
+    HumidityMin,
+    HumidityMax,
+    AirQualityMax,
+    PressureMin,
+    PressureMax,
+}
+
+impl ThresholdMetric {
+    pub const ALL: [ThresholdMetric; 7] = [
+        ThresholdMetric::TempMin,
+        ThresholdMetric::TempMax,
+        ThresholdMetric::HumidityMin,
+        ThresholdMetric::HumidityMax,
+        ThresholdMetric::AirQualityMax,
+        ThresholdMetric::PressureMin,
+        ThresholdMetric::PressureMax,
+    ];
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "TEMP_MIN" => Some(ThresholdMetric::TempMin),
+            "TEMP_MAX" => Some(ThresholdMetric::TempMax),
+            "HUM_MIN" => Some(ThresholdMetric::HumidityMin),
+            "HUM_MAX" => Some(ThresholdMetric::HumidityMax),
+            "AQ_MAX" => Some(ThresholdMetric::AirQualityMax),
+            "PRES_MIN" => Some(ThresholdMetric::PressureMin),
+            "PRES_MAX" => Some(ThresholdMetric::PressureMax),
+            _ => None,
+        }
+    }
+    
+    // Faixa física de cada sensor (mesma usada nas conversões)
+    pub fn valid_range(&self) -> (f32, f32) {
+        match self {
+            ThresholdMetric::TempMin | ThresholdMetric::TempMax => (-40.0, 125.0),
+            ThresholdMetric::HumidityMin | ThresholdMetric::HumidityMax => (0.0, 100.0),
+            ThresholdMetric::AirQualityMax => (0.0, 10000.0),
+            ThresholdMetric::PressureMin | ThresholdMetric::PressureMax => (30.0, 110.0),
+        }
+    }
+}
+
+// Comandos recebidos pela serial
+// Exemplos: "INTERVAL 5000", "SET THRESH TEMP_MAX 35.0", "NOISE AQ 32", "CALINFO",
+// "REF TEMP 24.8" (par com o sensor de referência), "REF FIT" (aplica o ajuste),
+// "PAUSE"/"RESUME" (manutenção), "MEM" (tamanho das estruturas em RAM)
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    SetInterval(u32),
+    SetThreshold(ThresholdMetric, f32),
+    Noise(SensorType, u16),
+    CalInfo,
+    Reference(SensorType, f32),
+    ReferenceFit,
+    Pause,
+    Resume,
+    SetDeviceId(u8),
+    Dump(u32, u8), // Página, amostras por página
+    MemoryReport,
+    ZeroAirQuality, // Aparelho em ar limpo: arma o auto-zero do MQ-135
+    Invalid(&'static str),
+}
+
+// Maior página do DUMP (o buffer inteiro)
+pub const MAX_DUMP_PAGE_SIZE: u8 = 50;
+
+// Limites de amostras do diagnóstico de ruído
+pub const MIN_NOISE_SAMPLES: u16 = 2;
+pub const MAX_NOISE_SAMPLES: u16 = 1000;
+
+impl Command {
+    pub fn parse(line: &str) -> Self {
+        let mut parts = line.split_whitespace();
+        
+        let command = match (parts.next(), parts.next()) {
+            (Some("CALINFO"), None) => Command::CalInfo,
+            (Some("PAUSE"), None) => Command::Pause,
+            (Some("RESUME"), None) => Command::Resume,
+            (Some("MEM"), None) => Command::MemoryReport,
+            (Some("ZERO"), None) => Command::ZeroAirQuality,
+            (Some("REF"), Some("FIT")) => Command::ReferenceFit,
+            (Some("REF"), Some(channel)) => match parts.next() {
+                Some(value) => Self::parse_reference(channel, value),
+                None => Command::Invalid("argumentos insuficientes"),
+            },
+            (Some("INTERVAL"), Some(value)) => Self::parse_interval(value),
+            (Some("SET"), Some("THRESH")) => match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => Self::parse_threshold(name, value),
+                _ => Command::Invalid("argumentos insuficientes"),
+            },
+            (Some("SET"), Some("ID")) => match parts.next() {
+                Some(value) => match value.parse::<u8>() {
+                    Ok(id) if id <= MAX_DEVICE_ID => Command::SetDeviceId(id),
+                    Ok(_) => Command::Invalid("ID fora da faixa"),
+                    Err(_) => Command::Invalid("número inválido"),
+                },
+                None => Command::Invalid("argumentos insuficientes"),
+            },
+            (Some("NOISE"), Some(channel)) => match parts.next() {
+                Some(count) => Self::parse_noise(channel, count),
+                None => Command::Invalid("argumentos insuficientes"),
+            },
+            (Some("DUMP"), Some(page)) => match parts.next() {
+                Some(size) => Self::parse_dump(page, size),
+                None => Command::Invalid("argumentos insuficientes"),
+            },
+            (Some("INTERVAL"), None) | (Some("SET"), None) | (Some("NOISE"), None) | (Some("REF"), None) | (Some("DUMP"), None) => {
+                Command::Invalid("argumentos insuficientes")
+            }
+            _ => Command::Invalid("comando desconhecido"),
+        };
+        
+        // Rejeitar argumentos excedentes
+        if parts.next().is_some() {
+            return Command::Invalid("argumentos excedentes");
+        }
+        
+        command
+    }
+    
+    fn parse_interval(value: &str) -> Self {
+        let interval = match value.parse::<u32>() {
+            Ok(interval) => interval,
+            Err(_) => return Command::Invalid("número inválido"),
+        };
+        
+        if interval < MIN_READING_INTERVAL || interval > MAX_READING_INTERVAL {
+            return Command::Invalid("intervalo fora da faixa");
+        }
+        
+        Command::SetInterval(interval)
+    }
+    
+    fn parse_reference(channel: &str, value: &str) -> Self {
+        let sensor = match SensorType::from_name(channel) {
+            Some(sensor) => sensor,
+            None => return Command::Invalid("canal desconhecido"),
+        };
+        
+        match value.parse::<f32>() {
+            Ok(reference) if reference.is_finite() => Command::Reference(sensor, reference),
+            _ => Command::Invalid("número inválido"),
+        }
+    }
+    
+    fn parse_noise(channel: &str, count: &str) -> Self {
+        let sensor = match SensorType::from_name(channel) {
+            Some(sensor) => sensor,
+            None => return Command::Invalid("canal desconhecido"),
+        };
+        
+        let samples = match count.parse::<u16>() {
+            Ok(samples) => samples,
+            Err(_) => return Command::Invalid("número inválido"),
+        };
+        
+        if samples < MIN_NOISE_SAMPLES || samples > MAX_NOISE_SAMPLES {
+            return Command::Invalid("número de amostras fora da faixa");
+        }
+        
+        Command::Noise(sensor, samples)
+    }
+    
+    fn parse_dump(page: &str, size: &str) -> Self {
+        let (page, size) = match (page.parse::<u32>(), size.parse::<u8>()) {
+            (Ok(page), Ok(size)) => (page, size),
+            _ => return Command::Invalid("número inválido"),
+        };
+        
+        if size == 0 || size > MAX_DUMP_PAGE_SIZE {
+            return Command::Invalid("tamanho de página fora da faixa");
+        }
+        
+        Command::Dump(page, size)
+    }
+    
+    fn parse_threshold(name: &str, value: &str) -> Self {
+        let metric = match ThresholdMetric::from_name(name) {
+            Some(metric) => metric,
+            None => return Command::Invalid("métrica desconhecida"),
+        };
+        
+        let threshold = match value.parse::<f32>() {
+            Ok(threshold) if threshold.is_finite() => threshold,
+            _ => return Command::Invalid("número inválido"),
+        };
+        
+        let (min, max) = metric.valid_range();
+        if threshold < min || threshold > max {
+            return Command::Invalid("limite fora da faixa do sensor");
+        }
+        
+        Command::SetThreshold(metric, threshold)
+    }
+}
+
+// Métrica exibida localmente, alternada pelo botão
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+    Temperature,
+    Humidity,
+    AirQuality,
+    Pressure,
+}
+
+impl DisplayMode {
+    pub fn next(self) -> Self {
+        match self {
+            DisplayMode::Temperature => DisplayMode::Humidity,
+            DisplayMode::Humidity => DisplayMode::AirQuality,
+            DisplayMode::AirQuality => DisplayMode::Pressure,
+            DisplayMode::Pressure => DisplayMode::Temperature,
+        }
+    }
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            DisplayMode::Temperature => "TEMP",
+            DisplayMode::Humidity => "HUM",
+            DisplayMode::AirQuality => "AQ",
+            DisplayMode::Pressure => "PRES",
+        }
+    }
+    
+    pub fn value(&self, data: &EnvironmentalData) -> f32 {
+        match self {
+            DisplayMode::Temperature => data.temperature,
+            DisplayMode::Humidity => data.humidity,
+            DisplayMode::AirQuality => data.air_quality,
+            DisplayMode::Pressure => data.pressure,
+        }
+    }
+}
+
+// Botão com debounce por timestamp
+pub struct DebouncedButton {
+    debounce_ms: u32,
+    stable_state: bool,
+    last_raw: bool,
+    last_change: u32,
+}
+
+impl DebouncedButton {
+    pub fn new(debounce_ms: u32) -> Self {
+        Self {
+            debounce_ms,
+            stable_state: false,
+            last_raw: false,
+            last_change: 0,
+        }
+    }
+    
+    // Devolve true uma vez por pressionamento, após o sinal estabilizar
+    pub fn update(&mut self, pressed: bool, now: u32) -> bool {
+        if pressed != self.last_raw {
+            self.last_raw = pressed;
+            self.last_change = now;
+            return false;
+        }
+        
+        if pressed != self.stable_state && now.wrapping_sub(self.last_change) >= self.debounce_ms {
+            self.stable_state = pressed;
+            return pressed;
+        }
+        
+        false
+    }
+}
+
+// Formato de exposição do Prometheus (uma métrica por linha)
+pub const PROMETHEUS_METRICS: [&str; 4] = [
+    "env_temperature_celsius",
+    "env_humidity_percent",
+    "env_air_quality_ppm",
+    "env_pressure_kilopascals",
+];
+
+pub fn write_prometheus<W: Write>(out: &mut W, data: &EnvironmentalData) -> core::fmt::Result {
+    let values = [data.temperature, data.humidity, data.air_quality, data.pressure];
+    for (name, value) in PROMETHEUS_METRICS.iter().zip(values.iter()) {
+        writeln!(out, "{} {:.1}", name, value)?;
+    }
+    Ok(())
+}
+
+// Formato da telemetria enviada pela serial
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,       // "T:25.4C,H:60.0%,..." com floats formatados
+    FixedPoint, // "F:2537,6000,4000,10132,<timestamp>" com inteiros escalados
+    Base64,     // "B:<base64 de EnvironmentalData::to_bytes>" para canais só de texto
+    Binary,     // Pacote de 22 bytes (EnvironmentalData::to_binary), sem prefixo de ID
+}
+
+pub const BINARY_START_BYTE: u8 = 0xAA;
+pub const BINARY_PACKET_SIZE: usize = 22;
+
+// CRC-8 (polinômio 0x07, inicial 0x00): crc8(b"123456789") == 0xF4
+pub fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for &byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// Quando a serial emite: tudo (telemetria, heartbeat, checkpoints, alertas)
+// ou só mudanças de estado (alerta ativado/encerrado, status do sistema)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputPolicy {
+    Periodic,
+    OnChange,
+}
+
+// Escalas do formato de ponto fixo:
+// temperatura em centésimos de °C (2537 = 25.37°C)
+// umidade em centésimos de % (6000 = 60.00%)
+// qualidade do ar em décimos de ppm (4000 = 400.0 ppm)
+// pressão em centésimos de kPa (10132 = 101.32 kPa)
+pub const FIXED_POINT_SCALES: [f32; 4] = [100.0, 100.0, 10.0, 100.0];
+
+pub fn encode_fixed_point(data: &EnvironmentalData) -> [i32; 4] {
+    let values = [data.temperature, data.humidity, data.air_quality, data.pressure];
+    let mut encoded = [0i32; 4];
+    for i in 0..4 {
+        encoded[i] = (values[i] * FIXED_POINT_SCALES[i]).round() as i32;
+    }
+    encoded
+}
+
+pub fn decode_fixed_point(encoded: &[i32; 4], timestamp: u32) -> EnvironmentalData {
+    EnvironmentalData {
+        temperature: encoded[0] as f32 / FIXED_POINT_SCALES[0],
+        humidity: encoded[1] as f32 / FIXED_POINT_SCALES[1],
+        air_quality: encoded[2] as f32 / FIXED_POINT_SCALES[2],
+        pressure: encoded[3] as f32 / FIXED_POINT_SCALES[3],
+        timestamp,
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Base64 padrão (RFC 4648) com padding; None se não couber em N caracteres
+pub fn encode_base64<const N: usize>(input: &[u8]) -> Option<heapless::String<N>> {
+    let mut output = heapless::String::new();
+    
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let group = (b0 << 16) | (b1 << 8) | b2;
+        
+        for i in 0..4 {
+            // Um bloco de n bytes produz n + 1 caracteres; o resto é '='
+            let symbol = if i <= chunk.len() {
+                BASE64_ALPHABET[((group >> (18 - 6 * i)) & 0x3F) as usize] as char
+            } else {
+                '='
+            };
+            output.push(symbol).ok()?;
+        }
+    }
+    
+    Some(output)
+}
+
+fn base64_value(symbol: u8) -> Option<u32> {
+    match symbol {
+        b'A'..=b'Z' => Some((symbol - b'A') as u32),
+        b'a'..=b'z' => Some((symbol - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((symbol - b'0') as u32 + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+// Exige grupos completos de 4 caracteres e padding só no final
+pub fn decode_base64<const N: usize>(text: &str) -> Option<heapless::Vec<u8, N>> {
+    let bytes = text.as_bytes();
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    
+    let mut output = heapless::Vec::new();
+    let groups = bytes.len() / 4;
+    
+    for (index, chunk) in bytes.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&symbol| symbol == b'=').count();
+        if padding > 2 || (padding > 0 && index + 1 != groups) {
+            return None;
+        }
+        
+        let mut group = 0u32;
+        for &symbol in &chunk[..4 - padding] {
+            group = (group << 6) | base64_value(symbol)?;
+        }
+        group <<= 6 * padding as u32;
+        
+        let decoded = [(group >> 16) as u8, (group >> 8) as u8, group as u8];
+        output.extend_from_slice(&decoded[..3 - padding]).ok()?;
+    }
+    
+    Some(output)
+}
+
+// CRC-16/CCITT-FALSE (polinômio 0x1021, inicial 0xFFFF, sem reflexão):
+// crc16_ccitt(b"123456789") == 0x29B1
+pub fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// Confere uma linha de dados "<payload>*XXXX" recebida da serial; aceita o
+// "\r\n" final e o prefixo "@id:" de send_data (fora do CRC)
+pub fn verify_frame(frame: &[u8]) -> bool {
+    let mut frame = frame;
+    while let [rest @ .., b'\r' | b'\n'] = frame {
+        frame = rest;
+    }
+    if frame.first() == Some(&b'@') {
+        match frame.iter().position(|&byte| byte == b':') {
+            Some(colon) => frame = &frame[colon + 1..],
+            None => return false,
+        }
+    }
+    
+    let star = match frame.iter().rposition(|&byte| byte == b'*') {
+        Some(star) if frame.len() - star == 5 => star,
+        _ => return false,
+    };
+    let expected = core::str::from_utf8(&frame[star + 1..])
+        .ok()
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+    
+    expected == Some(crc16_ccitt(&frame[..star]))
+}
+
+// Tamanho em RAM (bytes) das principais estruturas, nos tipos usados no
+// hardware. No ATmega328P são 2048 bytes de SRAM no total
+pub fn memory_footprint() -> [(&'static str, usize); 9] {
+    use core::mem::size_of;
+    [
+        ("EnvironmentalData", size_of::<EnvironmentalData>()),
+        ("SystemConfig", size_of::<SystemConfig>()),
+        ("Alert", size_of::<Alert>()),
+        ("DataStorage", size_of::<DataStorage>()),
+        ("AlertSystem", size_of::<AlertSystem>()),
+        ("DailyProfile", size_of::<DailyProfile>()),
+        ("SensorManager", size_of::<SensorManager>()),
+        ("CommunicationSystem", size_of::<CommunicationSystem>()),
+        ("EnvironmentalMonitoringSystem", size_of::<EnvironmentalMonitoringSystem>()),
+    ]
+}
+
+// Cor do LED RGB (duty cycle de cada canal) para o estado e a severidade
+// azul = calibrando, magenta = pausado, vermelho = crítico/erro, amarelo = aviso, verde = normal
+pub fn rgb_for(status: &SystemStatus, highest_alert: Option<AlertLevel>) -> (u8, u8, u8) {
+    match (status, highest_alert) {
+        (SystemStatus::Calibrating, _) => (0, 0, 255),
+        (SystemStatus::Paused, _) => (255, 0, 255),
+        (SystemStatus::Error, _) => (255, 0, 0),
+        (_, Some(AlertLevel::Critical)) => (255, 0, 0),
+        (_, Some(AlertLevel::Warning)) => (255, 255, 0),
+        _ => (0, 255, 0),
+    }
+}
+
+// Causa do último reset, decodificada do registrador MCUSR do AVR
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetCause {
+    PowerOn,
+    External,
+    BrownOut,
+    Watchdog,
+    Unknown,
+}
+
+const MCUSR_PORF: u8 = 1 << 0;
+const MCUSR_EXTRF: u8 = 1 << 1;
+const MCUSR_BORF: u8 = 1 << 2;
+const MCUSR_WDRF: u8 = 1 << 3;
+
+impl ResetCause {
+    pub fn from_mcusr(mcusr: u8) -> Self {
+        if mcusr & MCUSR_PORF != 0 {
+            ResetCause::PowerOn
+        } else if mcusr & MCUSR_WDRF != 0 {
+            ResetCause::Watchdog
+        } else if mcusr & MCUSR_BORF != 0 {
+            ResetCause::BrownOut
+        } else if mcusr & MCUSR_EXTRF != 0 {
+            ResetCause::External
+        } else {
+            ResetCause::Unknown
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResetCause::PowerOn => "POWER_ON",
+            ResetCause::External => "EXTERNAL",
+            ResetCause::BrownOut => "BROWN_OUT",
+            ResetCause::Watchdog => "WATCHDOG",
+            ResetCause::Unknown => "UNKNOWN",
+        }
+    }
+    
+    // Watchdog e brown-out indicam travamento ou alimentação instável
+    pub fn is_unexpected(&self) -> bool {
+        matches!(self, ResetCause::Watchdog | ResetCause::BrownOut)
+    }
+}
+
+// Lê e limpa o MCUSR (os flags persistem entre resets se não forem limpos)
+#[cfg_attr(test, allow(dead_code))]
+fn read_reset_cause(cpu: &arduino_hal::pac::CPU) -> ResetCause {
+    let mcusr = cpu.mcusr.read().bits();
+    cpu.mcusr.write(|w| unsafe { w.bits(0) });
+    ResetCause::from_mcusr(mcusr)
+}
+
+// Horas de operação do sensor de gás (u32, persistido a cada hora)
+const EEPROM_SENSOR_HOURS_ADDR: u16 = 0x010;
+
+// ID do dispositivo no barramento (u8; 0xFF = EEPROM apagada, sem ID)
+const EEPROM_DEVICE_ID_ADDR: u16 = 0x014;
+pub const MAX_DEVICE_ID: u8 = 254;
+
+// Resets por watchdog/brown-out desde a gravação do firmware (u32)
+const EEPROM_UNEXPECTED_RESETS_ADDR: u16 = 0x018;
+
+// Região da EEPROM reservada ao log de alertas
+const EEPROM_ALERT_LOG_ADDR: u16 = 0x100;
+const EEPROM_ALERT_LOG_SLOTS: u16 = 32;
+const ALERT_RECORD_SIZE: u16 = 10;
+
+// Sistema de comunicação
+pub struct CommunicationSystem<T = SerialTransport, I = BoardIndicators, N = arduino_hal::Eeprom> {
+    transport: T,
+    indicators: I,
+    eeprom: N,
+    eeprom_log_index: u16,
+    rx_buffer: heapless::Vec<u8, 32>,
+    output_format: OutputFormat,
+    alert_format: AlertFormat,
+    reset_cause: ResetCause,
+    device_id: Option<u8>, // Prefixo "@id:" de cada linha enviada
+    at_line_start: bool,
+    tx_queue: Option<heapless::spsc::Queue<u8, TX_QUEUE_SIZE>>, // Saída enfileirada (pump_serial)
+    tx_dropped: u32, // Bytes de linhas descartadas com a fila cheia
+}
+
+// A fila spsc guarda até TX_QUEUE_SIZE - 1 bytes
+pub const TX_QUEUE_SIZE: usize = 128;
+// Maior prefixo de linha possível ("@254:")
+const LINE_PREFIX_MAX: usize = 5;
+
+#[cfg_attr(test, allow(dead_code))]
+impl CommunicationSystem {
+    pub fn new() -> Result<Self, SensorError> {
+        let dp = arduino_hal::Peripherals::take().map_err(|_| SensorError::CommunicationError)?;
+        let reset_cause = read_reset_cause(&dp.CPU);
+        let pins = arduino_hal::pins!(dp);
+        
+        let serial = arduino_hal::Usart::new(
+            dp.USART0,
+            pins.d0,
+            pins.d1.into_output(),
+            9600.into_baudrate(),
+        );
+        
+        let led_status = pins.d13.into_output();
+        let led_alert = pins.d12.into_output();
+        let buzzer = pins.d8.into_output();
+        
+        // LED RGB em pinos PWM (d9/d10 no Timer1, d11 no Timer2)
+        let timer1 = Timer1Pwm::new(dp.TC1, Prescaler::Prescale64);
+        let timer2 = Timer2Pwm::new(dp.TC2, Prescaler::Prescale64);
+        let mut rgb_red = pins.d9.into_output().into_pwm(&timer1);
+        let mut rgb_green = pins.d10.into_output().into_pwm(&timer1);
+        let mut rgb_blue = pins.d11.into_output().into_pwm(&timer2);
+        rgb_red.enable();
+        rgb_green.enable();
+        rgb_blue.enable();
+        let button = pins.d2.into_pull_up_input();
+        let eeprom = arduino_hal::Eeprom::new(dp.EEPROM);
+        
+        let indicators = BoardIndicators {
+            led_status,
+            led_alert,
+            buzzer,
+            rgb_red,
+            rgb_green,
+            rgb_blue,
+            button,
+        };
+        
+        Ok(CommunicationSystem::with_parts(SerialTransport { serial }, indicators, eeprom, reset_cause))
+    }
+}
+
+impl<T: DataTransport, I: StatusIndicator, N: NonVolatileStorage> CommunicationSystem<T, I, N> {
+    pub fn with_parts(transport: T, indicators: I, eeprom: N, reset_cause: ResetCause) -> Self {
+        let mut id = [0u8; 1];
+        let device_id = match eeprom.read(EEPROM_DEVICE_ID_ADDR, &mut id) {
+            Ok(()) if id[0] <= MAX_DEVICE_ID => Some(id[0]),
+            _ => None,
+        };
+        
+        Self {
+            transport,
+            indicators,
+            eeprom,
+            eeprom_log_index: 0,
+            rx_buffer: heapless::Vec::new(),
+            output_format: OutputFormat::Text,
+            alert_format: AlertFormat::Text,
+            reset_cause,
+            device_id,
+            at_line_start: true,
+            tx_queue: None,
+            tx_dropped: 0,
+        }
+    }
+    
+    // Com a fila ligada, send_* só enfileiram e retornam na hora; o loop
+    // principal esvazia a fila com pump_serial. Sem espaço para a linha
+    // inteira, a linha nova é descartada (drop-newest) e contada em
+    // tx_dropped: o que já está na fila sai intacto, nunca uma linha pela metade
+    pub fn set_tx_queue(&mut self, enabled: bool) {
+        if enabled && self.tx_queue.is_none() {
+            self.tx_queue = Some(heapless::spsc::Queue::new());
+        } else if !enabled {
+            // Bytes ainda na fila saem antes de voltar ao envio bloqueante
+            while self.tx_pending() > 0 {
+                if let Err(nb::Error::Other(_)) = self.pump_serial() {
+                    break;
+                }
+            }
+            self.tx_queue = None;
+        }
+    }
+    
+    // Envia no máximo um byte da fila sem bloquear; WouldBlock com a serial
+    // ocupada ou a fila vazia
+    pub fn pump_serial(&mut self) -> nb::Result<(), SensorError> {
+        let queue = match self.tx_queue.as_mut() {
+            Some(queue) => queue,
+            None => return Err(nb::Error::WouldBlock),
+        };
+        let byte = *queue.peek().ok_or(nb::Error::WouldBlock)?;
+        
+        self.transport.try_write_byte(byte)?;
+        queue.dequeue();
+        Ok(())
+    }
+    
+    pub fn tx_pending(&self) -> usize {
+        self.tx_queue.as_ref().map_or(0, |queue| queue.len())
+    }
+    
+    pub fn tx_dropped(&self) -> u32 {
+        self.tx_dropped
+    }
+    
+    pub fn device_id(&self) -> Option<u8> {
+        self.device_id
+    }
+    
+    pub fn set_device_id(&mut self, id: u8) -> Result<(), SensorError> {
+        if id > MAX_DEVICE_ID {
+            return Err(SensorError::ConfigError);
+        }
+        self.eeprom.write(EEPROM_DEVICE_ID_ADDR, &[id])?;
+        self.device_id = Some(id);
+        Ok(())
+    }
+    
+    pub fn reset_cause(&self) -> ResetCause {
+        self.reset_cause
+    }
+    
+    // Chamado uma vez no boot; devolve o total acumulado
+    pub fn count_unexpected_reset(&mut self) -> Result<u32, SensorError> {
+        let count = self.read_eeprom_u32(EEPROM_UNEXPECTED_RESETS_ADDR);
+        if !self.reset_cause.is_unexpected() {
+            return Ok(count);
+        }
+        
+        let count = count.saturating_add(1);
+        self.write_eeprom_u32(EEPROM_UNEXPECTED_RESETS_ADDR, count)?;
+        Ok(count)
+    }
+    
+    // Handshake de inicialização com a causa do último reset
+    pub fn send_boot(&mut self) -> Result<(), SensorError> {
+        let mut message: heapless::String<24> = heapless::String::new();
+        writeln!(message, "BOOT:{}", self.reset_cause.name()).map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+    
+    pub fn send_data(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
+        match self.output_format {
+            OutputFormat::FixedPoint => return self.send_data_fixed_point(data),
+            OutputFormat::Base64 => return self.send_data_base64(data),
+            OutputFormat::Binary => return self.send_data_binary(data),
+            OutputFormat::Text => {}
+        }
+        
+        let mut message: heapless::String<96> = heapless::String::new();
+        write!(
+            message,
+            "T:{:.1}C,H:{:.1}%,AQ:{:.1}ppm,P:{:.1}kPa,T:{}",
+            data.temperature,
+            data.humidity,
+            data.air_quality,
+            data.pressure,
+            data.timestamp
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        // Checksum do conteúdo da linha para o receptor validar (verify_frame)
+        let crc = crc16_ccitt(message.as_bytes());
+        writeln!(message, "*{:04X}", crc).map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_data_fixed_point(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
+        let [temperature, humidity, air_quality, pressure] = encode_fixed_point(data);
+        
+        let mut message: heapless::String<64> = heapless::String::new();
+        writeln!(
+            message,
+            "F:{},{},{},{},{}",
+            temperature, humidity, air_quality, pressure, data.timestamp
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Um frame binário por linha: "B:" + 28 caracteres base64 (20 bytes)
+    pub fn send_data_base64(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
+        let encoded: heapless::String<28> = encode_base64(&data.to_bytes())
+            .ok_or(SensorError::CommunicationError)?;
+        
+        let mut message: heapless::String<32> = heapless::String::new();
+        writeln!(message, "B:{}", encoded).map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // 22 bytes contra ~55 da linha de texto: menos da metade do tempo de
+    // barramento a 9600 baud. Bytes crus: 0x0A no payload não é fim de linha
+    pub fn send_data_binary(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
+        let packet = data.to_binary();
+        
+        if !self.has_room(packet.len()) {
+            self.count_dropped(packet.len());
+            return Ok(());
+        }
+        for byte in packet {
+            self.emit_byte(byte)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn set_alert_format(&mut self, format: AlertFormat) {
+        self.alert_format = format;
+    }
+    
+    pub fn send_alert(&mut self, alert: &Alert) -> Result<(), SensorError> {
+        if self.alert_format == AlertFormat::Code {
+            return self.send_alert_code(alert);
+        }
+        
+        let level_str = match alert.level {
+            AlertLevel::Info => "INFO",
+            AlertLevel::Warning => "WARNING",
+            AlertLevel::Critical => "CRITICAL",
+        };
+        
+        // Cabe a mensagem completa do alerta (ALERT_MESSAGE_LEN) mais o envelope
+        let mut message: heapless::String<128> = heapless::String::new();
+        writeln!(
+            message,
+            "ALERT[{}]: {} - Value: {:.1} at {}",
+            level_str, alert.message, alert.value, alert.timestamp
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    fn send_alert_code(&mut self, alert: &Alert) -> Result<(), SensorError> {
+        let mut message: heapless::String<40> = heapless::String::new();
+        writeln!(
+            message,
+            "AC:{},{},{:.1},{}",
+            alert.code as u8,
+            alert.level.severity(),
+            alert.value,
+            alert.timestamp
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Lê bytes disponíveis sem bloquear e devolve um comando por linha completa
+    pub fn poll_command(&mut self) -> Option<Command> {
+        while let Some(byte) = self.transport.read_byte() {
+            if byte == b'\n' || byte == b'\r' {
+                if self.rx_buffer.is_empty() {
+                    continue;
+                }
+                
+                let command = match core::str::from_utf8(&self.rx_buffer) {
+                    Ok(line) => Command::parse(line),
+                    Err(_) => Command::Invalid("caracteres inválidos"),
+                };
+                self.rx_buffer.clear();
+                return Some(command);
+            }
+            
+            if self.rx_buffer.push(byte).is_err() {
+                self.rx_buffer.clear();
+                return Some(Command::Invalid("linha muito longa"));
+            }
+        }
+        
+        None
+    }
+    
+    pub fn send_metrics_prometheus(&mut self, data: &EnvironmentalData) -> Result<(), SensorError> {
+        let mut message: heapless::String<128> = heapless::String::new();
+        write_prometheus(&mut message, data).map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_heartbeat(&mut self, status: &SystemStatus, uptime_ms: u32) -> Result<(), SensorError> {
+        let mut message: heapless::String<40> = heapless::String::new();
+        writeln!(message, "HB:{},UP:{}", status.name(), uptime_ms)
+            .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_status_change(&mut self, status: &SystemStatus) -> Result<(), SensorError> {
+        let mut message: heapless::String<24> = heapless::String::new();
+        writeln!(message, "STATUS:{}", status.name()).map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Transição de uma condição de alerta: "ALERT_ON:1,Qualidade do ar crítica"
+    pub fn send_alert_transition(&mut self, alert: &Alert, active: bool) -> Result<(), SensorError> {
+        let prefix = if active { "ALERT_ON" } else { "ALERT_OFF" };
+        let mut message: heapless::String<80> = heapless::String::new();
+        writeln!(message, "{}:{},{}", prefix, alert.code as u8, alert.message)
+            .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_dump_marker(&mut self, label: &str, page: u32, pages: Option<u32>) -> Result<(), SensorError> {
+        let mut message: heapless::String<32> = heapless::String::new();
+        match pages {
+            Some(pages) => writeln!(message, "{}:{},{}", label, page, pages),
+            None => writeln!(message, "{}:{}", label, page),
+        }
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Modo resumo da tempestade de alertas: uma linha por ciclo
+    pub fn send_alert_summary(&mut self, count: u16, timestamp: u32) -> Result<(), SensorError> {
+        let mut message: heapless::String<32> = heapless::String::new();
+        writeln!(message, "ALERT_STORM:{},{}", count, timestamp)
+            .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), SensorError> {
+        let mut message: heapless::String<48> = heapless::String::new();
+        writeln!(
+            message,
+            "HASH:{:08X},PREV:{:08X},T:{}",
+            checkpoint.hash, checkpoint.previous_hash, checkpoint.timestamp
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Uma linha por estrutura: "MEM:DataStorage,1234"
+    pub fn send_memory_footprint(&mut self, sizes: &[(&str, usize)]) -> Result<(), SensorError> {
+        for (name, size) in sizes {
+            let mut message: heapless::String<48> = heapless::String::new();
+            writeln!(message, "MEM:{},{}", name, size).map_err(|_| SensorError::CommunicationError)?;
+            self.write_str(&message)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn send_noise(&mut self, sensor: SensorType, samples: u16, stats: &NoiseStats) -> Result<(), SensorError> {
+        let mut message: heapless::String<80> = heapless::String::new();
+        writeln!(
+            message,
+            "NOISE:{},N:{},MIN:{},MAX:{},MEAN:{:.1},STD:{:.2}",
+            sensor.name(), samples, stats.min, stats.max, stats.mean, stats.std_dev
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_calibration_info(&mut self, info: &CalibrationInfo) -> Result<(), SensorError> {
+        let mut message: heapless::String<64> = heapless::String::new();
+        write!(
+            message,
+            "CAL:{},GAIN:{:.4},OFFSET:{:.3},LAST:",
+            info.sensor.name(), info.gain, info.offset
+        )
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        match info.last_calibration {
+            Some(timestamp) => writeln!(message, "{}", timestamp),
+            None => writeln!(message, "-"),
+        }
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_wiring_warning(&mut self, sensor: SensorType) -> Result<(), SensorError> {
+        let mut message: heapless::String<56> = heapless::String::new();
+        writeln!(message, "WARN: possível troca de cabos no canal {}", sensor.name())
+            .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn send_response(&mut self, result: Result<(), &str>) -> Result<(), SensorError> {
+        let mut message: heapless::String<64> = heapless::String::new();
+        match result {
+            Ok(()) => writeln!(message, "OK"),
+            Err(reason) => writeln!(message, "ERR {}", reason),
+        }
+        .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    // Cada linha é montada num buffer antes de chegar aqui: ou cabe inteira
+    // na fila (com o prefixo de cada linha) ou é descartada
+    fn write_str(&mut self, text: &str) -> Result<(), SensorError> {
+        let lines = text.bytes().filter(|&byte| byte == b'\n').count().max(1);
+        let prefix = if self.device_id.is_some() { LINE_PREFIX_MAX * lines } else { 0 };
+        if !self.has_room(text.len() + prefix) {
+            self.count_dropped(text.len());
+            return Ok(());
+        }
+        
+        for byte in text.bytes() {
+            self.write_byte(byte)?;
+        }
+        
+        Ok(())
+    }
+    
+    // Drop-newest: sem `needed` bytes livres, quem chama descarta a saída
+    // inteira em vez de bloquear esperando a serial. Sem fila sempre há espaço
+    fn has_room(&self, needed: usize) -> bool {
+        match self.tx_queue.as_ref() {
+            Some(queue) => queue.capacity() - queue.len() >= needed,
+            None => true,
+        }
+    }
+    
+    fn count_dropped(&mut self, bytes: usize) {
+        self.tx_dropped = self.tx_dropped.saturating_add(bytes as u32);
+    }
+    
+    // Toda saída passa por aqui: com vários monitores na mesma linha,
+    // cada linha começa com o ID de origem ("@7:T:21.5C,...")
+    fn write_byte(&mut self, byte: u8) -> Result<(), SensorError> {
+        if self.at_line_start {
+            if let Some(id) = self.device_id {
+                let mut prefix: heapless::String<6> = heapless::String::new();
+                write!(prefix, "@{}:", id).map_err(|_| SensorError::CommunicationError)?;
+                for prefix_byte in prefix.bytes() {
+                    self.emit_byte(prefix_byte)?;
+                }
+            }
+        }
+        
+        self.emit_byte(byte)?;
+        self.at_line_start = byte == b'\n';
+        Ok(())
+    }
+    
+    fn emit_byte(&mut self, byte: u8) -> Result<(), SensorError> {
+        match self.tx_queue.as_mut() {
+            Some(queue) => queue.enqueue(byte).map_err(|_| SensorError::CommunicationError),
+            None => self.transport.write_byte(byte),
+        }
+    }
+    
+    pub fn is_button_pressed(&self) -> bool {
+        self.indicators.is_button_pressed()
+    }
+    
+    pub fn send_display(&mut self, mode: DisplayMode, value: f32) -> Result<(), SensorError> {
+        let mut message: heapless::String<32> = heapless::String::new();
+        writeln!(message, "DISPLAY:{}={:.1}", mode.label(), value)
+            .map_err(|_| SensorError::CommunicationError)?;
+        
+        self.write_str(&message)
+    }
+    
+    pub fn set_rgb_status(&mut self, status: &SystemStatus, highest_alert: Option<AlertLevel>) {
+        let (red, green, blue) = rgb_for(status, highest_alert);
+        self.indicators.set_rgb(red, green, blue);
+    }
+    
+    pub fn set_buzzer(&mut self, on: bool) {
+        self.indicators.set_buzzer(on);
+    }
+    
+    // Registro circular de alertas na EEPROM (tipo, nível, valor, timestamp)
+    pub fn log_alert_eeprom(&mut self, alert: &Alert) -> Result<(), SensorError> {
+        let mut record = [0u8; ALERT_RECORD_SIZE as usize];
+        record[0] = alert.kind.log_code();
+        record[1] = alert.level.severity();
+        record[2..6].copy_from_slice(&alert.value.to_le_bytes());
+        record[6..10].copy_from_slice(&alert.timestamp.to_le_bytes());
+        
+        let address = EEPROM_ALERT_LOG_ADDR + self.eeprom_log_index * ALERT_RECORD_SIZE;
+        self.eeprom.write(address, &record)?;
+        self.eeprom_log_index = (self.eeprom_log_index + 1) % EEPROM_ALERT_LOG_SLOTS;
+        
+        Ok(())
+    }
+    
+    pub fn read_eeprom_u32(&self, address: u16) -> u32 {
+        let mut bytes = [0u8; 4];
+        if self.eeprom.read(address, &mut bytes).is_err() {
+            return 0;
+        }
+        
+        // EEPROM apagada lê 0xFF em todos os bytes
+        match u32::from_le_bytes(bytes) {
+            u32::MAX => 0,
+            value => value,
+        }
+    }
+    
+    pub fn write_eeprom_u32(&mut self, address: u16, value: u32) -> Result<(), SensorError> {
+        self.eeprom.write(address, &value.to_le_bytes())
+    }
+    
+    pub fn update_status_leds(&mut self, status: bool, alert: bool) {
+        self.indicators.set_status_led(status);
+        self.indicators.set_alert_led(alert);
+    }
+}
+
+// Flags por amostra armazenada
+pub const SAMPLE_MISSING: u8 = 0x01;      // Ciclo sem leitura válida
+pub const SAMPLE_INTERPOLATED: u8 = 0x02; // Valor preenchido por interpolação
+pub const SAMPLE_WITHHELD: u8 = 0x04;     // Não enviada de propósito (banda morta, cadência, OnChange)
+
+// Marcadores de evento por amostra, para localizar contextos no histórico
+pub const EVENT_ALERT_WARNING: u8 = 0x01;
+pub const EVENT_ALERT_CRITICAL: u8 = 0x02;
+pub const EVENT_USER: u8 = 0x80;
+
+fn adjust_sums(sums: &mut [f32; 4], data: &EnvironmentalData, sign: f32) {
+    sums[0] += sign * data.temperature;
+    sums[1] += sign * data.humidity;
+    sums[2] += sign * data.air_quality;
+    sums[3] += sign * data.pressure;
+}
+
+// Inconsistência interna do buffer circular (ver check_invariants)
+#[derive(Debug, PartialEq)]
+pub enum StorageError {
+    WriteIndexOutOfBounds(usize),
+    UnwrittenSlotInUse(usize),    // Slot após write_index com dados sem o buffer estar cheio
+    NonMonotonicTimestamp(usize), // Índice físico da amostra que volta no tempo
+    RunningSumsOutOfSync,
+    FullFlagMismatch,             // is_full incoerente com o número de gravações
+}
+
+// Sistema de armazenamento de dados
+pub struct DataStorage {
+    data_buffer: [EnvironmentalData; 50],
+    write_index: usize,
+    is_full: bool,
+    running_sums: [f32; 4], // Somas correntes de todo o buffer (média em O(1))
+    transmitted: [bool; 50], // Amostras já enviadas pela serial
+    sample_flags: [u8; 50],  // SAMPLE_MISSING / SAMPLE_INTERPOLATED / SAMPLE_WITHHELD
+    event_flags: [u8; 50],   // EVENT_* marcados após a gravação
+    written: u32,            // Slots gravados desde o boot (sequência da próxima amostra)
+}
+
+impl DataStorage {
+    pub fn new() -> Self {
+        Self {
+            data_buffer: [EnvironmentalData::ZEROED; 50],
+            write_index: 0,
+            is_full: false,
+            running_sums: [0.0; 4],
+            transmitted: [false; 50],
+            sample_flags: [0; 50],
+            event_flags: [0; 50],
+            written: 0,
+        }
+    }
+    
+    pub fn store_data(&mut self, data: EnvironmentalData) {
+        self.push_slot(data, 0);
+    }
+    
+    // Ciclo sem leitura válida: guarda um marcador com o último valor conhecido,
+    // a ser substituído por interpolação quando chegar a próxima leitura válida
+    pub fn store_missing(&mut self, timestamp: u32) {
+        let placeholder = match self.get_latest_data() {
+            Some(latest) => EnvironmentalData {
+                timestamp,
+                ..latest.clone()
+            },
+            None => return,
+        };
+        
+        let index = self.write_index;
+        self.push_slot(placeholder, SAMPLE_MISSING);
+        // Marcadores não são transmitidos
+        self.transmitted[index] = true;
+    }
+    
+    fn push_slot(&mut self, data: EnvironmentalData, flags: u8) {
+        // Buffer cheio: a amostra sobrescrita sai das somas
+        if self.is_full {
+            let old = self.data_buffer[self.write_index].clone();
+            adjust_sums(&mut self.running_sums, &old, -1.0);
+        }
+        adjust_sums(&mut self.running_sums, &data, 1.0);
+        
+        self.data_buffer[self.write_index] = data;
+        self.transmitted[self.write_index] = false;
+        self.sample_flags[self.write_index] = flags;
+        self.event_flags[self.write_index] = 0;
+        self.write_index = (self.write_index + 1) % 50;
+        self.written = self.written.wrapping_add(1);
+        
+        if self.write_index == 0 {
+            self.is_full = true;
+            self.resync_sums();
+        }
+    }
+    
+    // Recalcula as somas do zero a cada volta do buffer: somar e subtrair
+    // f32 indefinidamente acumularia erro de arredondamento sem limite
+    fn resync_sums(&mut self) {
+        let mut sums = [0.0; 4];
+        for data in self.iter() {
+            adjust_sums(&mut sums, data, 1.0);
+        }
+        self.running_sums = sums;
+    }
+    
+    pub fn sample_flags(&self, index: usize) -> u8 {
+        self.sample_flags[index % 50]
+    }
+    
+    // Marca a amostra mais recente; os marcadores acumulam até o slot ser sobrescrito
+    pub fn mark_event(&mut self, flags: u8) -> bool {
+        if self.write_index == 0 && !self.is_full {
+            return false;
+        }
+        
+        let index = if self.write_index == 0 { 49 } else { self.write_index - 1 };
+        self.event_flags[index] |= flags;
+        true
+    }
+    
+    pub fn event_flags(&self, index: usize) -> u8 {
+        self.event_flags[index % 50]
+    }
+    
+    // Índices físicos (ordem cronológica) das amostras com algum dos marcadores
+    pub fn find_events(&self, mask: u8) -> heapless::Vec<usize, 50> {
+        let mut found = heapless::Vec::new();
+        for index in self.slot_indices() {
+            if self.event_flags[index] & mask != 0 {
+                let _ = found.push(index);
+            }
+        }
+        found
+    }
+    
+    // Preenche lacunas entre duas leituras válidas por interpolação linear
+    // no tempo, marcando os valores como interpolados. Retorna quantos slots
+    pub fn interpolate_gaps(&mut self) -> usize {
+        let (start, count) = if self.is_full {
+            (self.write_index, 50)
+        } else {
+            (0, self.write_index)
+        };
+        
+        let mut filled = 0;
+        let mut last_valid: Option<usize> = None;
+        
+        for position in 0..count {
+            let index = (start + position) % 50;
+            if self.sample_flags[index] & SAMPLE_MISSING != 0 {
+                continue;
+            }
+            
+            if let Some(previous) = last_valid {
+                for gap in (previous + 1)..position {
+                    self.interpolate_slot(
+                        (start + previous) % 50,
+                        (start + gap) % 50,
+                        index,
+                    );
+                    filled += 1;
+                }
+            }
+            last_valid = Some(position);
+        }
+        
+        filled
+    }
+    
+    fn interpolate_slot(&mut self, before: usize, target: usize, after: usize) {
+        let a = self.data_buffer[before].clone();
+        let b = self.data_buffer[after].clone();
+        let old = self.data_buffer[target].clone();
+        
+        let span = b.timestamp.wrapping_sub(a.timestamp);
+        let fraction = if span == 0 {
+            0.5
+        } else {
+            old.timestamp.wrapping_sub(a.timestamp) as f32 / span as f32
+        };
+        let lerp = |x: f32, y: f32| x + (y - x) * fraction;
+        
+        let interpolated = EnvironmentalData {
+            temperature: lerp(a.temperature, b.temperature),
+            humidity: lerp(a.humidity, b.humidity),
+            air_quality: lerp(a.air_quality, b.air_quality),
+            pressure: lerp(a.pressure, b.pressure),
+            timestamp: old.timestamp,
+        };
+        
+        adjust_sums(&mut self.running_sums, &old, -1.0);
+        adjust_sums(&mut self.running_sums, &interpolated, 1.0);
+        self.data_buffer[target] = interpolated;
+        self.sample_flags[target] = (self.sample_flags[target] & !SAMPLE_MISSING) | SAMPLE_INTERPOLATED;
+    }
+    
+    pub fn get_latest_data(&self) -> Option<&EnvironmentalData> {
+        if self.write_index == 0 && !self.is_full {
+            return None;
+        }
+        
+        let index = if self.write_index == 0 { 49 } else { self.write_index - 1 };
+        Some(&self.data_buffer[index])
+    }
+    
+    pub fn oldest(&self) -> Option<&EnvironmentalData> {
+        if self.write_index == 0 && !self.is_full {
+            return None;
+        }
+        
+        // Cheio: o próximo slot a ser sobrescrito é o mais antigo
+        let index = if self.is_full { self.write_index } else { 0 };
+        Some(&self.data_buffer[index])
+    }
+    
+    // Regressão linear da métrica sobre a janela armazenada
+    // Devolve (inclinação por segundo, valor ajustado na amostra mais recente)
+    pub fn trend(&self, sensor: SensorType) -> Option<(f32, f32)> {
+        let origin = self.oldest()?.timestamp;
+        let latest = self.get_latest_data()?.timestamp.wrapping_sub(origin) as f32 / 1000.0;
+        let seconds = |data: &EnvironmentalData| data.timestamp.wrapping_sub(origin) as f32 / 1000.0;
+        
+        let mut count = 0.0;
+        let mut sum_t = 0.0;
+        let mut sum_v = 0.0;
+        for data in self.iter() {
+            count += 1.0;
+            sum_t += seconds(data);
+            sum_v += data.value(sensor);
+        }
+        if count < 2.0 {
+            return None;
+        }
+        
+        // Somas centradas na média para não perder precisão em f32
+        let mean_t = sum_t / count;
+        let mean_v = sum_v / count;
+        let mut covariance = 0.0;
+        let mut variance = 0.0;
+        for data in self.iter() {
+            let dt = seconds(data) - mean_t;
+            covariance += dt * (data.value(sensor) - mean_v);
+            variance += dt * dt;
+        }
+        if variance == 0.0 {
+            return None;
+        }
+        
+        let slope = covariance / variance;
+        Some((slope, mean_v + slope * (latest - mean_t)))
+    }
+    
+    // Tempo estimado (ms) até a métrica atingir o limite no ritmo atual
+    // None se a tendência se afasta do limite ou ele já foi ultrapassado
+    pub fn predict_threshold_crossing(&self, sensor: SensorType, threshold: f32) -> Option<u32> {
+        let (slope, current) = self.trend(sensor)?;
+        let seconds = (threshold - current) / slope;
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+        
+        Some((seconds * 1000.0) as u32)
+    }
+    
+    // Percorre as amostras armazenadas da mais antiga para a mais recente
+    pub fn iter(&self) -> impl Iterator<Item = &EnvironmentalData> {
+        self.slot_indices().map(move |index| &self.data_buffer[index])
+    }
+    
+    // Índices físicos dos slots preenchidos, em ordem cronológica
+    fn slot_indices(&self) -> impl Iterator<Item = usize> {
+        let (start, count) = if self.is_full {
+            (self.write_index, 50)
+        } else {
+            (0, self.write_index)
+        };
+        
+        (0..count).map(move |i| (start + i) % 50)
+    }
+    
+    // Média só das leituras reais (por SensorType::index()): marcadores de
+    // lacuna e valores interpolados repetiriam a referência que está sendo testada
+    pub fn measured_average(&self) -> Option<[f32; 4]> {
+        let mut sums = [0.0; 4];
+        let mut count = 0;
+        
+        for index in self.slot_indices() {
+            if self.sample_flags[index] & (SAMPLE_MISSING | SAMPLE_INTERPOLATED) != 0 {
+                continue;
+            }
+            for sensor in ALL_SENSORS {
+                sums[sensor.index()] += self.data_buffer[index].value(sensor);
+            }
+            count += 1;
+        }
+        
+        if count == 0 {
+            return None;
+        }
+        Some(sums.map(|sum| sum / count as f32))
+    }
+    
+    // Sequência de gravação da amostra mais antiga ainda no buffer
+    pub fn oldest_sequence(&self) -> u32 {
+        self.written.wrapping_sub(self.len() as u32)
+    }
+    
+    // Páginas contadas pela sequência de gravação (a n-ésima amostra desde o
+    // boot fica na página n / page_size), não pela posição no buffer: a mesma
+    // página traz as mesmas amostras mesmo com o buffer girando entre pedidos.
+    // Devolve as posições cronológicas (0 = mais antiga) ainda armazenadas;
+    // None se a página já foi toda sobrescrita ou ainda não começou
+    pub fn page_bounds(&self, page: u32, page_size: usize) -> Option<core::ops::Range<usize>> {
+        let size = page_size as u32;
+        if size == 0 {
+            return None;
+        }
+        let first = page.checked_mul(size)?;
+        let oldest = self.oldest_sequence();
+        let start = first.max(oldest);
+        let end = first.saturating_add(size).min(self.written);
+        if start >= end {
+            return None;
+        }
+        
+        Some((start - oldest) as usize..(end - oldest) as usize)
+    }
+    
+    // Páginas desde o boot, incluindo as já sobrescritas
+    pub fn page_count(&self, page_size: usize) -> u32 {
+        if page_size == 0 { 0 } else { self.written.div_ceil(page_size as u32) }
+    }
+    
+    // Amostra ainda não transmitida mais antiga (índice físico)
+    // As retidas de propósito não são reenviadas
+    pub fn first_pending(&self) -> Option<usize> {
+        self.slot_indices().find(|&index| self.is_pending(index))
+    }
+    
+    pub fn pending_count(&self) -> usize {
+        self.slot_indices().filter(|&index| self.is_pending(index)).count()
+    }
+    
+    fn is_pending(&self, index: usize) -> bool {
+        !self.transmitted[index] && self.sample_flags[index] & SAMPLE_WITHHELD == 0
+    }
+    
+    pub fn slot(&self, index: usize) -> &EnvironmentalData {
+        &self.data_buffer[index % 50]
+    }
+    
+    pub fn mark_transmitted(&mut self, index: usize) {
+        self.transmitted[index % 50] = true;
+    }
+    
+    pub fn mark_latest_transmitted(&mut self) {
+        let index = if self.write_index == 0 { 49 } else { self.write_index - 1 };
+        self.transmitted[index] = true;
+    }
+    
+    pub fn mark_latest_withheld(&mut self) {
+        let index = if self.write_index == 0 { 49 } else { self.write_index - 1 };
+        self.sample_flags[index] |= SAMPLE_WITHHELD;
+    }
+    
+    // Média de todas as amostras armazenadas sem varrer o buffer
+    // O timestamp é o ponto médio entre a amostra mais antiga e a mais recente
+    pub fn get_running_average(&self) -> Option<EnvironmentalData> {
+        let oldest = self.oldest()?;
+        let latest = self.get_latest_data()?;
+        let count = self.len() as f32;
+        
+        Some(EnvironmentalData {
+            temperature: self.running_sums[0] / count,
+            humidity: self.running_sums[1] / count,
+            air_quality: self.running_sums[2] / count,
+            pressure: self.running_sums[3] / count,
+            timestamp: oldest.timestamp.wrapping_add(latest.timestamp.wrapping_sub(oldest.timestamp) / 2),
+        })
+    }
+    
+    // Amostras válidas armazenadas (slots nunca gravados não contam)
+    pub fn len(&self) -> usize {
+        if self.is_full { 50 } else { self.write_index }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    // Média das últimas `count` amostras; `count` acima de len() é limitado
+    // às amostras gravadas em vez de incluir slots zerados
+    pub fn get_average_data(&self, count: usize) -> Option<EnvironmentalData> {
+        let (start_index, count) = self.window(count)?;
+        
+        let mut sum_temp = 0.0;
+        let mut sum_humidity = 0.0;
+        let mut sum_air_quality = 0.0;
+        let mut sum_pressure = 0.0;
+        
+        // Timestamps somados como deltas da primeira amostra (seguro na virada do millis)
+        let base_timestamp = self.data_buffer[start_index].timestamp;
+        let mut sum_timestamp_delta: u64 = 0;
+        
+        for i in 0..count {
+            let index = (start_index + i) % 50;
+            let data = &self.data_buffer[index];
+            
+            sum_temp += data.temperature;
+            sum_humidity += data.humidity;
+            sum_air_quality += data.air_quality;
+            sum_pressure += data.pressure;
+            sum_timestamp_delta += data.timestamp.wrapping_sub(base_timestamp) as u64;
+        }
+        
+        Some(EnvironmentalData {
+            temperature: sum_temp / count as f32,
+            humidity: sum_humidity / count as f32,
+            air_quality: sum_air_quality / count as f32,
+            pressure: sum_pressure / count as f32,
+            // Timestamp médio das amostras agregadas
+            timestamp: base_timestamp.wrapping_add((sum_timestamp_delta / count as u64) as u32),
+        })
+    }
+    
+    // Leituras por segundo nas últimas `count` amostras (None com menos de duas)
+    pub fn effective_sample_rate_hz(&self, count: usize) -> Option<f32> {
+        let available = self.len();
+        let count = count.min(available);
+        if count < 2 {
+            return None;
+        }
+        
+        let mut window = self.iter().skip(available - count);
+        let first = window.next()?.timestamp;
+        let last = window.last()?.timestamp;
+        let span_ms = last.wrapping_sub(first);
+        if span_ms == 0 {
+            return None;
+        }
+        
+        Some((count - 1) as f32 * 1000.0 / span_ms as f32)
+    }
+    
+    // Mínimos e máximos por campo nas últimas `count` amostras (picos do relatório);
+    // o timestamp é o da amostra mais recente da janela
+    pub fn get_min_data(&self, count: usize) -> Option<EnvironmentalData> {
+        self.extreme_data(count, f32::min)
+    }
+    
+    pub fn get_max_data(&self, count: usize) -> Option<EnvironmentalData> {
+        self.extreme_data(count, f32::max)
+    }
+    
+    fn extreme_data(&self, count: usize, pick: fn(f32, f32) -> f32) -> Option<EnvironmentalData> {
+        let (start_index, count) = self.window(count)?;
+        let mut result = self.data_buffer[start_index].clone();
+        
+        for i in 1..count {
+            let data = &self.data_buffer[(start_index + i) % 50];
+            result.temperature = pick(result.temperature, data.temperature);
+            result.humidity = pick(result.humidity, data.humidity);
+            result.air_quality = pick(result.air_quality, data.air_quality);
+            result.pressure = pick(result.pressure, data.pressure);
+            result.timestamp = data.timestamp;
+        }
+        
+        Some(result)
+    }
+    
+    // Início (índice físico) e tamanho da janela das últimas `count` amostras,
+    // limitada às amostras realmente gravadas
+    fn window(&self, count: usize) -> Option<(usize, usize)> {
+        let count = count.min(self.len());
+        if count == 0 {
+            return None;
+        }
+        
+        Some(((self.write_index + 50 - count) % 50, count))
+    }
+    
+    // Mediana de cada métrica nas últimas `count` amostras: um pico isolado
+    // não desloca o resultado, ao contrário da média
+    pub fn get_median_data(&self, count: usize) -> Option<EnvironmentalData> {
+        let (start, count) = self.window(count)?;
+        
+        // Um único rascunho de 200 bytes, reaproveitado métrica a métrica:
+        // o histórico fica intacto e a pilha não cresce com as métricas
+        let mut scratch = [0.0f32; 50];
+        let mut metric = |sensor: SensorType| {
+            for (position, value) in scratch[..count].iter_mut().enumerate() {
+                *value = self.data_buffer[(start + position) % 50].value(sensor);
+            }
+            median(&mut scratch[..count])
+        };
+        
+        Some(EnvironmentalData {
+            temperature: metric(SensorType::Temperature),
+            humidity: metric(SensorType::Humidity),
+            air_quality: metric(SensorType::AirQuality),
+            pressure: metric(SensorType::Pressure),
+            // A janela começa em `start` e segue em ordem cronológica
+            timestamp: self.data_buffer[(start + count / 2) % 50].timestamp,
+        })
+    }
+    
+    // Diagnóstico: confere índices, ordem temporal e somas correntes
+    pub fn check_invariants(&self) -> Result<(), StorageError> {
+        if self.write_index >= 50 {
+            return Err(StorageError::WriteIndexOutOfBounds(self.write_index));
+        }
+        
+        // Antes da primeira volta written == write_index; written só dá a volta
+        // depois de 2^32 gravações
+        let written_ok = if self.is_full {
+            self.written >= 50
+        } else {
+            self.written == self.write_index as u32
+        };
+        if !written_ok {
+            return Err(StorageError::FullFlagMismatch);
+        }
+        
+        // Sem o buffer cheio, os slots a partir de write_index nunca foram gravados
+        if !self.is_full {
+            for index in self.write_index..50 {
+                if self.data_buffer[index].timestamp != 0 || self.sample_flags[index] != 0 {
+                    return Err(StorageError::UnwrittenSlotInUse(index));
+                }
+            }
+        }
+        
+        // Diferença "negativa" em aritmética circular = amostra mais antiga
+        // que a anterior (a virada do millis continua válida)
+        let mut previous: Option<u32> = None;
+        for index in self.slot_indices() {
+            let timestamp = self.data_buffer[index].timestamp;
+            if matches!(previous, Some(last) if timestamp.wrapping_sub(last) > u32::MAX / 2) {
+                return Err(StorageError::NonMonotonicTimestamp(index));
+            }
+            previous = Some(timestamp);
+        }
+        
+        let mut sums = [0.0; 4];
+        for data in self.iter() {
+            adjust_sums(&mut sums, data, 1.0);
+        }
+        for (sum, running) in sums.iter().zip(self.running_sums.iter()) {
+            // Só o erro de f32 desde a última volta do buffer (resync_sums)
+            if (sum - running).abs() > 1e-4 * sum.abs().max(1.0) {
+                return Err(StorageError::RunningSumsOutOfSync);
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+// Mediana de um conjunto não vazio (reordena o slice); com tamanho par,
+// média dos dois valores centrais
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+// Reprocessa o histórico pelo sistema de alertas como se fosse ao vivo
+// Os alertas são entregues ao callback (sem lista em RAM) e contados.
+// Roda numa cópia zerada: o estado do sistema ao vivo não é tocado
+pub fn replay<F: FnMut(&Alert)>(
+    alert_system: &AlertSystem,
+    storage: &DataStorage,
+    mut on_alert: F,
+) -> usize {
+    let mut alert_system = alert_system.fresh();
+    let mut total = 0;
+    
+    for data in storage.iter() {
+        for alert in alert_system.check_alerts(data).iter() {
+            on_alert(alert);
+            total += 1;
+        }
+    }
+    
+    total
+}
+
+// Acumulador de médias por métrica
+#[derive(Debug, Clone, Copy)]
+pub struct MetricAccumulator {
+    sum_temperature: f32,
+    sum_humidity: f32,
+    sum_air_quality: f32,
+    sum_pressure: f32,
+    m2: [f32; 4], // Soma dos quadrados dos desvios (Welford), por SensorType::index()
+    count: u32,
+}
+
+impl MetricAccumulator {
+    pub const fn new() -> Self {
+        Self {
+            sum_temperature: 0.0,
+            sum_humidity: 0.0,
+            sum_air_quality: 0.0,
+            sum_pressure: 0.0,
+            m2: [0.0; 4],
+            count: 0,
+        }
+    }
+    
+    pub fn add(&mut self, data: &EnvironmentalData) {
+        let previous_means = self.means();
+        
+        self.sum_temperature += data.temperature;
+        self.sum_humidity += data.humidity;
+        self.sum_air_quality += data.air_quality;
+        self.sum_pressure += data.pressure;
+        self.count += 1;
+        
+        let means = self.means();
+        for (index, sensor) in ALL_SENSORS.iter().enumerate() {
+            let value = data.value(*sensor);
+            self.m2[index] += (value - previous_means[index]) * (value - means[index]);
+        }
+    }
+    
+    fn means(&self) -> [f32; 4] {
+        if self.count == 0 {
+            return [0.0; 4];
+        }
+        
+        let count = self.count as f32;
+        [
+            self.sum_temperature / count,
+            self.sum_humidity / count,
+            self.sum_air_quality / count,
+            self.sum_pressure / count,
+        ]
+    }
+    
+    // (média, desvio padrão) de uma métrica; None sem amostras
+    pub fn stats(&self, sensor: SensorType) -> Option<(f32, f32)> {
+        if self.count == 0 {
+            return None;
+        }
+        
+        let index = sensor.index();
+        Some((self.means()[index], (self.m2[index] / self.count as f32).sqrt()))
+    }
+    
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+    
+    pub fn average(&self) -> Option<EnvironmentalData> {
+        if self.count == 0 {
+            return None;
+        }
+        
+        let count = self.count as f32;
+        Some(EnvironmentalData {
+            temperature: self.sum_temperature / count,
+            humidity: self.sum_humidity / count,
+            air_quality: self.sum_air_quality / count,
+            pressure: self.sum_pressure / count,
+            timestamp: 0,
+        })
+    }
+}
+
+// Perfil diário: média de cada métrica por hora do dia (24 posições)
+pub struct DailyProfile {
+    buckets: [MetricAccumulator; 24],
+}
+
+impl DailyProfile {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [MetricAccumulator::new(); 24],
+        }
+    }
+    
+    pub fn update(&mut self, hour: u8, data: &EnvironmentalData) {
+        if let Some(bucket) = self.buckets.get_mut(hour as usize) {
+            bucket.add(data);
+        }
+    }
+    
+    pub fn hourly_average(&self, hour: u8) -> Option<EnvironmentalData> {
+        self.buckets.get(hour as usize)?.average()
+    }
+    
+    pub fn bucket(&self, hour: u8) -> Option<&MetricAccumulator> {
+        self.buckets.get(hour as usize)
+    }
+    
+    // Anomalia contextual: leitura fora de média ± sigma*desvio do histórico
+    // daquela hora, mesmo que esteja dentro dos limites absolutos
+    pub fn check_anomalies(&self, hour: u8, data: &EnvironmentalData, sigma: f32) -> heapless::Vec<Alert, 3> {
+        let mut alerts = heapless::Vec::new();
+        let bucket = match self.buckets.get(hour as usize) {
+            Some(bucket) if sigma > 0.0 && bucket.count() >= DIURNAL_MIN_SAMPLES => bucket,
+            _ => return alerts,
+        };
+        
+        let checks = [
+            (AlertKind::AirQuality, AlertCode::AirQualityUnusualForHour, SensorType::AirQuality, "Qualidade do ar atípica para o horário"),
+            (AlertKind::Temperature, AlertCode::TemperatureUnusualForHour, SensorType::Temperature, "Temperatura atípica para o horário"),
+            (AlertKind::Humidity, AlertCode::HumidityUnusualForHour, SensorType::Humidity, "Umidade atípica para o horário"),
+        ];
+        
+        for (kind, code, sensor, message) in checks {
+            let value = data.value(sensor);
+            let (mean, std_dev) = match bucket.stats(sensor) {
+                Some(stats) => stats,
+                None => continue,
+            };
+            // Hora sem variação registrada: não há como medir o desvio
+            if std_dev > f32::EPSILON && (value - mean).abs() > sigma * std_dev {
+                let _ = alerts.push(Alert::new(
+                    kind,
+                    code,
+                    AlertLevel::Info,
+                    message,
+                    value,
+                    data.timestamp,
+                ));
+            }
+        }
+        
+        alerts
+    }
+}
+
+// Amostras mínimas numa hora do perfil antes de julgar anomalias
+pub const DIURNAL_MIN_SAMPLES: u32 = 60;
+
+// Hora do dia a partir da hora de parede do RTC (segundos Unix, hora local)
+pub fn hour_of_day(unix_seconds: u32) -> u8 {
+    ((unix_seconds / 3_600) % 24) as u8
+}
+
+// Linha de base automática: média móvel exponencial com constante de 24h
+// Aprende o "normal" do ambiente (dia e noite) sem configuração manual
+pub const BASELINE_WINDOW_MS: u32 = 24 * 3_600_000;
+
+pub struct AutoBaseline {
+    window_ms: u32,
+    margin: f32,
+    baseline: Option<EnvironmentalData>,
+    last_timestamp: u32,
+}
+
+impl AutoBaseline {
+    pub fn new(window_ms: u32, margin: f32) -> Self {
+        Self {
+            window_ms: window_ms.max(1),
+            margin,
+            baseline: None,
+            last_timestamp: 0,
+        }
+    }
+    
+    pub fn update(&mut self, data: &EnvironmentalData) {
+        let elapsed = data.timestamp.wrapping_sub(self.last_timestamp);
+        self.last_timestamp = data.timestamp;
+        
+        match &mut self.baseline {
+            None => self.baseline = Some(data.clone()),
+            Some(baseline) => {
+                // Peso proporcional ao tempo decorrido desde a última amostra
+                let alpha = (elapsed as f32 / self.window_ms as f32).min(1.0);
+                baseline.temperature += alpha * (data.temperature - baseline.temperature);
+                baseline.humidity += alpha * (data.humidity - baseline.humidity);
+                baseline.air_quality += alpha * (data.air_quality - baseline.air_quality);
+                baseline.pressure += alpha * (data.pressure - baseline.pressure);
+                baseline.timestamp = data.timestamp;
+            }
+        }
+    }
+    
+    pub fn baseline(&self) -> Option<&EnvironmentalData> {
+        self.baseline.as_ref()
+    }
+    
+    // Limites relativos à linha de base atual
+    pub fn check(&self, data: &EnvironmentalData) -> heapless::Vec<Alert, 3> {
+        let mut alerts = heapless::Vec::new();
+        let baseline = match &self.baseline {
+            Some(baseline) => baseline,
+            None => return alerts,
+        };
+        
+        let checks = [
+            (AlertKind::AirQuality, AlertCode::AirQualityDeviation, data.air_quality, baseline.air_quality, "Qualidade do ar fora do normal"),
+            (AlertKind::Temperature, AlertCode::TemperatureDeviation, data.temperature, baseline.temperature, "Temperatura fora do normal"),
+            (AlertKind::Humidity, AlertCode::HumidityDeviation, data.humidity, baseline.humidity, "Umidade fora do normal"),
+        ];
+        
+        for (kind, code, value, reference, message) in checks {
+            if (value - reference).abs() > self.margin * reference.abs() {
+                let _ = alerts.push(Alert::new(
+                    kind,
+                    code,
+                    AlertLevel::Info,
+                    message,
+                    value,
+                    data.timestamp,
+                ));
+            }
+        }
+        
+        alerts
+    }
+}
+
+// Variação mínima, separada para subida e descida, para uma métrica ser reportada
+#[derive(Debug, Clone, Copy)]
+pub struct AsymmetricDeadband {
+    pub up: f32,
+    pub down: f32,
+}
+
+impl AsymmetricDeadband {
+    pub const NONE: Self = Self { up: 0.0, down: 0.0 };
+    
+    pub fn exceeded(&self, last: f32, value: f32) -> bool {
+        let delta = value - last;
+        if delta >= 0.0 {
+            delta >= self.up
+        } else {
+            -delta >= self.down
+        }
+    }
+}
+
+// Só deixa passar leituras com alguma métrica fora da sua banda morta
+// em relação ao último valor transmitido
+pub struct TransmitFilter {
+    deadbands: [AsymmetricDeadband; 4],
+    last_sent: Option<[f32; 4]>,
+}
+
+impl TransmitFilter {
+    pub fn new(deadbands: [AsymmetricDeadband; 4]) -> Self {
+        Self {
+            deadbands,
+            last_sent: None,
+        }
+    }
+    
+    pub fn should_send(&mut self, data: &EnvironmentalData) -> bool {
+        let values = [data.temperature, data.humidity, data.air_quality, data.pressure];
+        let changed = match &self.last_sent {
+            Some(last) => (0..4).any(|i| self.deadbands[i].exceeded(last[i], values[i])),
+            None => true,
+        };
+        
+        if changed {
+            self.last_sent = Some(values);
+        }
+        changed
+    }
+}
+
+// Intervalo de transmissão com backoff exponencial: enquanto nada muda
+// (filtro de banda morta) o intervalo dobra até o teto; qualquer mudança
+// volta o intervalo ao mínimo e sai assim que o mínimo tiver passado
+pub struct AdaptiveTxInterval {
+    min_ms: u32,
+    max_ms: u32,
+    current_ms: u32,
+    last_sent: Option<u32>,
+    change_pending: bool, // Mudança vista antes de vencer o mínimo
+}
+
+impl AdaptiveTxInterval {
+    pub fn new(min_ms: u32, max_ms: u32) -> Self {
+        Self {
+            min_ms,
+            max_ms,
+            current_ms: min_ms,
+            last_sent: None,
+            change_pending: false,
+        }
+    }
+    
+    pub fn set_min_ms(&mut self, min_ms: u32) {
+        self.min_ms = min_ms;
+        self.current_ms = self.current_ms.max(min_ms);
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.max_ms > self.min_ms
+    }
+    
+    pub fn interval_ms(&self) -> u32 {
+        self.current_ms
+    }
+    
+    pub fn reset(&mut self) {
+        self.current_ms = self.min_ms;
+    }
+    
+    fn elapsed(&self, now: u32, interval: u32) -> bool {
+        !matches!(self.last_sent, Some(last) if now.wrapping_sub(last) < interval)
+    }
+    
+    // Devolve true se a amostra deve ser transmitida agora
+    // O mínimo vale sempre, inclusive para mudanças
+    pub fn should_transmit(&mut self, now: u32, changed: bool) -> bool {
+        if changed {
+            self.reset();
+            self.change_pending = true;
+        }
+        
+        if self.change_pending {
+            if !self.elapsed(now, self.min_ms) {
+                return false;
+            }
+            self.change_pending = false;
+        } else if !self.elapsed(now, self.current_ms) {
+            return false;
+        } else if self.last_sent.is_some() {
+            self.current_ms = self.current_ms.saturating_mul(2).min(self.max_ms);
+        }
+        
+        self.last_sent = Some(now);
+        true
+    }
+}
+
+// Período de descarte de leituras após chaveamento de cargas (relé, ventilador),
+// quando a alimentação pode afundar e corromper as conversões do ADC
+pub struct ReadingBlackout {
+    start: u32,
+    duration_ms: u32,
+    discarded: u32,
+}
+
+impl ReadingBlackout {
+    pub fn new() -> Self {
+        Self {
+            start: 0,
+            duration_ms: 0,
+            discarded: 0,
+        }
+    }
+    
+    pub fn suppress_for(&mut self, now: u32, duration_ms: u32) {
+        self.start = now;
+        self.duration_ms = duration_ms;
+    }
+    
+    pub fn is_active(&self, now: u32) -> bool {
+        now.wrapping_sub(self.start) < self.duration_ms
+    }
+    
+    // Devolve true se a leitura deve ser descartada neste instante
+    pub fn should_discard(&mut self, now: u32) -> bool {
+        if self.is_active(now) {
+            self.discarded += 1;
+            return true;
+        }
+        false
+    }
+    
+    pub fn discarded_count(&self) -> u32 {
+        self.discarded
+    }
+    
+    pub fn restore_discarded_count(&mut self, discarded: u32) {
+        self.discarded = discarded;
+    }
+}
+
+// Tempo de operação acumulado do sensor de gás (vida útil limitada)
+pub struct SensorLifetime {
+    hours: u32,
+    pending_ms: u32,
+    lifetime_hours: u32,
+}
+
+impl SensorLifetime {
+    pub fn new(hours: u32, lifetime_hours: u32) -> Self {
+        Self {
+            hours,
+            pending_ms: 0,
+            lifetime_hours,
+        }
+    }
+    
+    // Devolve true quando uma hora nova foi completada (momento de persistir)
+    pub fn add_operating_time(&mut self, elapsed_ms: u32) -> bool {
+        self.pending_ms = self.pending_ms.saturating_add(elapsed_ms);
+        if self.pending_ms < 3_600_000 {
+            return false;
+        }
+        
+        self.hours += self.pending_ms / 3_600_000;
+        self.pending_ms %= 3_600_000;
+        true
+    }
+    
+    pub fn hours(&self) -> u32 {
+        self.hours
+    }
+    
+    pub fn is_expired(&self) -> bool {
+        self.hours >= self.lifetime_hours
+    }
+}
+
+// Deriva do oscilador interno: compara o avanço do millis() com um RTC
+pub struct ClockDriftEstimator {
+    reference: Option<(u32, u32)>, // (millis, segundos do RTC) da primeira amostra
+    latest: Option<(u32, u32)>,
+}
+
+impl ClockDriftEstimator {
+    pub fn new() -> Self {
+        Self {
+            reference: None,
+            latest: None,
+        }
+    }
+    
+    pub fn record(&mut self, millis: u32, rtc_seconds: u32) {
+        if self.reference.is_none() {
+            self.reference = Some((millis, rtc_seconds));
+        }
+        self.latest = Some((millis, rtc_seconds));
+    }
+    
+    // Erro em ppm: positivo quando o millis() adianta em relação ao RTC
+    pub fn estimate_ppm(&self) -> Option<f32> {
+        let (millis_start, rtc_start) = self.reference?;
+        let (millis_end, rtc_end) = self.latest?;
+        
+        let rtc_elapsed_ms = rtc_end.wrapping_sub(rtc_start) as u64 * 1000;
+        if rtc_elapsed_ms == 0 {
+            return None;
+        }
+        
+        let millis_elapsed = millis_end.wrapping_sub(millis_start) as u64;
+        let error = millis_elapsed as f32 - rtc_elapsed_ms as f32;
+        Some(error / rtc_elapsed_ms as f32 * 1_000_000.0)
+    }
+}
+
+// Hash encadeado (FNV-1a 32 bits) para verificação de integridade
+// Cada bloco inclui o hash do bloco anterior, como numa blockchain leve
+const FNV_OFFSET_BASIS: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+pub fn fnv1a_update(mut hash: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    pub hash: u32,
+    pub previous_hash: u32,
+    pub timestamp: u32,
+}
+
+pub struct RollingHasher {
+    block_size: u16,
+    samples_in_block: u16,
+    previous_hash: u32,
+    current: u32,
+}
+
+impl RollingHasher {
+    pub fn new(block_size: u16) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            samples_in_block: 0,
+            previous_hash: 0,
+            current: FNV_OFFSET_BASIS,
+        }
+    }
+    
+    // Devolve um checkpoint quando o bloco atual é fechado
+    pub fn add_sample(&mut self, data: &EnvironmentalData) -> Option<Checkpoint> {
+        if self.samples_in_block == 0 {
+            self.current = fnv1a_update(FNV_OFFSET_BASIS, &self.previous_hash.to_le_bytes());
+        }
+        
+        self.current = fnv1a_update(self.current, &data.to_bytes());
+        self.samples_in_block += 1;
+        
+        if self.samples_in_block < self.block_size {
+            return None;
+        }
+        
+        let checkpoint = Checkpoint {
+            hash: self.current,
+            previous_hash: self.previous_hash,
+            timestamp: data.timestamp,
+        };
+        self.previous_hash = self.current;
+        self.samples_in_block = 0;
+        Some(checkpoint)
+    }
+    
+    pub fn last_hash(&self) -> u32 {
+        self.previous_hash
+    }
+    
+    // Recalcula o hash de um bloco para conferir um log recebido
+    pub fn block_hash(previous_hash: u32, samples: &[EnvironmentalData]) -> u32 {
+        let mut hash = fnv1a_update(FNV_OFFSET_BASIS, &previous_hash.to_le_bytes());
+        for sample in samples {
+            hash = fnv1a_update(hash, &sample.to_bytes());
+        }
+        hash
+    }
+}
+
+// Snapshot binário do estado (config + última leitura + contadores)
+// Layout: "MA" | versão | endianness | tamanho do payload (u16) | payload | FNV-1a (u32)
+// Todos os campos em little-endian. Versão 2: limites de todas as métricas,
+// histerese de alerta e fatores de calibração (a versão 1 só tinha AQ_MAX)
+pub const STATE_MAGIC: [u8; 2] = *b"MA";
+pub const STATE_VERSION: u8 = 2;
+const STATE_LITTLE_ENDIAN: u8 = 0x01;
+const STATE_HEADER_SIZE: usize = 6;
+const STATE_PAYLOAD_SIZE: usize = 133;
+pub const STATE_BLOB_SIZE: usize = STATE_HEADER_SIZE + STATE_PAYLOAD_SIZE + 4;
+
+#[derive(Debug, PartialEq)]
+pub enum StateError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnsupportedEndianness,
+    ChecksumMismatch,
+    InvalidValue(&'static str), // Blob íntegro, mas com configuração recusada
+}
+
+#[derive(Debug, Clone)]
+pub struct StateSnapshot {
+    pub reading_interval: u32,
+    pub thresholds: ThresholdConfig,
+    pub gains: [f32; 4],
+    pub offsets: [f32; 4],
+    pub calibration_factors: [f32; 4],
+    pub alert_hysteresis: [f32; 4],
+    pub latest: Option<EnvironmentalData>,
+    pub cycle_timeouts: u32,
+    pub discarded_readings: u32,
+    pub sensor_hours: u32,
+}
+
+impl StateSnapshot {
+    pub fn to_bytes(&self) -> [u8; STATE_BLOB_SIZE] {
+        let mut blob = [0u8; STATE_BLOB_SIZE];
+        blob[0..2].copy_from_slice(&STATE_MAGIC);
+        blob[2] = STATE_VERSION;
+        blob[3] = STATE_LITTLE_ENDIAN;
+        blob[4..6].copy_from_slice(&(STATE_PAYLOAD_SIZE as u16).to_le_bytes());
+        
+        let payload = &mut blob[STATE_HEADER_SIZE..STATE_HEADER_SIZE + STATE_PAYLOAD_SIZE];
+        payload[0..4].copy_from_slice(&self.reading_interval.to_le_bytes());
+        // Limites na ordem de ThresholdMetric::ALL
+        for (i, metric) in ThresholdMetric::ALL.iter().enumerate() {
+            payload[4 + i * 4..8 + i * 4].copy_from_slice(&self.thresholds.get(*metric).to_le_bytes());
+        }
+        for i in 0..4 {
+            payload[32 + i * 4..36 + i * 4].copy_from_slice(&self.gains[i].to_le_bytes());
+            payload[48 + i * 4..52 + i * 4].copy_from_slice(&self.offsets[i].to_le_bytes());
+            payload[64 + i * 4..68 + i * 4].copy_from_slice(&self.calibration_factors[i].to_le_bytes());
+            payload[80 + i * 4..84 + i * 4].copy_from_slice(&self.alert_hysteresis[i].to_le_bytes());
+        }
+        if let Some(latest) = &self.latest {
+            payload[96] = 1;
+            payload[97..117].copy_from_slice(&latest.to_bytes());
+        }
+        payload[117..121].copy_from_slice(&self.cycle_timeouts.to_le_bytes());
+        payload[121..125].copy_from_slice(&self.discarded_readings.to_le_bytes());
+        payload[125..129].copy_from_slice(&self.sensor_hours.to_le_bytes());
+        // payload[129..133] reservado para contadores futuros
+        
+        let checksum = fnv1a_update(FNV_OFFSET_BASIS, &blob[..STATE_BLOB_SIZE - 4]);
+        blob[STATE_BLOB_SIZE - 4..].copy_from_slice(&checksum.to_le_bytes());
+        blob
+    }
+    
+    pub fn from_bytes(blob: &[u8]) -> Result<Self, StateError> {
+        if blob.len() < STATE_HEADER_SIZE {
+            return Err(StateError::Truncated);
+        }
+        if blob[0..2] != STATE_MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        if blob[2] != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(blob[2]));
+        }
+        if blob[3] != STATE_LITTLE_ENDIAN {
+            return Err(StateError::UnsupportedEndianness);
+        }
+        if blob.len() < STATE_BLOB_SIZE || u16::from_le_bytes([blob[4], blob[5]]) as usize != STATE_PAYLOAD_SIZE {
+            return Err(StateError::Truncated);
+        }
+        
+        let (content, checksum) = blob[..STATE_BLOB_SIZE].split_at(STATE_BLOB_SIZE - 4);
+        let stored = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+        if fnv1a_update(FNV_OFFSET_BASIS, content) != stored {
+            return Err(StateError::ChecksumMismatch);
+        }
+        
+        let payload = &blob[STATE_HEADER_SIZE..STATE_HEADER_SIZE + STATE_PAYLOAD_SIZE];
+        let u32_at = |i: usize| u32::from_le_bytes([payload[i], payload[i + 1], payload[i + 2], payload[i + 3]]);
+        let f32_at = |i: usize| f32::from_bits(u32_at(i));
+        
+        let latest = if payload[96] == 1 {
+            let mut bytes = [0u8; 20];
+            bytes.copy_from_slice(&payload[97..117]);
+            Some(EnvironmentalData::from_bytes(&bytes))
+        } else {
+            None
+        };
+        
+        let mut thresholds = ThresholdConfig::default();
+        for (i, metric) in ThresholdMetric::ALL.iter().enumerate() {
+            thresholds.set(*metric, f32_at(4 + i * 4));
+        }
+        let f32x4_at = |i: usize| [f32_at(i), f32_at(i + 4), f32_at(i + 8), f32_at(i + 12)];
+        
+        Ok(Self {
+            reading_interval: u32_at(0),
+            thresholds,
+            gains: f32x4_at(32),
+            offsets: f32x4_at(48),
+            calibration_factors: f32x4_at(64),
+            alert_hysteresis: f32x4_at(80),
+            latest,
+            cycle_timeouts: u32_at(117),
+            discarded_readings: u32_at(121),
+            sensor_hours: u32_at(125),
+        })
+    }
+}
+
+// Reenvios por ciclo, para não estourar o tempo do ciclo após uma queda longa
+pub const MAX_RESEND_PER_CYCLE: usize = 5;
+
+// Sistema principal de monitoramento
+pub struct EnvironmentalMonitoringSystem<
+    S = AdcSensorSource,
+    T = SerialTransport,
+    I = BoardIndicators,
+    N = arduino_hal::Eeprom,
+    C = HalClock,
+    H = NoopHandler,
+> {
+    sensor_manager: SensorManager<S>,
+    alert_system: AlertSystem,
+    alert_throttle: AlertThrottle,
+    alert_storm: AlertStormDetector,
+    alert_routing: AlertRouting,
+    auto_baseline: AutoBaseline,
+    air_quality_auto_zero: AutoZero,
+    communication: CommunicationSystem<T, I, N>,
+    clock: C,
+    data_storage: DataStorage,
+    daily_profile: DailyProfile,
+    hasher: RollingHasher,
+    transmit_filter: TransmitFilter,
+    output_policy: OutputPolicy,
+    device_health: Option<DeviceHealth>,
+    assisted_calibration: AssistedCalibration,
+    clock_drift: ClockDriftEstimator,
+    blackout: ReadingBlackout,
+    error_handler: H,
+    deferred_error: Option<SensorError>, // Falha de envio no meio do ciclo: o ciclo segue e o erro é devolvido no fim
+    consecutive_outliers: u8,
+    rejected_outliers: u32,
+    sensor_health: SensorHealthTracker,
+    saturation_alerted: bool, // Alerta de temperatura saturada já emitido neste episódio
+    unexpected_resets: u32, // Lido da EEPROM no boot (já inclui o reset atual)
+    particulates: Option<(pms5003::ParticulateMatter, u32)>, // Última medição do PMS5003 e quando chegou
+    particulate_reader: pms5003::FrameReader,
+    gas_sensor_life: SensorLifetime,
+    last_lifetime_tick: u32,
+    boot_time: u32,
+    warmed_up: core::cell::Cell<[bool; 4]>, // Aquecimento concluído fica registrado
+    button: DebouncedButton,
+    display_mode: DisplayMode,
+    last_reading_time: u32,
+    last_transmit_time: Option<u32>,
+    adaptive_tx: AdaptiveTxInterval,
+    heartbeat_interval: u32,
+    cycle_budget: u32,
+    cycle_timeouts: u32,
+    last_cycle_timeout: Option<u32>,
+    system_status: SystemStatus,
+    uptime: UptimeTracker,
+}
+
+#[derive(Debug)]
+pub enum SystemStatus {
+    Running,
+    Calibrating,
+    Error,
+    Paused,
+}
+
+impl SystemStatus {
+    pub fn index(&self) -> usize {
+        match self {
+            SystemStatus::Running => 0,
+            SystemStatus::Calibrating => 1,
+            SystemStatus::Error => 2,
+            SystemStatus::Paused => 3,
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemStatus::Running => "RUNNING",
+            SystemStatus::Calibrating => "CALIBRATING",
+            SystemStatus::Error => "ERROR",
+            SystemStatus::Paused => "PAUSED",
+        }
+    }
+}
+
+// Tempo acumulado em cada estado desde o boot (disponibilidade/SLA)
+pub struct UptimeTracker {
+    durations: [u64; 4],
+    current: usize,
+    since: u32,
+}
+
+impl UptimeTracker {
+    pub fn new(status: &SystemStatus, now: u32) -> Self {
+        Self {
+            durations: [0; 4],
+            current: status.index(),
+            since: now,
+        }
+    }
+    
+    pub fn transition(&mut self, status: &SystemStatus, now: u32) {
+        self.durations[self.current] += now.wrapping_sub(self.since) as u64;
+        self.current = status.index();
+        self.since = now;
+    }
+    
+    pub fn time_in(&self, status: &SystemStatus, now: u32) -> u64 {
+        let mut duration = self.durations[status.index()];
+        if status.index() == self.current {
+            duration += now.wrapping_sub(self.since) as u64;
+        }
+        duration
+    }
+    
+    pub fn availability_percent(&self, now: u32) -> f32 {
+        let total: u64 = self.durations.iter().sum::<u64>() + now.wrapping_sub(self.since) as u64;
+        if total == 0 {
+            return 100.0;
+        }
+        
+        self.time_in(&SystemStatus::Running, now) as f32 / total as f32 * 100.0
+    }
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl EnvironmentalMonitoringSystem {
+    pub fn new() -> Result<Self, SensorError> {
+        Self::with_config(SystemConfig::default())
+    }
+    
+    pub fn with_config(config: SystemConfig) -> Result<Self, SensorError> {
+        Self::with_handler(config, NoopHandler)
+    }
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl<H: ErrorHandler> EnvironmentalMonitoringSystem<AdcSensorSource, SerialTransport, BoardIndicators, arduino_hal::Eeprom, HalClock, H> {
+    pub fn with_handler(config: SystemConfig, error_handler: H) -> Result<Self, SensorError> {
+        let sensor_manager = SensorManager::with_config(config.clone())?;
+        let communication = CommunicationSystem::new()?;
+        Self::from_parts_with_handler(sensor_manager, communication, HalClock, config, error_handler)
+    }
+}
+
+// Limites ajustados para que só o pico do roteiro DEMO_STEPS alerte
+pub fn demo_config() -> SystemConfig {
+    SystemConfig {
+        thresholds: ThresholdConfig {
+            air_quality_max: 1000.0,
+            ..ThresholdConfig::default()
+        },
+        warmup_ms: [0, 0, DEMO_STEPS[0].duration_ms, 0],
+        ..SystemConfig::default()
+    }
+}
+
+#[cfg_attr(test, allow(dead_code))]
+impl EnvironmentalMonitoringSystem<DemoSensorSource<HalClock>> {
+    // Sem sensores: roteiro DEMO_STEPS no lugar das leituras
+    pub fn demo() -> Result<Self, SensorError> {
+        let config = demo_config();
+        let source = DemoSensorSource::new(DemoScript::new(&DEMO_STEPS), HalClock);
+        let sensor_manager = SensorManager::with_source(source, config.clone());
+        let communication = CommunicationSystem::new()?;
+        Self::from_parts(sensor_manager, communication, HalClock, config)
+    }
+}
+
+impl<S, T, I, N, C> EnvironmentalMonitoringSystem<S, T, I, N, C>
+where
+    S: SensorSource,
+    T: DataTransport,
+    I: StatusIndicator,
+    N: NonVolatileStorage,
+    C: Clock,
+{
+    // Monta o sistema sobre quaisquer implementações dos traits de hardware
+    pub fn from_parts(
+        sensor_manager: SensorManager<S>,
+        communication: CommunicationSystem<T, I, N>,
+        clock: C,
+        config: SystemConfig,
+    ) -> Result<Self, SensorError> {
+        Self::from_parts_with_handler(sensor_manager, communication, clock, config, NoopHandler)
+    }
+}
+
+impl<S, T, I, N, C, H> EnvironmentalMonitoringSystem<S, T, I, N, C, H>
+where
+    S: SensorSource,
+    T: DataTransport,
+    I: StatusIndicator,
+    N: NonVolatileStorage,
+    C: Clock,
+    H: ErrorHandler,
+{
+    pub fn from_parts_with_handler(
+        sensor_manager: SensorManager<S>,
+        mut communication: CommunicationSystem<T, I, N>,
+        clock: C,
+        config: SystemConfig,
+        error_handler: H,
+    ) -> Result<Self, SensorError> {
+        config.validate().map_err(|_| SensorError::ConfigError)?;
+        let alert_throttle = AlertThrottle::new(config.alert_max_per_window, config.alert_window_ms);
+        let mut alert_system = AlertSystem::new(config.clone());
+        if config.alert_hysteresis.iter().any(|band| *band > 0.0) {
+            alert_system = alert_system.with_hysteresis(config.alert_hysteresis);
+        }
+        communication.send_boot()?;
+        let unexpected_resets = communication.count_unexpected_reset()?;
+        let gas_sensor_hours = communication.read_eeprom_u32(EEPROM_SENSOR_HOURS_ADDR);
+        let data_storage = DataStorage::new();
+        let now = clock.now_ms();
+        
+        Ok(Self {
+            sensor_manager,
+            alert_system,
+            alert_throttle,
+            alert_storm: AlertStormDetector::new(config.alert_storm_threshold, config.alert_storm_cycles),
+            alert_routing: AlertRouting::default(),
+            auto_baseline: AutoBaseline::new(BASELINE_WINDOW_MS, config.baseline_margin),
+            // MQ-135 em ar limpo: ~400 ppm de CO2
+            air_quality_auto_zero: AutoZero::new(400.0, 5.0),
+            communication,
+            clock,
+            data_storage,
+            daily_profile: DailyProfile::new(),
+            hasher: RollingHasher::new(config.hash_block_size),
+            transmit_filter: TransmitFilter::new(config.deadbands),
+            output_policy: OutputPolicy::Periodic,
+            device_health: None,
+            assisted_calibration: AssistedCalibration::new(),
+            clock_drift: ClockDriftEstimator::new(),
+            blackout: ReadingBlackout::new(),
+            error_handler,
+            deferred_error: None,
+            consecutive_outliers: 0,
+            rejected_outliers: 0,
+            sensor_health: SensorHealthTracker::new(),
+            saturation_alerted: false,
+            unexpected_resets,
+            particulates: None,
+            particulate_reader: pms5003::FrameReader::new(),
+            gas_sensor_life: SensorLifetime::new(gas_sensor_hours, config.gas_sensor_lifetime_hours),
+            last_lifetime_tick: now,
+            boot_time: now,
+            warmed_up: core::cell::Cell::new([false; 4]),
+            button: DebouncedButton::new(50),
+            display_mode: DisplayMode::Temperature,
+            last_reading_time: 0,
+            last_transmit_time: None,
+            adaptive_tx: AdaptiveTxInterval::new(
+                config.transmit_interval_ms.max(config.reading_interval),
+                config.transmit_backoff_max_ms,
+            ),
+            heartbeat_interval: config.heartbeat_interval_ms,
+            cycle_budget: config.cycle_budget_ms,
+            cycle_timeouts: 0,
+            last_cycle_timeout: None,
+            system_status: SystemStatus::Running,
+            uptime: UptimeTracker::new(&SystemStatus::Running, now),
+        })
+    }
+    
+    pub fn run_monitoring_cycle(&mut self) -> Result<(), SensorError> {
+        let result = self.monitoring_cycle();
+        // O erro do próprio ciclo tem precedência sobre um envio que falhou
+        let result = match self.deferred_error.take() {
+            Some(err) if result.is_ok() => Err(err),
+            _ => result,
+        };
+        
+        if let Err(err) = &result {
+            if self.error_handler.on_error(err, &self.system_status) == ErrorAction::Recalibrate {
+                let _ = self.calibrate_all_sensors();
+            }
+        }
+        
+        result
+    }
+    
+    // Guarda o primeiro erro de envio do ciclo sem interrompê-lo: uma serial
+    // fora do ar não pode pular alertas, LEDs nem o registro da leitura
+    fn defer<V>(&mut self, result: Result<V, SensorError>) -> Option<V> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.deferred_error.get_or_insert(err);
+                None
+            }
+        }
+    }
+    
+    fn monitoring_cycle(&mut self) -> Result<(), SensorError> {
+        self.process_commands()?;
+        
+        let current_time = self.clock.now_ms();
+        
+        // Botão alterna a métrica exibida
+        let pressed = self.communication.is_button_pressed();
+        if self.button.update(pressed, current_time) {
+            self.display_mode = self.display_mode.next();
+            if let Some(value) = self.displayed_value() {
+                let shown = self.communication.send_display(self.display_mode, value);
+                self.defer(shown);
+            }
+        }
+        
+        let periodic = self.output_policy == OutputPolicy::Periodic;
+        self.poll_particulates(current_time);
+        
+        // Em manutenção: só comandos (e o heartbeat, tarefa à parte), sem leituras nem alertas
+        if matches!(self.system_status, SystemStatus::Paused) {
+            return Ok(());
+        }
+        
+        // Vida útil do sensor de gás, persistida a cada hora completa
+        let elapsed = current_time.wrapping_sub(self.last_lifetime_tick);
+        self.last_lifetime_tick = current_time;
+        if self.gas_sensor_life.add_operating_time(elapsed) {
+            self.communication.write_eeprom_u32(EEPROM_SENSOR_HOURS_ADDR, self.gas_sensor_life.hours())?;
+            
+            if self.gas_sensor_life.is_expired() {
+                let replace = [Alert::new(
+                    AlertKind::Device,
+                    AlertCode::GasSensorExpired,
+                    AlertLevel::Warning,
+                    "Substituir sensor de gás",
+                    self.gas_sensor_life.hours() as f32,
+                    current_time,
+                )];
+                self.emit_alerts(&replace, current_time);
+            }
+        }
+        
+        // Verificar se é hora de fazer nova leitura. O millis() volta a zero
+        // a cada ~49,7 dias: a subtração circular ainda dá o tempo decorrido
+        // (ex.: última leitura em u32::MAX - 999, agora 4000 -> 5000 ms)
+        if current_time.wrapping_sub(self.last_reading_time) >= self.sensor_manager.config.reading_interval {
+            // Durante o blackout a leitura é adiada até o fim do período
+            if self.blackout.should_discard(current_time) {
+                return Ok(());
+            }
+            
+            // A saúde dos canais é atualizada só depois de a amostra (ou a
+            // lacuna) estar no buffer: o alerta marca o evento nela, não na anterior
+            let reading = self.sensor_manager.read_all_sensors(&self.clock);
+            
+            match reading {
+                Ok(data) => {
+                    // Leitura travou além do orçamento: descartar e seguir
+                    if self.cycle_overrun(current_time) {
+                        self.update_sensor_health(current_time);
+                        self.last_reading_time = current_time;
+                        return Ok(());
+                    }
+                    
+                    // Plausibilidade relativa à média recente do próprio sensor.
+                    // Depois de MAX_CONSECUTIVE_OUTLIERS o novo patamar é aceito
+                    // até uma leitura voltar à tolerância e rearmar o filtro
+                    if !self.is_outlier(&data) {
+                        self.consecutive_outliers = 0;
+                    } else if self.consecutive_outliers < MAX_CONSECUTIVE_OUTLIERS {
+                        self.consecutive_outliers += 1;
+                        self.rejected_outliers += 1;
+                        self.data_storage.store_missing(data.timestamp);
+                        self.update_sensor_health(current_time);
+                        self.last_reading_time = current_time;
+                        return Ok(());
+                    }
+                    
+                    // Armazenar dados
+                    self.data_storage.store_data(data.clone());
+                    self.data_storage.interpolate_gaps();
+                    self.update_sensor_health(current_time);
+                    
+                    // Comparar com o perfil da hora antes de incorporar a leitura.
+                    // Só com hora real: sem RTC (ou com ele falhando) a contagem
+                    // desde o boot poluiria os buckets com horas erradas
+                    let mut diurnal_alerts = heapless::Vec::new();
+                    if let Some(seconds) = self.clock.unix_seconds() {
+                        let hour = hour_of_day(seconds);
+                        let diurnal_sigma = self.sensor_manager.config.diurnal_sigma;
+                        diurnal_alerts = self.daily_profile.check_anomalies(hour, &data, diurnal_sigma);
+                        self.daily_profile.update(hour, &data);
+                    }
+                    
+                    // Enviar dados (a amostra fica pendente se a transmissão falhar);
+                    // variações dentro da banda morta são armazenadas mas não enviadas.
+                    // Com transmit_interval_ms a leitura segue rápida e só a amostra
+                    // mais recente sai na cadência de transmissão. Com backoff, o
+                    // ambiente estável sai em intervalos crescentes até o teto
+                    if periodic && self.should_transmit(&data, current_time) {
+                        let sent = self.communication.send_data(&data);
+                        if self.defer(sent).is_some() {
+                            self.last_transmit_time = Some(current_time);
+                            self.data_storage.mark_latest_transmitted();
+                        }
+                    } else {
+                        self.data_storage.mark_latest_withheld();
+                    }
+                    
+                    // Emitir checkpoint de integridade ao fechar um bloco, depois da amostra
+                    // que o fecha ter saído
+                    if let Some(checkpoint) = self.hasher.add_sample(&data) {
+                        if periodic {
+                            let sent = self.communication.send_checkpoint(&checkpoint);
+                            self.defer(sent);
+                        }
+                    }
+                    
+                    // Link funcionando: reenviar amostras que ficaram pendentes
+                    if periodic && self.deferred_error.is_none() {
+                        let resent = self.resend_pending(MAX_RESEND_PER_CYCLE);
+                        self.defer(resent);
+                    }
+                    
+                    // Verificar alertas
+                    // Métricas ainda em aquecimento não geram alertas
+                    // Sensores mortos (ou ainda em recuperação) também não
+                    let mut ready = self.warmup_status(current_time);
+                    for (index, ready) in ready.iter_mut().enumerate() {
+                        *ready &= self.sensor_health.is_alive(index);
+                    }
+                    let is_ready = |alert: &Alert| alert.kind.sensor().map_or(true, |sensor| ready[sensor.index()]);
+                    
+                    let mut previously_active = self.alert_system.active_alerts();
+                    previously_active.retain(is_ready);
+                    let mut alerts = self.alert_system.check_alerts(&data);
+                    alerts.retain(is_ready);
+                    let mut active_sinks = self.emit_alerts(&alerts, current_time);
+                    
+                    if !periodic {
+                        let mut now_active = self.alert_system.active_alerts();
+                        now_active.retain(is_ready);
+                        let sent = self.send_alert_transitions(&previously_active, &now_active);
+                        self.defer(sent);
+                    }
+                    
+                    // Comparar com a linha de base antes de incorporar a leitura
+                    let mut baseline_alerts = self.auto_baseline.check(&data);
+                    baseline_alerts.retain(is_ready);
+                    active_sinks |= self.emit_alerts(&baseline_alerts, current_time);
+                    if ready.iter().all(|ready| *ready) {
+                        self.auto_baseline.update(&data);
+                    }
+                    
+                    diurnal_alerts.retain(is_ready);
+                    active_sinks |= self.emit_alerts(&diurnal_alerts, current_time);
+                    
+                    // Pré-alerta: tendência atual atinge o limite dentro do horizonte
+                    let horizon = self.sensor_manager.config.prealert_horizon_ms;
+                    let limit = self.alert_system.active_profile().air_quality_max;
+                    if horizon > 0 && ready[SensorType::AirQuality.index()] && data.air_quality <= limit {
+                        let crossing = self.data_storage.predict_threshold_crossing(SensorType::AirQuality, limit);
+                        if matches!(crossing, Some(eta) if eta <= horizon) {
+                            let prealert = [Alert::new(
+                                AlertKind::AirQuality,
+                                AlertCode::AirQualityRising,
+                                AlertLevel::Info,
+                                "Qualidade do ar tende a ultrapassar o limite",
+                                data.air_quality,
+                                data.timestamp,
+                            )];
+                            active_sinks |= self.emit_alerts(&prealert, current_time);
+                        }
+                    }
+                    
+                    // Saúde do próprio dispositivo
+                    let health = self.sensor_manager.read_device_health();
+                    self.device_health = Some(health);
+                    if self.sensor_manager.is_vref_out_of_range(health.supply_voltage) {
+                        let supply = [Alert::new(
+                            AlertKind::Device,
+                            AlertCode::SupplyOutOfRange,
+                            AlertLevel::Warning,
+                            "Referência do ADC fora do esperado",
+                            health.supply_voltage,
+                            data.timestamp,
+                        )];
+                        active_sinks |= self.emit_alerts(&supply, current_time);
+                    }
+                    if health.mcu_temperature > self.sensor_manager.config.mcu_temp_max {
+                        let overheat = [Alert::new(
+                            AlertKind::Device,
+                            AlertCode::McuOverheat,
+                            AlertLevel::Critical,
+                            "MCU superaquecido",
+                            health.mcu_temperature,
+                            data.timestamp,
+                        )];
+                        active_sinks |= self.emit_alerts(&overheat, current_time);
+                    }
+                    
+                    // Auto-zero do sensor de gás (comando ZERO), já aquecido e estável
+                    if ready[SensorType::AirQuality.index()] {
+                        let uncalibrated = self.sensor_manager.uncalibrated(SensorType::AirQuality, data.air_quality);
+                        if let Some(mean) = self.air_quality_auto_zero.update(uncalibrated) {
+                            let reference = self.air_quality_auto_zero.reference();
+                            let new_offset = self.sensor_manager.zero_offset(SensorType::AirQuality, mean, reference);
+                            self.sensor_manager.set_offset(SensorType::AirQuality, new_offset, current_time);
+                        }
+                    }
+                    
+                    // Atualizar LEDs de status e buzzer
+                    self.communication.update_status_leds(true, active_sinks & ALERT_SINK_LED != 0);
+                    self.communication.set_buzzer(active_sinks & ALERT_SINK_BUZZER != 0);
+                    
+                    let highest_alert = alerts.iter().map(|alert| alert.level).max();
+                    self.communication.set_rgb_status(&self.system_status, highest_alert);
+                    
+                    if let Some(count) = self.alert_storm.end_cycle() {
+                        let sent = self.communication.send_alert_summary(count, current_time);
+                        self.defer(sent);
+                    }
+                    
+                    self.last_reading_time = current_time;
+                }
+                Err(e) => {
+                    // Marcar a lacuna para interpolação posterior
+                    self.data_storage.store_missing(current_time);
+                    self.update_sensor_health(current_time);
+                    self.last_reading_time = current_time;
+                    self.set_status(SystemStatus::Error);
+                    return Err(e);
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Reenvia as amostras pendentes mais antigas, no máximo `max` por chamada
+    pub fn resend_pending(&mut self, max: usize) -> Result<usize, SensorError> {
+        let mut sent = 0;
+        
+        while sent < max {
+            let index = match self.data_storage.first_pending() {
+                Some(index) => index,
+                None => break,
+            };
+            
+            let data = self.data_storage.slot(index).clone();
+            self.communication.send_data(&data)?;
+            self.data_storage.mark_transmitted(index);
+            sent += 1;
+        }
+        
+        Ok(sent)
+    }
+    
+    // Registra um evento de timeout se o ciclo estourou o orçamento
+    fn cycle_overrun(&mut self, cycle_start: u32) -> bool {
+        let now = self.clock.now_ms();
+        if now.wrapping_sub(cycle_start) <= self.cycle_budget {
+            return false;
+        }
+        
+        self.cycle_timeouts += 1;
+        self.last_cycle_timeout = Some(now);
+        true
+    }
+    
+    pub fn predict_threshold_crossing(&self, sensor: SensorType, threshold: f32) -> Option<u32> {
+        self.data_storage.predict_threshold_crossing(sensor, threshold)
+    }
+    
+    pub fn export_state(&self) -> [u8; STATE_BLOB_SIZE] {
+        let manager = &self.sensor_manager;
+        StateSnapshot {
+            reading_interval: manager.config.reading_interval,
+            thresholds: self.alert_system.thresholds(),
+            gains: ALL_SENSORS.map(|sensor| manager.gain(sensor)),
+            offsets: ALL_SENSORS.map(|sensor| manager.offset(sensor)),
+            calibration_factors: manager.calibration_factors(),
+            alert_hysteresis: manager.config.alert_hysteresis,
+            latest: self.data_storage.get_latest_data().cloned(),
+            cycle_timeouts: self.cycle_timeouts,
+            discarded_readings: self.blackout.discarded_count(),
+            sensor_hours: self.gas_sensor_life.hours(),
+        }
+        .to_bytes()
+    }
+    
+    // Restaura um snapshot de export_state (ex.: após reboot ou migração)
+    pub fn restore_state(&mut self, blob: &[u8]) -> Result<(), StateError> {
+        let snapshot = StateSnapshot::from_bytes(blob)?;
+        let now = self.clock.now_ms();
+        
+        // Valida tudo antes de aplicar: um snapshot recusado não muda nada
+        let mut config = self.sensor_manager.config.clone();
+        config.thresholds = snapshot.thresholds;
+        config.calibration_factor = snapshot.calibration_factors;
+        config.alert_hysteresis = snapshot.alert_hysteresis;
+        config.validate().map_err(StateError::InvalidValue)?;
+        if snapshot.gains.iter().chain(snapshot.offsets.iter()).any(|value| !value.is_finite())
+            || snapshot.gains.contains(&0.0)
+        {
+            return Err(StateError::InvalidValue("ganho ou offset inválido"));
+        }
+        
+        self.sensor_manager.config.reading_interval = snapshot
+            .reading_interval
+            .clamp(MIN_READING_INTERVAL, MAX_READING_INTERVAL);
+        self.sync_transmit_interval();
+        self.sensor_manager.config.thresholds = snapshot.thresholds;
+        self.sensor_manager.config.calibration_factor = snapshot.calibration_factors;
+        self.sensor_manager.config.alert_hysteresis = snapshot.alert_hysteresis;
+        self.alert_system
+            .set_thresholds(snapshot.thresholds)
+            .map_err(StateError::InvalidValue)?;
+        self.alert_system.set_hysteresis(snapshot.alert_hysteresis);
+        for sensor in ALL_SENSORS {
+            let i = sensor.index();
+            self.sensor_manager.set_linear_calibration(sensor, snapshot.gains[i], snapshot.offsets[i], now);
+        }
+        // snapshot.latest fica só para diagnóstico: gravá-la no buffer quebraria
+        // a ordem dos timestamps (millis recomeça no boot) e a reenviaria
+        self.cycle_timeouts = snapshot.cycle_timeouts;
+        self.blackout.restore_discarded_count(snapshot.discarded_readings);
+        self.gas_sensor_life = SensorLifetime::new(
+            snapshot.sensor_hours,
+            self.sensor_manager.config.gas_sensor_lifetime_hours,
+        );
+        
+        Ok(())
+    }
+    
+    pub fn get_cycle_timeout_count(&self) -> u32 {
+        self.cycle_timeouts
+    }
+    
+    pub fn get_last_cycle_timeout(&self) -> Option<u32> {
+        self.last_cycle_timeout
+    }
+    
+    // Encaminha cada alerta aos destinos da sua severidade
+    // LED e buzzer refletem a condição ativa, mesmo com o envio limitado
+    fn emit_alerts(&mut self, alerts: &[Alert], now: u32) -> u8 {
+        let mut active_sinks = 0;
+        
+        for alert in alerts {
+            let sinks = self.alert_routing.sinks_for(&alert.level);
+            active_sinks |= sinks;
+            
+            // Marcar no histórico mesmo quando o envio é limitado
+            match alert.level {
+                AlertLevel::Critical => { self.data_storage.mark_event(EVENT_ALERT_CRITICAL); }
+                AlertLevel::Warning => { self.data_storage.mark_event(EVENT_ALERT_WARNING); }
+                AlertLevel::Info => {}
+            }
+            
+            self.alert_storm.note_alert();
+            if !self.alert_throttle.allow(alert.kind, now) {
+                continue;
+            }
+            
+            // No modo OnChange a serial só recebe as transições de estado;
+            // no modo resumo, só a contagem do fim do ciclo
+            let serial = sinks & ALERT_SINK_SERIAL != 0 && !self.alert_storm.is_active();
+            if serial && self.output_policy == OutputPolicy::Periodic {
+                let sent = self.communication.send_alert(alert);
+                self.defer(sent);
+            }
+            if sinks & ALERT_SINK_EEPROM != 0 {
+                let logged = self.communication.log_alert_eeprom(alert);
+                self.defer(logged);
+            }
+        }
+        
+        active_sinks
+    }
+    
+    fn send_alert_transitions(&mut self, before: &[Alert], after: &[Alert]) -> Result<(), SensorError> {
+        for alert in after.iter().filter(|alert| !before.iter().any(|old| old.code == alert.code)) {
+            self.communication.send_alert_transition(alert, true)?;
+        }
+        for alert in before.iter().filter(|alert| !after.iter().any(|new| new.code == alert.code)) {
+            self.communication.send_alert_transition(alert, false)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn set_output_policy(&mut self, policy: OutputPolicy) {
+        self.output_policy = policy;
+    }
+    
+    pub fn set_tx_queue(&mut self, enabled: bool) {
+        self.communication.set_tx_queue(enabled);
+    }
+    
+    // Chamado a cada volta do loop principal com a fila de saída ligada
+    pub fn pump_serial(&mut self) -> nb::Result<(), SensorError> {
+        self.communication.pump_serial()
+    }
+    
+    // Sinal de vida independente do ciclo de leitura (também em pausa),
+    // disparado pelo agendador a cada heartbeat_interval_ms()
+    pub fn send_heartbeat(&mut self) -> Result<(), SensorError> {
+        if self.output_policy != OutputPolicy::Periodic {
+            return Ok(());
+        }
+        let now = self.clock.now_ms();
+        self.communication.send_heartbeat(&self.system_status, now)
+    }
+    
+    pub fn heartbeat_interval_ms(&self) -> u32 {
+        self.heartbeat_interval
+    }
+    
+    pub fn tx_pending(&self) -> usize {
+        self.communication.tx_pending()
+    }
+    
+    pub fn tx_dropped(&self) -> u32 {
+        self.communication.tx_dropped()
+    }
+    
+    // Quanto falta (ms) para a próxima leitura; 0 se já venceu. A diferença
+    // circular segue correta quando o millis() dá a volta (now < última leitura)
+    pub fn time_until_next_reading(&self, now: u32) -> u32 {
+        let elapsed = now.wrapping_sub(self.last_reading_time);
+        self.sensor_manager.config.reading_interval.saturating_sub(elapsed)
+    }
+    
+    pub fn set_alert_routing(&mut self, routing: AlertRouting) {
+        self.alert_routing = routing;
+    }
+    
+    fn process_commands(&mut self) -> Result<(), SensorError> {
+        while let Some(command) = self.communication.poll_command() {
+            let result = self.apply_command(command);
+            self.communication.send_response(result)?;
+        }
+        
+        Ok(())
+    }
+    
+    // Só aplica valores já validados pelo parser
+    pub fn apply_command(&mut self, command: Command) -> Result<(), &'static str> {
+        match command {
+            Command::SetInterval(interval) => {
+                self.sensor_manager.config.reading_interval = interval;
+                self.sync_transmit_interval();
+                Ok(())
+            }
+            Command::SetThreshold(metric, threshold) => self.alert_system.set_threshold(metric, threshold),
+            Command::CalInfo => {
+                for sensor in ALL_SENSORS {
+                    let info = self.sensor_manager.calibration_info(sensor);
+                    self.communication
+                        .send_calibration_info(&info)
+                        .map_err(|_| "falha de comunicação")?;
+                }
+                Ok(())
+            }
+            Command::Reference(sensor, reference) => {
+                let latest = self.data_storage.get_latest_data().ok_or("sem leituras")?;
+                let measured = self.sensor_manager.uncalibrated(sensor, latest.value(sensor));
+                self.assisted_calibration.add_pair(sensor, measured, reference);
+                Ok(())
+            }
+            Command::ReferenceFit => {
+                let sensor = self.assisted_calibration.sensor().ok_or("sem pares de referência")?;
+                let (gain, offset) = self.assisted_calibration.solve().ok_or("pares insuficientes")?;
+                self.sensor_manager.set_linear_calibration(sensor, gain, offset, self.clock.now_ms());
+                self.assisted_calibration = AssistedCalibration::new();
+                Ok(())
+            }
+            Command::Noise(sensor, samples) => {
+                let stats = self.sensor_manager
+                    .measure_noise(sensor, samples)
+                    .ok_or("sem amostras")?;
+                self.communication
+                    .send_noise(sensor, samples, &stats)
+                    .map_err(|_| "falha de comunicação")
+            }
+            Command::Pause => {
+                self.pause();
+                Ok(())
+            }
+            Command::Resume => {
+                if !matches!(self.system_status, SystemStatus::Paused) {
+                    return Err("sistema não está pausado");
+                }
+                self.resume();
+                Ok(())
+            }
+            Command::SetDeviceId(id) => self.communication
+                .set_device_id(id)
+                .map_err(|_| "falha ao gravar EEPROM"),
+            Command::Dump(page, size) => self.dump_page(page, size as usize),
+            Command::MemoryReport => self.memory_footprint_report().map_err(|_| "falha de comunicação"),
+            Command::ZeroAirQuality => {
+                self.air_quality_auto_zero.arm();
+                Ok(())
+            }
+            Command::Invalid(reason) => Err(reason),
+        }
+    }
+    
+    // Uma página do histórico, da amostra mais antiga para a mais recente,
+    // entre "DUMP:página,total" e "DUMP_END:página". O gateway confirma
+    // pedindo a próxima página (ou repete a mesma se algo se perdeu); a
+    // numeração segue a sequência de gravação, então não muda entre pedidos
+    pub fn dump_page(&mut self, page: u32, page_size: usize) -> Result<(), &'static str> {
+        let range = self.data_storage
+            .page_bounds(page, page_size)
+            .ok_or("página fora do histórico")?;
+        let pages = self.data_storage.page_count(page_size);
+        
+        self.communication
+            .send_dump_marker("DUMP", page, Some(pages))
+            .map_err(|_| "falha de comunicação")?;
+        for data in self.data_storage.iter().skip(range.start).take(range.len()) {
+            self.communication.send_data(data).map_err(|_| "falha de comunicação")?;
+        }
+        self.communication
+            .send_dump_marker("DUMP_END", page, None)
+            .map_err(|_| "falha de comunicação")
+    }
+    
+    // Uma linha por estrutura; com a fila cheia, send_* esperam abrir espaço
+    pub fn memory_footprint_report(&mut self) -> Result<(), SensorError> {
+        self.communication.send_memory_footprint(&memory_footprint())
+    }
+    
+    pub fn check_wiring(&mut self) -> Result<usize, SensorError> {
+        let suspicious = self.sensor_manager.detect_miswiring();
+        for sensor in suspicious.iter() {
+            self.communication.send_wiring_warning(*sensor)?;
+        }
+        
+        Ok(suspicious.len())
+    }
+    
+    pub fn calibrate_humidity_two_point(&mut self, raw_33: u16, raw_75: u16) -> Result<(f32, f32), SensorError> {
+        let now = self.clock.now_ms();
+        self.sensor_manager.calibrate_humidity_two_point(raw_33, raw_75, now)
+    }
+    
+    pub fn calibrate_sensor(
+        &mut self,
+        sensor: SensorType,
+        known_low: f32,
+        raw_low: u16,
+        known_high: f32,
+        raw_high: u16,
+    ) -> Result<(f32, f32), SensorError> {
+        let now = self.clock.now_ms();
+        self.sensor_manager.calibrate_sensor(sensor, known_low, raw_low, known_high, raw_high, now)
+    }
+    
+    pub fn calibrate_all_sensors(&mut self) -> Result<(), SensorError> {
+        self.set_status(SystemStatus::Calibrating);
+        
+        // Sem referências externas não há reta nova: ganhos, offsets e fatores
+        // de calibração ficam como estão (calibrate_sensor exige os dois pontos)
+        
+        self.set_status(SystemStatus::Running);
+        Ok(())
+    }
+    
+    pub fn get_reset_cause(&self) -> ResetCause {
+        self.communication.reset_cause()
+    }
+    
+    pub fn pause(&mut self) {
+        self.set_status(SystemStatus::Paused);
+    }
+    
+    pub fn resume(&mut self) {
+        if matches!(self.system_status, SystemStatus::Paused) {
+            self.set_status(SystemStatus::Running);
+        }
+    }
+    
+    pub fn get_system_status(&self) -> &SystemStatus {
+        &self.system_status
+    }
+    
+    fn set_status(&mut self, status: SystemStatus) {
+        let changed = status.index() != self.system_status.index();
+        if changed && self.output_policy == OutputPolicy::OnChange {
+            let _ = self.communication.send_status_change(&status);
+        }
+        
+        self.uptime.transition(&status, self.clock.now_ms());
+        self.communication.set_rgb_status(&status, None);
+        self.system_status = status;
+    }
+    
+    pub fn availability_percent(&self) -> f32 {
+        self.uptime.availability_percent(self.clock.now_ms())
+    }
+    
+    pub fn get_alert_frequency(&self) -> f32 {
+        self.alert_system.get_alert_frequency()
+    }
+    
+    pub fn active_alerts(&self) -> heapless::Vec<Alert, 8> {
+        self.alert_system.active_alerts()
+    }
+    
+    pub fn alert_frequency_for(&self, min_level: AlertLevel) -> f32 {
+        self.alert_system.alert_frequency_for(min_level)
+    }
+    
+    // Chamar logo após ligar/desligar um atuador
+    pub fn suppress_readings_for(&mut self, duration_ms: u32) {
+        self.blackout.suppress_for(self.clock.now_ms(), duration_ms);
+    }
+    
+    pub fn get_discarded_reading_count(&self) -> u32 {
+        self.blackout.discarded_count()
+    }
+    
+    fn is_outlier(&self, data: &EnvironmentalData) -> bool {
+        let average = match self.data_storage.measured_average() {
+            Some(average) => average,
+            None => return false,
+        };
+        let tolerance = &self.sensor_manager.config.outlier_tolerance;
+        
+        ALL_SENSORS.iter().any(|&sensor| {
+            is_relative_outlier(data.value(sensor), average[sensor.index()], tolerance[sensor.index()])
+        })
+    }
+    
+    // Nunca transmite mais rápido do que lê (mesma regra de from_parts)
+    fn sync_transmit_interval(&mut self) {
+        let config = &self.sensor_manager.config;
+        let min_ms = config.transmit_interval_ms.max(config.reading_interval);
+        self.adaptive_tx.set_min_ms(min_ms);
+    }
+    
+    pub fn transmit_interval_ms(&self) -> u32 {
+        if self.adaptive_tx.is_enabled() {
+            self.adaptive_tx.interval_ms()
+        } else {
+            self.sensor_manager.config.transmit_interval_ms
+        }
+    }
+    
+    fn should_transmit(&mut self, data: &EnvironmentalData, now: u32) -> bool {
+        if self.adaptive_tx.is_enabled() {
+            let changed = self.transmit_filter.should_send(data);
+            self.adaptive_tx.should_transmit(now, changed)
+        } else {
+            self.transmit_due(now) && self.transmit_filter.should_send(data)
+        }
+    }
+    
+    fn transmit_due(&self, now: u32) -> bool {
+        match self.last_transmit_time {
+            Some(last) => now.wrapping_sub(last) >= self.sensor_manager.config.transmit_interval_ms,
+            None => true,
+        }
+    }
+    
+    pub fn get_rejected_outlier_count(&self) -> u32 {
+        self.rejected_outliers
+    }
+    
+    pub fn unexpected_reset_count(&self) -> u32 {
+        self.unexpected_resets
+    }
+    
+    // Timestamps sem referência absoluta (RTC falhou e o relógio caiu para millis)
+    pub fn is_clock_degraded(&self) -> bool {
+        self.clock.is_degraded()
+    }
+    
+    pub fn reinit_sensor(&mut self, sensor: SensorType) -> Result<(), SensorError> {
+        self.sensor_manager.reinit_sensor(sensor)
+    }
+    
+    pub fn sensor_health(&self, sensor: SensorType) -> SensorHealth {
+        self.sensor_health.health(sensor.index())
+    }
+    
+    // Alertas de saúde que não saem pela serial não interrompem o ciclo
+    fn update_sensor_health(&mut self, now: u32) {
+        let status = self.sensor_manager.channel_status();
+        let dead_after = self.sensor_manager.config.dead_after_failures;
+        let recovery = self.sensor_manager.config.recovery_readings;
+        
+        for (index, ok) in status.iter().enumerate() {
+            if self.sensor_health.record(index, *ok, dead_after, recovery) == Some(SensorHealth::Dead) {
+                let dead = [Alert::new(
+                    AlertKind::Device,
+                    AlertCode::SensorDead,
+                    AlertLevel::Warning,
+                    "Sensor sem leituras válidas",
+                    index as f32,
+                    now,
+                )];
+                self.emit_alerts(&dead, now);
+                
+                // Uma tentativa de reinicialização; a reabilitação ainda
+                // depende de recovery_readings leituras boas seguidas
+                let _ = self.sensor_manager.reinit_sensor(ALL_SENSORS[index]);
+            }
+        }
+        
+        // Sinalizado à parte da falha de conversão: aponta para o hardware.
+        // O canal já fica marcado como falho; o alerta sai uma vez por episódio
+        let saturated = self.sensor_manager.is_saturated(SensorType::Temperature);
+        let entered = saturated && !self.saturation_alerted;
+        self.saturation_alerted = saturated;
+        if entered {
+            let saturated = [Alert::new(
+                AlertKind::Device,
+                AlertCode::TemperatureAdcSaturated,
+                AlertLevel::Warning,
+                "Temperatura no fundo de escala do ADC",
+                ADC_MAX_RAW as f32,
+                now,
+            )];
+            self.emit_alerts(&saturated, now);
+        }
+    }
+    
+    // Chamado a cada leitura do RTC (segundos Unix, hora local)
+    pub fn record_rtc_time(&mut self, rtc_seconds: u32) {
+        self.clock_drift.record(self.clock.now_ms(), rtc_seconds);
+        self.alert_system.set_weekend(is_weekend(day_of_week(rtc_seconds)));
+    }
+    
+    pub fn set_alert_profiles(&mut self, weekday: AlertProfile, weekend: AlertProfile) {
+        self.alert_system.set_profiles(weekday, weekend);
+    }
+    
+    pub fn estimate_clock_drift_ppm(&self) -> Option<f32> {
+        self.clock_drift.estimate_ppm()
+    }
+    
+    pub fn sensor_age_hours(&self) -> u32 {
+        self.gas_sensor_life.hours()
+    }
+    
+    pub fn adc_read_time_us(&self) -> Option<u32> {
+        self.sensor_manager.adc_read_time_us()
+    }
+    
+    // Abaixo de expected_sample_rate_hz() o ciclo não está dando conta do intervalo
+    pub fn effective_sample_rate_hz(&self) -> Option<f32> {
+        self.data_storage.effective_sample_rate_hz(SAMPLE_RATE_WINDOW)
+    }
+    
+    pub fn expected_sample_rate_hz(&self) -> f32 {
+        1000.0 / self.sensor_manager.config.reading_interval as f32
+    }
+    
+    // Medição do sensor de partículas, válida por PARTICULATES_STALE_MS
+    pub fn update_particulates(&mut self, particulates: pms5003::ParticulateMatter, now: u32) {
+        self.particulates = Some((particulates, now));
+    }
+    
+    // Sensor desconectado ou travado: a última medição deixa de valer
+    pub fn particulates(&self, now: u32) -> Option<pms5003::ParticulateMatter> {
+        self.particulates
+            .filter(|(_, received)| now.wrapping_sub(*received) <= PARTICULATES_STALE_MS)
+            .map(|(particulates, _)| particulates)
+    }
+    
+    pub fn derived_metrics(&self) -> Option<DerivedMetrics> {
+        let latest = self.data_storage.get_latest_data()?;
+        let particulates = self.particulates(self.clock.now_ms());
+        Some(latest.derived(&self.sensor_manager.config, particulates))
+    }
+    
+    // Bytes já recebidos do PMS5003 (ele envia um frame por segundo sozinho)
+    fn poll_particulates(&mut self, now: u32) {
+        while let Some(byte) = self.sensor_manager.read_particulate_byte() {
+            if let Some(Ok(particulates)) = self.particulate_reader.push(byte) {
+                self.update_particulates(particulates, now);
+            }
+        }
+    }
+    
+    // Cada métrica só é confiável após o aquecimento do próprio sensor.
+    // Uma vez aquecida ela fica pronta: depois de ~49,7 dias o millis() dá
+    // a volta e o tempo desde o boot voltaria a parecer pequeno
+    pub fn warmup_status(&self, now: u32) -> [bool; 4] {
+        let elapsed = now.wrapping_sub(self.boot_time);
+        let mut ready = self.warmed_up.get();
+        for (ready, warmup) in ready.iter_mut().zip(self.sensor_manager.config.warmup_ms) {
+            *ready |= elapsed >= warmup;
+        }
+        self.warmed_up.set(ready);
+        ready
+    }
+    
+    pub fn is_metric_ready(&self, sensor: SensorType) -> bool {
+        self.warmup_status(self.clock.now_ms())[sensor.index()]
+    }
+    
+    pub fn get_device_health(&self) -> Option<DeviceHealth> {
+        self.device_health
+    }
+    
+    pub fn get_display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+    
+    pub fn displayed_value(&self) -> Option<f32> {
+        self.data_storage
+            .get_latest_data()
+            .map(|data| self.display_mode.value(data))
+    }
+    
+    pub fn get_suppressed_alert_count(&self) -> u32 {
+        self.alert_throttle.suppressed_count()
+    }
+    
+    pub fn is_alert_storm(&self) -> bool {
+        self.alert_storm.is_active()
+    }
+    
+    pub fn get_baseline(&self) -> Option<&EnvironmentalData> {
+        self.auto_baseline.baseline()
+    }
+    
+    pub fn get_daily_profile(&self) -> &DailyProfile {
+        &self.daily_profile
+    }
+}
+
+// Agendador cooperativo: dispara cada tarefa registrada quando seu período
+// vence, dentro do loop principal (sem preempção nem catch-up de atrasos)
+pub struct ScheduledTask<Ctx> {
+    period_ms: u32,
+    last_run: Option<u32>,
+    callback: fn(&mut Ctx),
+}
+
+pub struct Scheduler<Ctx, const N: usize> {
+    tasks: heapless::Vec<ScheduledTask<Ctx>, N>,
+}
+
+// Todas as N posições do agendador já estão ocupadas
+#[derive(Debug, PartialEq)]
+pub struct SchedulerFull;
+
+impl<Ctx, const N: usize> Default for Scheduler<Ctx, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Ctx, const N: usize> Scheduler<Ctx, N> {
+    pub fn new() -> Self {
+        Self {
+            tasks: heapless::Vec::new(),
+        }
+    }
+    
+    // A tarefa roda na primeira chamada de run_pending e depois a cada período
+    pub fn add(&mut self, period_ms: u32, callback: fn(&mut Ctx)) -> Result<(), SchedulerFull> {
+        self.push(period_ms, None, callback)
+    }
+    
+    // A primeira execução só acontece um período depois de `start`
+    // (ex.: recalibração diária, que não deve rodar logo no boot)
+    pub fn add_after(&mut self, period_ms: u32, start: u32, callback: fn(&mut Ctx)) -> Result<(), SchedulerFull> {
+        self.push(period_ms, Some(start), callback)
+    }
+    
+    fn push(&mut self, period_ms: u32, last_run: Option<u32>, callback: fn(&mut Ctx)) -> Result<(), SchedulerFull> {
+        self.tasks
+            .push(ScheduledTask {
+                period_ms,
+                last_run,
+                callback,
+            })
+            .map_err(|_| SchedulerFull)
+    }
+    
+    // Devolve quantas tarefas foram executadas
+    pub fn run_pending(&mut self, now: u32, context: &mut Ctx) -> usize {
+        let mut executed = 0;
+        for task in self.tasks.iter_mut() {
+            let due = match task.last_run {
+                Some(last_run) => now.wrapping_sub(last_run) >= task.period_ms,
+                None => true,
+            };
+            if due {
+                task.last_run = Some(now);
+                (task.callback)(context);
+                executed += 1;
+            }
+        }
+        executed
+    }
+}
+
+// Recalibração periódica dos sensores
+#[cfg(not(test))]
+const RECALIBRATION_PERIOD_MS: u32 = 24 * 3_600_000;
+
+// Período da tarefa de monitoramento e maior espera do loop principal
+#[cfg(not(test))]
+const MAX_IDLE_SLEEP_MS: u32 = 100;
+
+// Política da placa: leitura inválida tenta recalibrar; falhas de envio
+// ficam pendentes e são reenviadas pelo próprio ciclo
+#[cfg(not(test))]
+struct BoardErrorPolicy;
+
+#[cfg(not(test))]
+impl ErrorHandler for BoardErrorPolicy {
+    fn on_error(&mut self, err: &SensorError, _status: &SystemStatus) -> ErrorAction {
+        match err {
+            SensorError::ReadError => ErrorAction::Recalibrate,
+            _ => ErrorAction::Continue,
+        }
+    }
+}
+
+#[cfg(not(test))]
+type BoardMonitoringSystem = EnvironmentalMonitoringSystem<
+    AdcSensorSource,
+    SerialTransport,
+    BoardIndicators,
+    arduino_hal::Eeprom,
+    HalClock,
+    BoardErrorPolicy,
+>;
+
+// Modo idle do ATmega328P: só a CPU para; timers e USART seguem rodando
+// e qualquer interrupção (o tick de ~1 ms do millis()) a acorda
+#[cfg(not(test))]
+fn enable_idle_sleep() {
+    // SAFETY: o SMCR só é escrito aqui, uma vez, antes do laço principal
+    let cpu = unsafe { &*arduino_hal::pac::CPU::ptr() };
+    cpu.smcr.write(|w| w.sm().idle().se().set_bit());
+}
+
+// Dorme até `duration_ms` passar, voltando a dormir a cada interrupção
+#[cfg(not(test))]
+fn idle_for(clock: &HalClock, duration_ms: u32) {
+    let start = clock.now_ms();
+    while clock.now_ms().wrapping_sub(start) < duration_ms {
+        avr_device::asm::sleep();
+    }
+}
+
+// O tratamento do erro já aconteceu no handler
+#[cfg(not(test))]
+fn monitoring_task(monitoring_system: &mut BoardMonitoringSystem) {
+    let _ = monitoring_system.run_monitoring_cycle();
+}
+
+#[cfg(not(test))]
+fn recalibration_task(monitoring_system: &mut BoardMonitoringSystem) {
+    let _ = monitoring_system.calibrate_all_sensors();
+}
+
+#[cfg(not(test))]
+fn heartbeat_task(monitoring_system: &mut BoardMonitoringSystem) {
+    let _ = monitoring_system.send_heartbeat();
+}
+
+// Um byte por volta, sem esperar a serial
+#[cfg(not(test))]
+fn tx_flush_task(monitoring_system: &mut BoardMonitoringSystem) {
+    let _ = monitoring_system.pump_serial();
+}
+
+// Função principal
+#[cfg(not(test))]
+#[arduino_hal::entry]
+fn main() -> ! {
+    let mut monitoring_system = BoardMonitoringSystem::with_handler(SystemConfig::default(), BoardErrorPolicy)
+        .expect("Falha ao inicializar sistema de monitoramento");
+    
+    // Calibrar sensores na inicialização
+    monitoring_system.calibrate_all_sensors()
+        .expect("Falha na calibração dos sensores");
+    
+    // Avisar sobre canais com leituras incompatíveis com o sensor esperado
+    let _ = monitoring_system.check_wiring();
+    
+    // Envio pela serial sem travar o ciclo de monitoramento
+    monitoring_system.set_tx_queue(true);
+    
+    let clock = HalClock;
+    enable_idle_sleep();
+    let mut scheduler: Scheduler<BoardMonitoringSystem, 4> = Scheduler::new();
+    let heartbeat_interval = monitoring_system.heartbeat_interval_ms();
+    let _ = scheduler.add(MAX_IDLE_SLEEP_MS, monitoring_task);
+    let _ = scheduler.add_after(RECALIBRATION_PERIOD_MS, clock.now_ms(), recalibration_task);
+    let _ = scheduler.add(heartbeat_interval, heartbeat_task);
+    // Período 0: a cada volta do loop
+    let _ = scheduler.add(0, tx_flush_task);
+    
+    loop {
+        scheduler.run_pending(clock.now_ms(), &mut monitoring_system);
+        
+        // CPU em idle até a próxima leitura, limitado ao período da tarefa
+        // para seguir atendendo comandos e erros; com saída pendente, só 1 ms
+        let idle = if monitoring_system.tx_pending() > 0 {
+            1
+        } else {
+            monitoring_system
+                .time_until_next_reading(clock.now_ms())
+                .clamp(1, MAX_IDLE_SLEEP_MS)
+        };
+        idle_for(&clock, idle);
+    }
+}
+
+// Testes no host: o sistema inteiro sobre implementações simuladas dos traits
+// de hardware (cargo test, sem a placa)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::vec::Vec;
+    
+    // Valores brutos do passo NORMAL: 24.9°C, 50%, ~400 ppm, 100.8 kPa
+    const NORMAL_RAW: [u16; 4] = [51, 512, 624, 154];
+    
+    // Cada mock é um handle: o teste guarda um clone e observa/controla
+    // o estado que o sistema usa
+    #[derive(Clone, Default)]
+    struct MockClock {
+        now: Rc<Cell<u32>>,
+        unix_base: Rc<Cell<Option<u32>>>,
+    }
+    
+    impl MockClock {
+        fn set(&self, ms: u32) {
+            self.now.set(ms);
+        }
+        
+        // Simula um RTC: hora de parede = base + tempo desde o boot
+        fn set_unix(&self, seconds: u32) {
+            self.unix_base.set(Some(seconds));
+        }
+        
+        fn advance(&self, ms: u32) {
+            self.now.set(self.now.get().wrapping_add(ms));
+        }
+    }
+    
+    impl Clock for MockClock {
+        fn now_ms(&self) -> u32 {
+            self.now.get()
+        }
+        
+        fn now_us(&self) -> u32 {
+            self.now.get().wrapping_mul(1000)
+        }
+        
+        fn unix_seconds(&self) -> Option<u32> {
+            self.unix_base.get().map(|base| base.wrapping_add(self.now.get() / 1000))
+        }
+    }
+    
+    // Cada leitura pode consumir tempo do relógio compartilhado (ADC lento, I2C travado)
+    // Leituras enfileiradas saem primeiro; depois volta o valor fixo
+    #[derive(Clone)]
+    struct MockSensorSource {
+        raw: Rc<Cell<[u16; 4]>>,
+        queued: Rc<RefCell<VecDeque<[u16; 4]>>>,
+        reads: Rc<Cell<u32>>,
+        clock: MockClock,
+        read_delay_ms: Rc<Cell<u32>>,
+        jammed: Rc<Cell<[bool; 4]>>,
+        reinits: Rc<Cell<u32>>,
+    }
+    
+    impl MockSensorSource {
+        fn new(raw: [u16; 4]) -> Self {
+            Self::with_clock(raw, MockClock::default())
+        }
+        
+        fn with_clock(raw: [u16; 4], clock: MockClock) -> Self {
+            Self {
+                raw: Rc::new(Cell::new(raw)),
+                queued: Rc::default(),
+                reads: Rc::default(),
+                clock,
+                read_delay_ms: Rc::new(Cell::new(0)),
+                jammed: Rc::default(),
+                reinits: Rc::default(),
+            }
+        }
+        
+        fn set(&self, raw: [u16; 4]) {
+            self.raw.set(raw);
+        }
+        
+        fn set_read_delay(&self, ms: u32) {
+            self.read_delay_ms.set(ms);
+        }
+        
+        fn queue(&self, raws: &[[u16; 4]]) {
+            self.queued.borrow_mut().extend(raws);
+        }
+        
+        fn reads(&self) -> u32 {
+            self.reads.get()
+        }
+        
+        // Canal travado lê fundo de escala até ser reinicializado
+        fn jam(&self, sensor: SensorType) {
+            let mut jammed = self.jammed.get();
+            jammed[sensor.index()] = true;
+            self.jammed.set(jammed);
+        }
+        
+        fn reinits(&self) -> u32 {
+            self.reinits.get()
+        }
+        
+        fn apply_jam(&self, mut raw: [u16; 4]) -> [u16; 4] {
+            for (value, jammed) in raw.iter_mut().zip(self.jammed.get()) {
+                if jammed {
+                    *value = ADC_MAX_RAW;
+                }
+            }
+            raw
+        }
+    }
+    
+    impl SensorSource for MockSensorSource {
+        fn read_raw(&mut self) -> [u16; 4] {
+            self.clock.advance(self.read_delay_ms.get());
+            self.reads.set(self.reads.get() + 1);
+            let raw = self.queued.borrow_mut().pop_front().unwrap_or(self.raw.get());
+            self.apply_jam(raw)
+        }
+        
+        fn read_channel(&mut self, sensor: SensorType) -> u16 {
+            self.apply_jam(self.raw.get())[sensor.index()]
+        }
+        
+        fn reinit(&mut self, sensor: SensorType) -> Result<(), SensorError> {
+            self.reinits.set(self.reinits.get() + 1);
+            let mut jammed = self.jammed.get();
+            jammed[sensor.index()] = false;
+            self.jammed.set(jammed);
+            Ok(())
+        }
+        
+        // MCU a 25°C alimentado com 5V
+        fn read_device_raw(&mut self) -> (u16, u16) {
+            (355, 225)
+        }
+    }
+    
+    #[derive(Clone, Default)]
+    struct MockTransport {
+        written: Rc<RefCell<Vec<u8>>>,
+        rx: Rc<RefCell<VecDeque<u8>>>,
+        failing: Rc<Cell<bool>>,
+    }
+    
+    impl MockTransport {
+        fn output(&self) -> String {
+            String::from_utf8_lossy(&self.written.borrow()).into_owned()
+        }
+        
+        fn take_output(&self) -> String {
+            let output = self.output();
+            self.written.borrow_mut().clear();
+            output
+        }
+        
+        fn set_failing(&self, failing: bool) {
+            self.failing.set(failing);
+        }
+        
+        fn receive(&self, line: &str) {
+            self.rx.borrow_mut().extend(line.bytes());
+        }
+    }
+    
+    impl DataTransport for MockTransport {
+        fn write_byte(&mut self, byte: u8) -> Result<(), SensorError> {
+            if self.failing.get() {
+                return Err(SensorError::CommunicationError);
+            }
+            self.written.borrow_mut().push(byte);
+            Ok(())
+        }
+        
+        fn read_byte(&mut self) -> Option<u8> {
+            self.rx.borrow_mut().pop_front()
+        }
+    }
+    
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    struct PanelState {
+        status_led: bool,
+        alert_led: bool,
+        buzzer: bool,
+        rgb: (u8, u8, u8),
+        button: bool,
+    }
+    
+    #[derive(Clone, Default)]
+    struct MockIndicators {
+        state: Rc<Cell<PanelState>>,
+    }
+    
+    impl MockIndicators {
+        fn get(&self) -> PanelState {
+            self.state.get()
+        }
+        
+        fn update(&self, change: impl FnOnce(&mut PanelState)) {
+            let mut state = self.state.get();
+            change(&mut state);
+            self.state.set(state);
+        }
+    }
+    
+    impl StatusIndicator for MockIndicators {
+        fn set_status_led(&mut self, on: bool) {
+            self.update(|state| state.status_led = on);
+        }
+        
+        fn set_alert_led(&mut self, on: bool) {
+            self.update(|state| state.alert_led = on);
+        }
+        
+        fn set_buzzer(&mut self, on: bool) {
+            self.update(|state| state.buzzer = on);
+        }
+        
+        fn set_rgb(&mut self, red: u8, green: u8, blue: u8) {
+            self.update(|state| state.rgb = (red, green, blue));
+        }
+        
+        fn is_button_pressed(&self) -> bool {
+            self.get().button
+        }
+    }
+    
+    // EEPROM de 1 KB do ATmega328P, apagada (0xFF)
+    #[derive(Clone)]
+    struct MockEeprom {
+        bytes: Rc<RefCell<[u8; 1024]>>,
+    }
+    
+    impl Default for MockEeprom {
+        fn default() -> Self {
+            Self { bytes: Rc::new(RefCell::new([0xFF; 1024])) }
+        }
+    }
+    
+    impl NonVolatileStorage for MockEeprom {
+        fn read(&self, address: u16, buffer: &mut [u8]) -> Result<(), SensorError> {
+            let start = address as usize;
+            let bytes = self.bytes.borrow();
+            let source = bytes.get(start..start + buffer.len()).ok_or(SensorError::CommunicationError)?;
+            buffer.copy_from_slice(source);
+            Ok(())
+        }
+        
+        fn write(&mut self, address: u16, bytes: &[u8]) -> Result<(), SensorError> {
+            let start = address as usize;
+            let mut memory = self.bytes.borrow_mut();
+            let target = memory.get_mut(start..start + bytes.len()).ok_or(SensorError::CommunicationError)?;
+            target.copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+    
+    type MockSystem<H = NoopHandler> =
+        EnvironmentalMonitoringSystem<MockSensorSource, MockTransport, MockIndicators, MockEeprom, MockClock, H>;
+    
+    struct Rig<H = NoopHandler> {
+        system: MockSystem<H>,
+        source: MockSensorSource,
+        transport: MockTransport,
+        indicators: MockIndicators,
+        eeprom: MockEeprom,
+        clock: MockClock,
+    }
+    
+    impl<H: ErrorHandler> Rig<H> {
+        // Avança o relógio até a próxima leitura e roda um ciclo
+        fn next_reading(&mut self) -> Result<(), SensorError> {
+            self.clock.advance(self.system.sensor_manager.config.reading_interval);
+            self.system.run_monitoring_cycle()
+        }
+    }
+    
+    // Sem aquecimento e com limite de qualidade do ar acima do ambiente normal
+    fn quiet_config() -> SystemConfig {
+        SystemConfig {
+            thresholds: ThresholdConfig {
+                air_quality_max: 1000.0,
+                ..ThresholdConfig::default()
+            },
+            warmup_ms: [0; 4],
+            ..SystemConfig::default()
+        }
+    }
+    
+    fn rig(config: SystemConfig) -> Rig {
+        rig_with_handler(config, NoopHandler)
+    }
+    
+    fn rig_with_handler<H: ErrorHandler>(config: SystemConfig, handler: H) -> Rig<H> {
+        let clock = MockClock::default();
+        let source = MockSensorSource::with_clock(NORMAL_RAW, clock.clone());
+        let transport = MockTransport::default();
+        let indicators = MockIndicators::default();
+        let eeprom = MockEeprom::default();
+        
+        let sensor_manager = SensorManager::with_source(source.clone(), config.clone());
+        let communication = CommunicationSystem::with_parts(
+            transport.clone(),
+            indicators.clone(),
+            eeprom.clone(),
+            ResetCause::PowerOn,
+        );
+        let system = EnvironmentalMonitoringSystem::from_parts_with_handler(
+            sensor_manager,
+            communication,
+            clock.clone(),
+            config,
+            handler,
+        )
+        .expect("configuração válida");
+        
+        Rig { system, source, transport, indicators, eeprom, clock }
+    }
+    
+    #[test]
+    fn boot_announces_reset_cause() {
+        let rig = rig(quiet_config());
+        assert_eq!(rig.transport.output(), "BOOT:POWER_ON\n");
+        assert_eq!(rig.system.unexpected_reset_count(), 0);
+    }
+    
+    #[test]
+    fn invalid_config_is_rejected() {
+        let config = SystemConfig { reading_interval: 0, ..quiet_config() };
+        let sensor_manager = SensorManager::with_source(MockSensorSource::new(NORMAL_RAW), config.clone());
+        let communication = CommunicationSystem::with_parts(
+            MockTransport::default(),
+            MockIndicators::default(),
+            MockEeprom::default(),
+            ResetCause::PowerOn,
+        );
+        let result = EnvironmentalMonitoringSystem::from_parts(sensor_manager, communication, MockClock::default(), config);
+        assert!(matches!(result, Err(SensorError::ConfigError)));
+    }
+    
+    #[test]
+    fn cycle_reads_stores_and_transmits() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        
+        rig.next_reading().unwrap();
+        
+        let output = rig.transport.take_output();
+        assert!(output.starts_with("T:24.9C,H:50.0%,AQ:"), "{}", output);
+        assert!(output.contains(",P:100.8kPa,T:5000*"), "{}", output);
+        let latest = rig.system.data_storage.get_latest_data().unwrap();
+        assert_eq!(latest.timestamp, 5000);
+        assert!((latest.air_quality - 400.0).abs() < 5.0);
+        
+        let panel = rig.indicators.get();
+        assert!(panel.status_led);
+        assert!(!panel.alert_led);
+        assert!(!panel.buzzer);
+    }
+    
+    #[test]
+    fn no_reading_before_interval_elapses() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        rig.clock.advance(4999);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.transport.output(), "");
+        assert_eq!(rig.system.time_until_next_reading(rig.clock.now_ms()), 1);
+        
+        rig.clock.advance(1);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert!(rig.transport.output().starts_with("T:"));
+    }
+    
+    #[test]
+    fn threshold_crossing_raises_alert_and_leds() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        
+        // Pico do roteiro demo: ~2000 ppm
+        rig.source.set([51, 512, 754, 154]);
+        rig.transport.take_output();
+        rig.next_reading().unwrap();
+        
+        assert!(rig.transport.output().contains("ALERT["), "{}", rig.transport.output());
+        assert!(rig.indicators.get().alert_led);
+        assert!(!rig.system.active_alerts().is_empty());
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        assert!(!rig.indicators.get().alert_led);
+    }
+    
+    #[test]
+    fn unreadable_first_sample_is_an_error() {
+        let mut rig = rig(quiet_config());
+        rig.source.set([ADC_MAX_RAW, 512, 624, 154]);
+        
+        assert!(matches!(rig.next_reading(), Err(SensorError::ReadError)));
+        assert!(matches!(rig.system.get_system_status(), SystemStatus::Error));
+        assert_eq!(rig.system.data_storage.get_latest_data().map(|data| data.timestamp), None);
+    }
+    
+    #[test]
+    fn serial_commands_are_answered() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        
+        rig.transport.receive("PAUSE\nFOO\n");
+        rig.next_reading().unwrap();
+        
+        assert_eq!(rig.transport.take_output(), "OK\nERR comando desconhecido\n");
+        assert!(matches!(rig.system.get_system_status(), SystemStatus::Paused));
+        
+        rig.transport.receive("RESUME\n");
+        rig.next_reading().unwrap();
+        assert!(rig.transport.output().starts_with("OK\nT:"));
+    }
+    
+    #[test]
+    fn unexpected_reset_is_counted_in_eeprom() {
+        let eeprom = MockEeprom::default();
+        for _ in 0..2 {
+            let config = quiet_config();
+            let communication = CommunicationSystem::with_parts(
+                MockTransport::default(),
+                MockIndicators::default(),
+                eeprom.clone(),
+                ResetCause::Watchdog,
+            );
+            let sensor_manager = SensorManager::with_source(MockSensorSource::new(NORMAL_RAW), config.clone());
+            let system = EnvironmentalMonitoringSystem::from_parts(sensor_manager, communication, MockClock::default(), config).unwrap();
+            assert!(system.unexpected_reset_count() > 0);
+        }
+        
+        let mut count = [0u8; 4];
+        eeprom.read(EEPROM_UNEXPECTED_RESETS_ADDR, &mut count).unwrap();
+        assert_eq!(u32::from_le_bytes(count), 2);
+    }
+    
+    fn sample(temperature: f32, humidity: f32, air_quality: f32, pressure: f32, timestamp: u32) -> EnvironmentalData {
+        EnvironmentalData { temperature, humidity, air_quality, pressure, timestamp }
+    }
+    
+    #[test]
+    fn daily_profile_averages_each_hour() {
+        let mut profile = DailyProfile::new();
+        // Dois dias: 08h e 20h com valores diferentes
+        for day in 0..2u32 {
+            let morning = day * 86_400 + 8 * 3_600;
+            let evening = day * 86_400 + 20 * 3_600 + 59;
+            profile.update(hour_of_day(morning), &sample(18.0 + day as f32 * 2.0, 60.0, 400.0, 101.0, morning));
+            profile.update(hour_of_day(evening), &sample(26.0, 40.0 + day as f32 * 10.0, 600.0, 100.0, evening));
+        }
+        
+        let morning = profile.hourly_average(8).unwrap();
+        assert_eq!(morning.temperature, 19.0);
+        assert_eq!(morning.humidity, 60.0);
+        let evening = profile.hourly_average(20).unwrap();
+        assert_eq!(evening.temperature, 26.0);
+        assert_eq!(evening.humidity, 45.0);
+        assert_eq!(profile.bucket(20).unwrap().count(), 2);
+        assert!(profile.hourly_average(12).is_none());
+        assert!(profile.hourly_average(24).is_none());
+    }
+    
+    #[test]
+    fn daily_profile_needs_wall_clock() {
+        // Sem set_unix o MockClock não tem RTC: a contagem desde o boot não vira hora do dia
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        assert!((0..24).all(|hour| rig.system.get_daily_profile().hourly_average(hour).is_none()));
+    }
+    
+    #[test]
+    fn parser_rejects_bad_numbers_ranges_and_commands() {
+        assert_eq!(Command::parse("INTERVAL 5000"), Command::SetInterval(5000));
+        assert_eq!(Command::parse("INTERVAL abc"), Command::Invalid("número inválido"));
+        assert_eq!(Command::parse("INTERVAL -5"), Command::Invalid("número inválido"));
+        assert_eq!(Command::parse("INTERVAL 50"), Command::Invalid("intervalo fora da faixa"));
+        assert_eq!(Command::parse("INTERVAL 3600001"), Command::Invalid("intervalo fora da faixa"));
+        assert_eq!(Command::parse("INTERVAL"), Command::Invalid("argumentos insuficientes"));
+        assert_eq!(Command::parse("INTERVAL 5000 1"), Command::Invalid("argumentos excedentes"));
+        
+        assert_eq!(
+            Command::parse("SET THRESH TEMP_MAX 35.0"),
+            Command::SetThreshold(ThresholdMetric::TempMax, 35.0)
+        );
+        assert_eq!(Command::parse("SET THRESH TEMP_MAX NaN"), Command::Invalid("número inválido"));
+        assert_eq!(Command::parse("SET THRESH TEMP_MAX 500"), Command::Invalid("limite fora da faixa do sensor"));
+        assert_eq!(Command::parse("SET THRESH FOO 1"), Command::Invalid("métrica desconhecida"));
+        
+        assert_eq!(Command::parse("REBOOT"), Command::Invalid("comando desconhecido"));
+        assert_eq!(Command::parse(""), Command::Invalid("comando desconhecido"));
+    }
+    
+    #[test]
+    fn rejected_command_leaves_config_untouched() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.transport.receive("INTERVAL 10\n");
+        rig.next_reading().unwrap();
+        
+        assert!(rig.transport.output().starts_with("ERR intervalo fora da faixa\n"));
+        assert_eq!(rig.system.sensor_manager.config.reading_interval, 5000);
+    }
+    
+    #[test]
+    fn throttle_passes_k_alerts_per_window() {
+        let mut throttle = AlertThrottle::new(3, 60_000);
+        let passed = (0..10).filter(|i| throttle.allow(AlertKind::AirQuality, i * 100)).count();
+        assert_eq!(passed, 3);
+        assert_eq!(throttle.suppressed_count(), 7);
+        
+        // Outro tipo tem a própria cota; a janela expirada libera de novo
+        assert!(throttle.allow(AlertKind::Temperature, 1_000));
+        assert!(throttle.allow(AlertKind::AirQuality, 60_000));
+    }
+    
+    #[test]
+    fn throttled_alerts_still_light_the_led() {
+        let mut rig = rig(quiet_config());
+        rig.source.set([51, 512, 754, 154]);
+        for _ in 0..6 {
+            rig.next_reading().unwrap();
+        }
+        
+        assert_eq!(rig.transport.output().matches("ALERT[").count(), 3);
+        assert_eq!(rig.system.get_suppressed_alert_count(), 3);
+        assert!(rig.indicators.get().alert_led);
+    }
+    
+    #[test]
+    fn tampered_sample_breaks_the_hash_chain() {
+        let samples: Vec<EnvironmentalData> = (0..4)
+            .map(|i| sample(20.0 + i as f32, 50.0, 400.0, 101.0, i * 5000))
+            .collect();
+        let mut hasher = RollingHasher::new(2);
+        let checkpoints: Vec<Checkpoint> = samples.iter().filter_map(|data| hasher.add_sample(data)).collect();
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[1].previous_hash, checkpoints[0].hash);
+        assert_eq!(RollingHasher::block_hash(0, &samples[..2]), checkpoints[0].hash);
+        assert_eq!(RollingHasher::block_hash(checkpoints[0].hash, &samples[2..]), checkpoints[1].hash);
+        
+        let mut tampered = samples.clone();
+        tampered[1].temperature += 0.1;
+        let forged = RollingHasher::block_hash(0, &tampered[..2]);
+        assert_ne!(forged, checkpoints[0].hash);
+        // O bloco seguinte, recalculado sobre o hash forjado, também não fecha
+        assert_ne!(RollingHasher::block_hash(forged, &samples[2..]), checkpoints[1].hash);
+    }
+    
+    #[test]
+    fn checkpoint_follows_the_sample_that_closes_the_block() {
+        let mut rig = rig(SystemConfig { hash_block_size: 2, ..quiet_config() });
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        rig.next_reading().unwrap();
+        
+        let output = rig.transport.output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("T:"), "{}", output);
+        assert!(lines[1].starts_with("HASH:"), "{}", output);
+        assert!(lines[1].ends_with(",PREV:00000000,T:10000"), "{}", output);
+    }
+    
+    #[test]
+    fn baseline_follows_a_new_normal() {
+        let window = 4 * 3_600_000;
+        let mut baseline = AutoBaseline::new(window, 0.25);
+        assert!(baseline.check(&sample(22.0, 50.0, 400.0, 101.0, 0)).is_empty());
+        baseline.update(&sample(22.0, 50.0, 400.0, 101.0, 0));
+        
+        let shifted = |timestamp| sample(22.0, 50.0, 600.0, 101.0, timestamp);
+        let alerts = baseline.check(&shifted(0));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].code, AlertCode::AirQualityDeviation);
+        
+        // Média exponencial: no meio do caminho o desvio ainda é menor que antes,
+        // depois de algumas janelas no novo patamar a linha de base chega a ele
+        for step in 1..=8 {
+            baseline.update(&shifted(step * window / 8));
+        }
+        let halfway = baseline.baseline().unwrap().air_quality;
+        assert!(halfway > 450.0 && halfway < 600.0, "{}", halfway);
+        for step in 9..=40 {
+            baseline.update(&shifted(step * window / 8));
+        }
+        assert!((baseline.baseline().unwrap().air_quality - 600.0).abs() < 1.0);
+        assert!(baseline.check(&shifted(5 * window)).is_empty());
+        // E o antigo normal passa a ser o desvio
+        assert_eq!(baseline.check(&sample(22.0, 50.0, 400.0, 101.0, 5 * window)).len(), 1);
+    }
+    
+    #[test]
+    fn availability_counts_time_in_each_state() {
+        let mut uptime = UptimeTracker::new(&SystemStatus::Running, 0);
+        uptime.transition(&SystemStatus::Error, 6_000);
+        uptime.transition(&SystemStatus::Calibrating, 8_000);
+        uptime.transition(&SystemStatus::Running, 10_000);
+        
+        assert_eq!(uptime.time_in(&SystemStatus::Error, 10_000), 2_000);
+        assert_eq!(uptime.time_in(&SystemStatus::Running, 12_000), 8_000);
+        assert!((uptime.availability_percent(12_000) - 8_000.0 / 12_000.0 * 100.0).abs() < 1e-3);
+    }
+    
+    #[test]
+    fn paused_time_lowers_availability() {
+        let mut rig = rig(quiet_config());
+        assert_eq!(rig.system.availability_percent(), 100.0);
+        
+        rig.clock.advance(3_000);
+        rig.system.pause();
+        rig.clock.advance(1_000);
+        assert!((rig.system.availability_percent() - 75.0).abs() < 1e-3);
+    }
+    
+    fn manager(config: SystemConfig) -> SensorManager<MockSensorSource> {
+        SensorManager::with_source(MockSensorSource::new(NORMAL_RAW), config)
+    }
+    
+    #[test]
+    fn pressure_compensation_depends_on_temperature() {
+        let plain = manager(quiet_config());
+        let compensated = manager(SystemConfig { pressure_temp_coeff: 0.05, ..quiet_config() });
+        
+        // Na referência de 25°C as duas conversões coincidem
+        let reference = plain.convert_pressure(154, 25.0).unwrap();
+        assert_eq!(compensated.convert_pressure(154, 25.0).unwrap(), reference);
+        
+        // Sem coeficiente a temperatura não importa; com ele, 0.05 kPa/°C
+        assert_eq!(plain.convert_pressure(154, 35.0).unwrap(), reference);
+        let hot = compensated.convert_pressure(154, 35.0).unwrap();
+        let cold = compensated.convert_pressure(154, 5.0).unwrap();
+        assert!((reference - hot - 0.5).abs() < 1e-4);
+        assert!((cold - reference - 1.0).abs() < 1e-4);
+    }
+    
+    fn alert(kind: AlertKind, code: AlertCode, level: AlertLevel) -> Alert {
+        Alert::new(kind, code, level, "teste", 1.0, 1000)
+    }
+    
+    #[test]
+    fn routing_sends_each_level_to_its_sinks() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        
+        let info = alert(AlertKind::AirQuality, AlertCode::AirQualityRising, AlertLevel::Info);
+        let sinks = rig.system.emit_alerts(&[info], 1000);
+        assert_eq!(sinks, ALERT_SINK_SERIAL);
+        assert!(rig.transport.take_output().starts_with("ALERT[INFO]: teste"));
+        assert_eq!(rig.eeprom.bytes.borrow()[EEPROM_ALERT_LOG_ADDR as usize], 0xFF);
+        
+        let critical = alert(AlertKind::Device, AlertCode::McuOverheat, AlertLevel::Critical);
+        let sinks = rig.system.emit_alerts(&[critical], 1000);
+        assert_eq!(sinks, ALERT_SINK_SERIAL | ALERT_SINK_LED | ALERT_SINK_BUZZER | ALERT_SINK_EEPROM);
+        assert!(rig.transport.output().starts_with("ALERT[CRITICAL]: teste"));
+        let mut record = [0u8; ALERT_RECORD_SIZE as usize];
+        rig.eeprom.read(EEPROM_ALERT_LOG_ADDR, &mut record).unwrap();
+        assert_eq!(record[..2], [AlertKind::Device.log_code(), AlertLevel::Critical.severity()]);
+    }
+    
+    #[test]
+    fn info_alert_does_not_sound_the_buzzer() {
+        // Pré-alerta (Info) de tendência sem ultrapassar o limite
+        let mut rig = rig(SystemConfig { prealert_horizon_ms: 3_600_000, ..quiet_config() });
+        for raw in [624, 640, 655] {
+            rig.source.set([51, 512, raw, 154]);
+            rig.next_reading().unwrap();
+        }
+        
+        assert!(rig.transport.output().contains("ALERT[INFO]"), "{}", rig.transport.output());
+        assert!(!rig.indicators.get().buzzer);
+        assert!(!rig.indicators.get().alert_led);
+    }
+    
+    #[test]
+    fn auto_zero_fires_only_when_armed_and_stable() {
+        let mut stable = AutoZero::new(400.0, 5.0);
+        assert!((0..AUTO_ZERO_WINDOW).all(|_| stable.update(450.0).is_none()));
+        
+        stable.arm();
+        let results: Vec<Option<f32>> = (0..AUTO_ZERO_WINDOW).map(|i| stable.update(450.0 + (i % 3) as f32)).collect();
+        assert!(results[..AUTO_ZERO_WINDOW - 1].iter().all(Option::is_none));
+        let mean = results[AUTO_ZERO_WINDOW - 1].unwrap();
+        assert!((mean - 451.0).abs() < 0.1);
+        assert!(!stable.is_armed());
+        
+        let mut unstable = AutoZero::new(400.0, 5.0);
+        unstable.arm();
+        for i in 0..3 * AUTO_ZERO_WINDOW {
+            let value = if i % 2 == 0 { 420.0 } else { 480.0 };
+            assert!(unstable.update(value).is_none());
+        }
+        assert!(unstable.is_armed());
+    }
+    
+    #[test]
+    fn zero_command_moves_air_quality_to_reference() {
+        let mut rig = rig(quiet_config());
+        rig.source.set([51, 512, 650, 154]);
+        rig.next_reading().unwrap();
+        let before = rig.system.data_storage.get_latest_data().unwrap().air_quality;
+        assert!(before > 450.0);
+        
+        rig.transport.receive("ZERO\n");
+        for _ in 0..AUTO_ZERO_WINDOW {
+            rig.next_reading().unwrap();
+        }
+        assert!(rig.system.sensor_manager.offset(SensorType::AirQuality) < 0.0);
+        
+        rig.next_reading().unwrap();
+        let after = rig.system.data_storage.get_latest_data().unwrap().air_quality;
+        assert!((after - 400.0).abs() < 0.5, "{}", after);
+    }
+    
+    #[test]
+    fn heartbeat_runs_without_readings() {
+        let mut rig = rig(quiet_config());
+        rig.system.pause();
+        rig.transport.take_output();
+        
+        let mut scheduler: Scheduler<MockSystem, 2> = Scheduler::new();
+        let interval = rig.system.heartbeat_interval_ms();
+        scheduler.add(interval, |system| { let _ = system.send_heartbeat(); }).unwrap();
+        scheduler.add(100, |system| { let _ = system.run_monitoring_cycle(); }).unwrap();
+        
+        // 95 s a passos de 100 ms: heartbeats em 0, 30, 60 e 90 s
+        for _ in 0..950 {
+            scheduler.run_pending(rig.clock.now_ms(), &mut rig.system);
+            rig.clock.advance(100);
+        }
+        
+        let output = rig.transport.output();
+        assert_eq!(output, "HB:PAUSED,UP:0\nHB:PAUSED,UP:30000\nHB:PAUSED,UP:60000\nHB:PAUSED,UP:90000\n");
+        assert!(rig.system.data_storage.get_latest_data().is_none());
+    }
+    
+    #[test]
+    fn replay_reproduces_stored_alert() {
+        let config = SystemConfig { alert_hysteresis: [10.0, 1.0, 2.0, 0.5], ..quiet_config() };
+        let mut storage = DataStorage::new();
+        storage.store_data(sample(22.0, 50.0, 400.0, 101.0, 5_000));
+        storage.store_data(sample(22.0, 50.0, 2_000.0, 101.0, 10_000));
+        storage.store_data(sample(22.0, 50.0, 400.0, 101.0, 15_000));
+        
+        // Sistema ao vivo já viu o pico: o canal fica travado pela histerese
+        let mut live = AlertSystem::new(config.clone()).with_hysteresis(config.alert_hysteresis);
+        assert_eq!(live.check_alerts(&sample(22.0, 50.0, 2_000.0, 101.0, 0)).len(), 1);
+        let active_before = live.active_alerts().len();
+        
+        let mut replayed = Vec::new();
+        let total = replay(&live, &storage, |alert| replayed.push((alert.code, alert.timestamp)));
+        
+        assert_eq!(total, 1);
+        assert_eq!(replayed, [(AlertCode::AirQualityHigh, 10_000)]);
+        assert_eq!(live.active_alerts().len(), active_before);
+    }
+    
+    #[test]
+    fn batch_conversion_matches_individual_conversions() {
+        let config = SystemConfig { pressure_temp_coeff: 0.02, humidity_temp_coeff: 0.1, ..quiet_config() };
+        let manager = manager(config);
+        for raw in [NORMAL_RAW, [80, 300, 700, 140], [20, 900, 560, 158]] {
+            let [temperature, humidity, air_quality, pressure] = manager.convert_batch(raw);
+            let temperature = temperature.unwrap();
+            assert_eq!(temperature, manager.convert_temperature(raw[0]).unwrap());
+            assert_eq!(humidity.unwrap(), manager.convert_humidity_compensated(raw[1], temperature).unwrap());
+            assert_eq!(air_quality.unwrap(), manager.convert_air_quality(raw[2]).unwrap());
+            assert_eq!(pressure.unwrap(), manager.convert_pressure(raw[3], temperature).unwrap());
+        }
+        
+        // Falha individual continua individual no lote
+        let readings = manager.convert_batch([51, 512, 624, 0]);
+        assert!(readings[3].is_err() && manager.convert_pressure(0, 24.9).is_err());
+        assert!(readings[..3].iter().all(Result::is_ok));
+    }
+    
+    #[test]
+    fn button_is_debounced_and_cycles_display() {
+        let mut button = DebouncedButton::new(50);
+        // Ruído de contato: alternâncias mais rápidas que o debounce
+        assert!(!button.update(true, 0));
+        assert!(!button.update(false, 10));
+        assert!(!button.update(true, 20));
+        assert!(!button.update(true, 60));
+        assert!(button.update(true, 70));
+        // Segurar não repete; soltar não conta como pressionamento
+        assert!(!button.update(true, 500));
+        assert!(!button.update(false, 510));
+        assert!(!button.update(false, 600));
+        
+        let mut mode = DisplayMode::Temperature;
+        for expected in [DisplayMode::Humidity, DisplayMode::AirQuality, DisplayMode::Pressure, DisplayMode::Temperature] {
+            mode = mode.next();
+            assert_eq!(mode, expected);
+        }
+    }
+    
+    #[test]
+    fn button_press_shows_next_metric() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        rig.indicators.update(|state| state.button = true);
+        for _ in 0..3 {
+            rig.clock.advance(30);
+            rig.system.run_monitoring_cycle().unwrap();
+        }
+        
+        assert_eq!(rig.system.get_display_mode(), DisplayMode::Humidity);
+        assert_eq!(rig.transport.output(), "DISPLAY:HUM=50.0\n");
+    }
+    
+    #[test]
+    fn prometheus_lines_have_names_and_values() {
+        let mut text: heapless::String<128> = heapless::String::new();
+        write_prometheus(&mut text, &sample(23.46, 41.0, 612.25, 100.8, 0)).unwrap();
+        assert_eq!(
+            text.as_str(),
+            "env_temperature_celsius 23.5\n\
+             env_humidity_percent 41.0\n\
+             env_air_quality_ppm 612.2\n\
+             env_pressure_kilopascals 100.8\n"
+        );
+    }
+    
+    #[test]
+    fn average_timestamp_is_the_mean_of_the_window() {
+        let mut storage = DataStorage::new();
+        for timestamp in [1_000, 2_000, 4_000, 9_000] {
+            storage.store_data(sample(20.0, 50.0, 400.0, 101.0, timestamp));
+        }
+        assert_eq!(storage.get_average_data(4).unwrap().timestamp, 4_000);
+        assert_eq!(storage.get_average_data(2).unwrap().timestamp, 6_500);
+        
+        // Janela atravessando a virada do millis
+        let mut storage = DataStorage::new();
+        for timestamp in [u32::MAX - 1_999, u32::MAX - 999, 0, 1_000] {
+            storage.store_data(sample(20.0, 50.0, 400.0, 101.0, timestamp));
+        }
+        assert_eq!(storage.get_average_data(4).unwrap().timestamp, u32::MAX - 499);
+        assert_eq!(storage.get_running_average().unwrap().timestamp, u32::MAX - 499);
+    }
+    
+    #[test]
+    fn window_larger_than_history_uses_only_stored_samples() {
+        let mut storage = DataStorage::new();
+        storage.store_data(sample(20.0, 50.0, 400.0, 102.0, 1_000));
+        storage.store_data(sample(22.0, 60.0, 900.0, 100.0, 2_000));
+        storage.store_data(sample(27.0, 40.0, 500.0, 101.0, 3_000));
+        
+        // Slots ainda zerados do buffer não entram na conta
+        let average = storage.get_average_data(10).unwrap();
+        assert_eq!(average.temperature, 23.0);
+        assert_eq!(average.humidity, 50.0);
+        assert_eq!(average.air_quality, 600.0);
+        assert_eq!(average.pressure, 101.0);
+        assert_eq!(average.timestamp, 2_000);
+        
+        let min = storage.get_min_data(10).unwrap();
+        assert_eq!((min.temperature, min.humidity, min.air_quality, min.pressure), (20.0, 40.0, 400.0, 100.0));
+        assert_eq!(min.timestamp, 3_000);
+        
+        let max = storage.get_max_data(10).unwrap();
+        assert_eq!((max.temperature, max.humidity, max.air_quality, max.pressure), (27.0, 60.0, 900.0, 102.0));
+        assert_eq!(max.timestamp, 3_000);
+    }
+    
+    #[test]
+    fn custom_reading_interval_is_respected() {
+        let mut rig = rig(SystemConfig { reading_interval: 1_000, ..quiet_config() });
+        for _ in 0..3 {
+            rig.clock.advance(999);
+            rig.system.run_monitoring_cycle().unwrap();
+            rig.clock.advance(1);
+            rig.system.run_monitoring_cycle().unwrap();
+        }
+        
+        let timestamps: Vec<u32> = rig.system.data_storage.iter().map(|data| data.timestamp).collect();
+        assert_eq!(timestamps, [1_000, 2_000, 3_000]);
+        assert_eq!(rig.system.expected_sample_rate_hz(), 1.0);
+    }
+    
+    #[test]
+    fn alert_frequency_filters_by_level() {
+        let mut alerts = AlertSystem::new(quiet_config());
+        let normal = sample(22.0, 50.0, 400.0, 101.0, 0);
+        let warning = sample(22.0, 50.0, 2_000.0, 101.0, 0); // Qualidade do ar: Warning
+        let critical = sample(40.0, 50.0, 400.0, 101.0, 0);  // Temperatura: Critical
+        for data in [&critical, &normal, &warning, &normal, &warning, &critical, &normal, &warning, &normal, &normal] {
+            alerts.check_alerts(data);
+        }
+        
+        assert_eq!(alerts.get_alert_frequency(), 50.0);
+        assert_eq!(alerts.alert_frequency_for(AlertLevel::Info), 50.0);
+        assert_eq!(alerts.alert_frequency_for(AlertLevel::Warning), 50.0);
+        assert_eq!(alerts.alert_frequency_for(AlertLevel::Critical), 20.0);
+    }
+    
+    #[test]
+    fn reading_over_budget_is_discarded() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        rig.source.set_read_delay(1_500);
+        rig.next_reading().unwrap();
+        
+        assert_eq!(rig.system.get_cycle_timeout_count(), 1);
+        assert_eq!(rig.system.get_last_cycle_timeout(), Some(11_500));
+        assert_eq!(rig.transport.output(), "");
+        assert_eq!(rig.system.data_storage.len(), 1);
+        
+        // Dentro do orçamento o ciclo volta ao normal
+        rig.source.set_read_delay(10);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.system.get_cycle_timeout_count(), 1);
+        assert_eq!(rig.system.data_storage.len(), 2);
+    }
+    
+    #[test]
+    fn fixed_point_round_trip() {
+        let data = sample(25.37, 60.0, 412.3, 101.32, 123_456);
+        let encoded = encode_fixed_point(&data);
+        assert_eq!(encoded, [2537, 6000, 4123, 10132]);
+        
+        let decoded = decode_fixed_point(&encoded, data.timestamp);
+        assert!((decoded.temperature - 25.37).abs() < 1e-4);
+        assert!((decoded.air_quality - 412.3).abs() < 1e-3);
+        assert!((decoded.pressure - 101.32).abs() < 1e-4);
+        assert_eq!(decoded.timestamp, 123_456);
+        assert_eq!(encode_fixed_point(&sample(-10.5, 0.0, 0.0, 30.0, 0))[0], -1050);
+    }
+    
+    #[test]
+    fn fixed_point_line_format() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.system.communication.set_output_format(OutputFormat::FixedPoint);
+        rig.system.communication.send_data(&sample(25.37, 60.0, 412.3, 101.32, 7)).unwrap();
+        assert_eq!(rig.transport.output(), "F:2537,6000,4123,10132,7\n");
+    }
+    
+    fn storage_with(timestamps: impl IntoIterator<Item = u32>) -> DataStorage {
+        let mut storage = DataStorage::new();
+        for timestamp in timestamps {
+            storage.store_data(sample(20.0, 50.0, 400.0, 101.0, timestamp));
+        }
+        storage
+    }
+    
+    #[test]
+    fn oldest_sample_in_partial_and_full_buffer() {
+        assert!(DataStorage::new().oldest().is_none());
+        
+        let partial = storage_with((1..=3).map(|i| i * 1000));
+        assert_eq!(partial.oldest().unwrap().timestamp, 1000);
+        
+        let exactly_full = storage_with((1..=50).map(|i| i * 1000));
+        assert_eq!(exactly_full.oldest().unwrap().timestamp, 1000);
+        
+        // 53 amostras: as três primeiras já foram sobrescritas
+        let wrapped = storage_with((1..=53).map(|i| i * 1000));
+        assert_eq!(wrapped.oldest().unwrap().timestamp, 4000);
+        assert_eq!(wrapped.get_latest_data().unwrap().timestamp, 53_000);
+    }
+    
+    #[test]
+    fn sea_level_pressure_matches_standard_atmosphere() {
+        // Atmosfera padrão a 1000 m: 89.876 kPa e 8.5°C -> 101.325 kPa
+        let station = sample(8.5, 50.0, 400.0, 89.876, 0);
+        assert!((station.sea_level_pressure(1000.0) - 101.325).abs() < 0.05);
+        
+        let sea_level = sample(15.0, 50.0, 400.0, 101.325, 0);
+        assert_eq!(sea_level.sea_level_pressure(0.0), 101.325);
+    }
+    
+    #[test]
+    fn swapped_channels_raise_wiring_warning() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        assert_eq!(rig.system.check_wiring().unwrap(), 0);
+        assert_eq!(rig.transport.output(), "");
+        
+        // Temperatura e umidade trocadas no conector
+        rig.source.set([512, 51, 624, 154]);
+        assert_eq!(rig.system.check_wiring().unwrap(), 2);
+        assert_eq!(
+            rig.transport.output(),
+            "WARN: possível troca de cabos no canal TEMP\nWARN: possível troca de cabos no canal HUM\n"
+        );
+    }
+    
+    #[test]
+    fn rgb_duty_cycles_for_status_and_severity() {
+        let levels = [None, Some(AlertLevel::Info), Some(AlertLevel::Warning), Some(AlertLevel::Critical)];
+        for level in levels {
+            assert_eq!(rgb_for(&SystemStatus::Calibrating, level), (0, 0, 255));
+            assert_eq!(rgb_for(&SystemStatus::Paused, level), (255, 0, 255));
+            assert_eq!(rgb_for(&SystemStatus::Error, level), (255, 0, 0));
+        }
+        
+        let running: Vec<(u8, u8, u8)> = levels.iter().map(|level| rgb_for(&SystemStatus::Running, *level)).collect();
+        assert_eq!(running, [(0, 255, 0), (0, 255, 0), (255, 255, 0), (255, 0, 0)]);
+    }
+    
+    #[test]
+    fn rgb_led_follows_the_cycle() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        assert_eq!(rig.indicators.get().rgb, (0, 255, 0));
+        
+        rig.source.set([51, 512, 754, 154]);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.indicators.get().rgb, (255, 255, 0));
+        
+        rig.system.pause();
+        assert_eq!(rig.indicators.get().rgb, (255, 0, 255));
+    }
+    
+    #[test]
+    fn mcusr_decodes_to_reset_cause() {
+        assert_eq!(ResetCause::from_mcusr(0x01), ResetCause::PowerOn);
+        assert_eq!(ResetCause::from_mcusr(0x02), ResetCause::External);
+        assert_eq!(ResetCause::from_mcusr(0x04), ResetCause::BrownOut);
+        assert_eq!(ResetCause::from_mcusr(0x08), ResetCause::Watchdog);
+        assert_eq!(ResetCause::from_mcusr(0x00), ResetCause::Unknown);
+        // Vários flags: power-on vence, depois watchdog
+        assert_eq!(ResetCause::from_mcusr(0x0F), ResetCause::PowerOn);
+        assert_eq!(ResetCause::from_mcusr(0x0E), ResetCause::Watchdog);
+        // Bits fora dos quatro flags são ignorados
+        assert_eq!(ResetCause::from_mcusr(0xF2), ResetCause::External);
+        
+        assert!(ResetCause::Watchdog.is_unexpected() && ResetCause::BrownOut.is_unexpected());
+        assert!(!ResetCause::PowerOn.is_unexpected() && !ResetCause::External.is_unexpected());
+    }
+    
+    #[test]
+    fn running_average_matches_full_scan_after_many_stores() {
+        let mut storage = DataStorage::new();
+        for i in 0..10_000u32 {
+            let wobble = (i % 17) as f32 * 0.37;
+            storage.store_data(sample(20.0 + wobble, 45.0 + wobble, 400.0 + 30.0 * wobble, 101.0 + wobble / 100.0, i * 1000));
+            
+            if i % 997 == 0 || i == 9_999 {
+                let running = storage.get_running_average().unwrap();
+                let scanned = storage.get_average_data(50).unwrap();
+                for sensor in ALL_SENSORS {
+                    let (a, b) = (running.value(sensor), scanned.value(sensor));
+                    assert!((a - b).abs() <= 1e-4 * b.abs(), "{:?}: {} != {}", sensor, a, b);
+                }
+                assert_eq!(running.timestamp, scanned.timestamp);
+                assert_eq!(storage.check_invariants(), Ok(()));
+            }
+        }
+    }
+    
+    #[test]
+    fn noise_stats_of_known_sequence() {
+        let stats = noise_stats([2u16, 4, 4, 4, 5, 5, 7, 9].into_iter()).unwrap();
+        assert_eq!((stats.min, stats.max), (2, 9));
+        assert!((stats.mean - 5.0).abs() < 1e-6);
+        assert!((stats.std_dev - 2.0).abs() < 1e-6);
+        assert!(noise_stats(core::iter::empty()).is_none());
+    }
+    
+    #[test]
+    fn noise_command_reports_channel_stats() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.transport.receive("NOISE AQ 16\n");
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.transport.output(), "NOISE:AQ,N:16,MIN:624,MAX:624,MEAN:624.0,STD:0.00\nOK\n");
+    }
+    
+    
+    // Timestamps das linhas de dados na ordem em que saíram
+    fn sent_timestamps(output: &str) -> Vec<u32> {
+        output
+            .lines()
+            .filter_map(|line| line.split("kPa,T:").nth(1))
+            .map(|rest| rest.split('*').next().unwrap().parse().unwrap())
+            .collect()
+    }
+    
+    #[test]
+    fn failed_samples_are_resent_after_recovery() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.next_reading().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [5000]);
+        
+        rig.transport.set_failing(true);
+        assert!(matches!(rig.next_reading(), Err(SensorError::CommunicationError)));
+        assert!(matches!(rig.next_reading(), Err(SensorError::CommunicationError)));
+        assert_eq!(rig.system.data_storage.pending_count(), 2);
+        // As leituras continuam sendo armazenadas com o link fora
+        assert_eq!(rig.system.data_storage.get_latest_data().unwrap().timestamp, 15000);
+        
+        rig.transport.set_failing(false);
+        rig.next_reading().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [20000, 10000, 15000]);
+        assert_eq!(rig.system.data_storage.pending_count(), 0);
+        
+        rig.next_reading().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [25000]);
+    }
+    
+    
+    #[test]
+    fn gap_between_valid_readings_is_interpolated() {
+        let mut storage = DataStorage::new();
+        storage.store_data(sample(20.0, 40.0, 400.0, 100.0, 1000));
+        storage.store_missing(2000);
+        storage.store_missing(3000);
+        // Sem a leitura posterior ainda não há o que interpolar
+        assert_eq!(storage.interpolate_gaps(), 0);
+        assert_eq!(storage.sample_flags(1), SAMPLE_MISSING);
+        
+        storage.store_data(sample(23.0, 46.0, 700.0, 103.0, 4000));
+        assert_eq!(storage.interpolate_gaps(), 2);
+        
+        let first = storage.slot(1);
+        assert_eq!(first.timestamp, 2000);
+        assert!((first.temperature - 21.0).abs() < 1e-4);
+        assert!((first.humidity - 42.0).abs() < 1e-4);
+        assert!((first.air_quality - 500.0).abs() < 1e-3);
+        assert!((first.pressure - 101.0).abs() < 1e-4);
+        let second = storage.slot(2);
+        assert_eq!(second.timestamp, 3000);
+        assert!((second.temperature - 22.0).abs() < 1e-4);
+        assert!((second.air_quality - 600.0).abs() < 1e-3);
+        
+        assert_eq!(storage.sample_flags(0), 0);
+        assert_eq!(storage.sample_flags(1), SAMPLE_INTERPOLATED);
+        assert_eq!(storage.sample_flags(2), SAMPLE_INTERPOLATED);
+        assert_eq!(storage.sample_flags(3), 0);
+        // Já preenchidas, não são interpoladas de novo
+        assert_eq!(storage.interpolate_gaps(), 0);
+        // A média passa a usar os valores interpolados
+        assert!((storage.get_running_average().unwrap().temperature - 21.5).abs() < 1e-4);
+    }
+    
+    
+    #[test]
+    fn calinfo_reports_coefficients_after_calibration() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        rig.transport.receive("CALINFO\n");
+        rig.system.run_monitoring_cycle().unwrap();
+        let before = rig.transport.take_output();
+        assert!(before.contains("CAL:TEMP,GAIN:1.0000,OFFSET:0.000,LAST:-\n"), "{}", before);
+        assert_eq!(before.lines().filter(|line| line.starts_with("CAL:")).count(), 4);
+        
+        rig.clock.set(7000);
+        let (gain, offset) = rig.system.calibrate_sensor(SensorType::Temperature, 0.0, 100, 50.0, 300).unwrap();
+        rig.transport.receive("CALINFO\n");
+        rig.system.run_monitoring_cycle().unwrap();
+        let after = rig.transport.take_output();
+        let expected = format!("CAL:TEMP,GAIN:{:.4},OFFSET:{:.3},LAST:7000\n", gain, offset);
+        assert!(after.contains(&expected), "{}", after);
+        // Os demais canais seguem sem calibração
+        assert!(after.contains("CAL:HUM,GAIN:1.0000,OFFSET:0.000,LAST:-\n"), "{}", after);
+    }
+    
+    
+    #[test]
+    fn samples_per_cycle_are_averaged_into_one_sample() {
+        let config = SystemConfig { samples_per_cycle: 4, ..quiet_config() };
+        let source = MockSensorSource::new(NORMAL_RAW);
+        let mut averaged = SensorManager::with_source(source.clone(), config.clone());
+        source.queue(&[[48, 500, 600, 150], [50, 510, 620, 152], [52, 514, 628, 156], [54, 524, 648, 158]]);
+        let data = averaged.read_all_sensors(&MockClock::default()).unwrap();
+        assert_eq!(source.reads(), 4);
+        
+        // Média arredondada dos brutos: [51, 512, 624, 154]
+        let mut single = manager(quiet_config());
+        let expected = single.read_all_sensors(&MockClock::default()).unwrap();
+        assert_eq!(data.temperature, expected.temperature);
+        assert_eq!(data.humidity, expected.humidity);
+        assert_eq!(data.air_quality, expected.air_quality);
+        assert_eq!(data.pressure, expected.pressure);
+        
+        // No sistema, as N leituras rápidas geram uma única amostra armazenada
+        let mut rig = rig(config);
+        let before = rig.source.reads();
+        rig.next_reading().unwrap();
+        assert_eq!(rig.source.reads() - before, 4);
+        assert_eq!(rig.system.data_storage.len(), 1);
+    }
+    
+    #[test]
+    fn filtered_read_median_ignores_spikes() {
+        let spikes = [
+            [51, 512, 624, 154],
+            [51, 510, 900, 154],
+            [200, 512, 620, 154],
+            [52, 514, 628, 153],
+            [50, 512, 100, 155],
+        ];
+        let source = MockSensorSource::new(NORMAL_RAW);
+        let mut filtered = SensorManager::with_source(source.clone(), quiet_config());
+        source.queue(&spikes);
+        // Pedido par é arredondado para o ímpar seguinte: 5 leituras
+        let data = filtered.read_all_sensors_filtered(4, &MockClock::default()).unwrap();
+        assert_eq!(source.reads(), 5);
+        
+        let expected = manager(quiet_config()).read_all_sensors(&MockClock::default()).unwrap();
+        assert_eq!(data.temperature, expected.temperature);
+        assert_eq!(data.humidity, expected.humidity);
+        assert_eq!(data.air_quality, expected.air_quality);
+        assert_eq!(data.pressure, expected.pressure);
+        
+        // A média das mesmas leituras seria puxada pelos picos
+        let averaged = average_raw_readings(spikes.into_iter()).unwrap();
+        assert_eq!(averaged[0], 81);
+    }
+    
+    #[test]
+    fn clock_drift_ppm_follows_known_rate() {
+        let mut rig = rig(quiet_config());
+        let mut rtc = 1_700_000_000;
+        rig.system.record_rtc_time(rtc);
+        assert_eq!(rig.system.estimate_clock_drift_ppm(), None);
+        
+        // millis() adiantando 50 ppm: 3 600 180 ms por hora do RTC
+        for _ in 0..10 {
+            rig.clock.advance(3_600_180);
+            rtc += 3_600;
+            rig.system.record_rtc_time(rtc);
+        }
+        let ppm = rig.system.estimate_clock_drift_ppm().unwrap();
+        assert!((ppm - 50.0).abs() < 0.5, "{}", ppm);
+        
+        // Atrasando 20 ppm, com o millis() dando a volta no meio
+        let mut estimator = ClockDriftEstimator::new();
+        estimator.record(u32::MAX - 1_000_000, 0);
+        estimator.record((u32::MAX - 1_000_000).wrapping_add(99_998_000), 100_000);
+        let ppm = estimator.estimate_ppm().unwrap();
+        assert!((ppm + 20.0).abs() < 0.5, "{}", ppm);
+    }
+    
+    
+    #[test]
+    fn mcu_temperature_from_adc() {
+        // Datasheet: ~1.22 LSB/°C com o zero em torno de 324 LSB
+        assert!(mcu_temperature_from_raw(324).abs() < 0.5);
+        assert!((mcu_temperature_from_raw(355) - 25.16).abs() < 0.01);
+        assert!((mcu_temperature_from_raw(446) - 100.0 - mcu_temperature_from_raw(324)).abs() < 0.01);
+        assert!(mcu_temperature_from_raw(300) < 0.0);
+        
+        let mut manager = manager(quiet_config());
+        let health = manager.read_device_health();
+        assert_eq!(health.mcu_temperature, mcu_temperature_from_raw(355));
+    }
+    
+    #[test]
+    fn assisted_calibration_fits_known_line() {
+        let mut calibration = AssistedCalibration::new();
+        calibration.add_pair(SensorType::Temperature, 20.0, 20.0);
+        assert_eq!(calibration.solve(), None);
+        // Mesmo valor medido: não há reta
+        calibration.add_pair(SensorType::Temperature, 20.0, 21.0);
+        assert_eq!(calibration.solve(), None);
+        
+        // Referência = 1.1 * medido - 2
+        calibration.add_pair(SensorType::Humidity, 30.0, 31.0);
+        assert_eq!(calibration.pair_count(), 1);
+        for measured in [40.0, 50.0, 60.0, 70.0] {
+            calibration.add_pair(SensorType::Humidity, measured, 1.1 * measured - 2.0);
+        }
+        let (gain, offset) = calibration.solve().unwrap();
+        assert!((gain - 1.1).abs() < 1e-4, "{}", gain);
+        assert!((offset + 2.0).abs() < 1e-3, "{}", offset);
+        
+        // Pelos comandos: REF a cada leitura e REF FIT aplica o ajuste
+        let mut rig = rig(quiet_config());
+        for raw in [51, 53, 55] {
+            rig.source.set([raw, 512, 624, 154]);
+            rig.next_reading().unwrap();
+            let measured = rig.system.data_storage.get_latest_data().unwrap().temperature;
+            rig.transport.receive(&format!("REF TEMP {}\n", 1.1 * measured - 2.0));
+            rig.system.run_monitoring_cycle().unwrap();
+        }
+        rig.transport.take_output();
+        rig.transport.receive("REF FIT\n");
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.transport.take_output(), "OK\n");
+        let info = rig.system.sensor_manager.calibration_info(SensorType::Temperature);
+        assert!((info.gain - 1.1).abs() < 1e-3, "{}", info.gain);
+        assert!((info.offset + 2.0).abs() < 0.05, "{}", info.offset);
+    }
+    
+    
+    #[test]
+    fn readings_inside_blackout_are_discarded() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        // Relé chaveado em 9000: leituras suspensas até 12000
+        rig.clock.set(9000);
+        rig.system.suppress_readings_for(3000);
+        for now in [10_000, 11_000, 11_999] {
+            rig.clock.set(now);
+            let reads = rig.source.reads();
+            rig.system.run_monitoring_cycle().unwrap();
+            assert_eq!(rig.source.reads(), reads);
+        }
+        assert_eq!(rig.system.get_discarded_reading_count(), 3);
+        assert_eq!(rig.system.data_storage.len(), 1);
+        assert_eq!(rig.transport.output(), "");
+        
+        // A leitura adiada sai assim que o blackout termina
+        rig.clock.set(12_000);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.system.data_storage.get_latest_data().unwrap().timestamp, 12_000);
+        assert!(rig.transport.take_output().contains("kPa,T:12000*"));
+        assert_eq!(rig.system.get_discarded_reading_count(), 3);
+    }
+    
+    
+    #[test]
+    fn gas_sensor_past_lifetime_asks_for_replacement() {
+        let mut rig = rig(SystemConfig { gas_sensor_lifetime_hours: 2, ..quiet_config() });
+        rig.transport.take_output();
+        
+        rig.clock.advance(3_600_000);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.system.sensor_age_hours(), 1);
+        assert!(!rig.transport.take_output().contains("Substituir sensor de gás"));
+        
+        rig.clock.advance(3_600_000);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.system.sensor_age_hours(), 2);
+        let output = rig.transport.take_output();
+        assert!(output.contains("ALERT[WARNING]: Substituir sensor de gás - Value: 2.0"), "{}", output);
+        
+        // As horas ficam gravadas para sobreviver a um reset
+        let mut stored = [0u8; 4];
+        rig.eeprom.read(EEPROM_SENSOR_HOURS_ADDR, &mut stored).unwrap();
+        assert_eq!(u32::from_le_bytes(stored), 2);
+    }
+    
+    
+    #[test]
+    fn alert_conditions_map_to_codes() {
+        let cases = [
+            (sample(40.0, 50.0, 400.0, 101.0, 1000), AlertCode::TemperatureHigh, 2),
+            (sample(0.0, 50.0, 400.0, 101.0, 1000), AlertCode::TemperatureLow, 3),
+            (sample(20.0, 95.0, 400.0, 101.0, 1000), AlertCode::HumidityHigh, 4),
+            (sample(20.0, 5.0, 400.0, 101.0, 1000), AlertCode::HumidityLow, 5),
+            (sample(20.0, 50.0, 1500.0, 101.0, 1000), AlertCode::AirQualityHigh, 1),
+            (sample(20.0, 50.0, 400.0, 115.0, 1000), AlertCode::PressureHigh, 16),
+            (sample(20.0, 50.0, 400.0, 20.0, 1000), AlertCode::PressureLow, 17),
+        ];
+        for (data, code, number) in cases {
+            let alerts = AlertSystem::new(quiet_config()).check_alerts(&data);
+            assert_eq!(alerts.len(), 1);
+            assert_eq!(alerts[0].code, code);
+            assert_eq!(alerts[0].code as u8, number);
+        }
+    }
+    
+    #[test]
+    fn code_format_keeps_text_mode_available() {
+        let transport = MockTransport::default();
+        let mut communication = CommunicationSystem::with_parts(
+            transport.clone(),
+            MockIndicators::default(),
+            MockEeprom::default(),
+            ResetCause::PowerOn,
+        );
+        let critical = alert(AlertKind::Temperature, AlertCode::TemperatureHigh, AlertLevel::Critical);
+        
+        communication.send_alert(&critical).unwrap();
+        assert_eq!(transport.take_output(), "ALERT[CRITICAL]: teste - Value: 1.0 at 1000\n");
+        
+        communication.set_alert_format(AlertFormat::Code);
+        communication.send_alert(&critical).unwrap();
+        let warning = alert(AlertKind::AirQuality, AlertCode::AirQualityHigh, AlertLevel::Warning);
+        communication.send_alert(&warning).unwrap();
+        assert_eq!(transport.take_output(), "AC:2,2,1.0,1000\nAC:1,1,1.0,1000\n");
+        
+        communication.set_alert_format(AlertFormat::Text);
+        communication.send_alert(&warning).unwrap();
+        assert_eq!(transport.take_output(), "ALERT[WARNING]: teste - Value: 1.0 at 1000\n");
+    }
+    
+    
+    #[test]
+    fn paused_system_takes_no_readings_until_resumed() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        rig.system.pause();
+        let reads = rig.source.reads();
+        for _ in 0..3 {
+            rig.next_reading().unwrap();
+        }
+        assert_eq!(rig.source.reads(), reads);
+        assert_eq!(rig.system.data_storage.len(), 1);
+        assert_eq!(rig.transport.take_output(), "");
+        
+        rig.system.resume();
+        assert!(matches!(rig.system.get_system_status(), SystemStatus::Running));
+        rig.next_reading().unwrap();
+        assert_eq!(rig.source.reads(), reads + 1);
+        assert_eq!(rig.system.data_storage.len(), 2);
+        assert!(rig.transport.take_output().starts_with("T:"));
+        
+        // resume sem pausa não muda o estado
+        rig.system.resume();
+        assert!(matches!(rig.system.get_system_status(), SystemStatus::Running));
+    }
+    
+    
+    #[test]
+    fn asymmetric_deadband_reports_rise_but_not_equal_fall() {
+        // Temperatura: subida de 0.5°C já é reportada, descida só a partir de 2°C
+        let steady = AsymmetricDeadband { up: 1e6, down: 1e6 };
+        let temperature = AsymmetricDeadband { up: 0.5, down: 2.0 };
+        let mut filter = TransmitFilter::new([temperature, steady, steady, steady]);
+        
+        assert!(filter.should_send(&sample(20.0, 50.0, 400.0, 101.0, 0)));
+        assert!(filter.should_send(&sample(21.0, 50.0, 400.0, 101.0, 1)));
+        assert!(!filter.should_send(&sample(20.0, 50.0, 400.0, 101.0, 2)));
+        assert!(!filter.should_send(&sample(19.5, 50.0, 400.0, 101.0, 3)));
+        assert!(filter.should_send(&sample(19.0, 50.0, 400.0, 101.0, 4)));
+        
+        // No sistema a descida fica armazenada mas não é transmitida
+        let deadbands = [temperature, steady, steady, steady];
+        let mut rig = rig(SystemConfig { deadbands, ..quiet_config() });
+        rig.next_reading().unwrap();
+        rig.source.set([53, 512, 624, 154]);
+        rig.transport.take_output();
+        rig.next_reading().unwrap();
+        assert!(rig.transport.take_output().starts_with("T:25.9C"));
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.transport.take_output(), "");
+        assert_eq!(rig.system.data_storage.len(), 3);
+        assert_eq!(rig.system.data_storage.pending_count(), 0);
+    }
+    
+    
+    #[test]
+    fn threshold_beyond_physical_range_is_invalid() {
+        for metric in ThresholdMetric::ALL {
+            let (min, max) = metric.valid_range();
+            for value in [max + 1.0, min - 1.0] {
+                let mut thresholds = ThresholdConfig::default();
+                thresholds.set(metric, value);
+                assert_eq!(thresholds.validate(), Err("limite fora da faixa do sensor"), "{:?} {}", metric, value);
+            }
+        }
+        
+        // No limite físico ainda é aceito
+        let mut thresholds = ThresholdConfig::default();
+        thresholds.set(ThresholdMetric::TempMax, 125.0);
+        thresholds.set(ThresholdMetric::HumidityMax, 100.0);
+        thresholds.set(ThresholdMetric::AirQualityMax, 10000.0);
+        assert_eq!(thresholds.validate(), Ok(()));
+        
+        // 150°C não é medível pelo LM35: a configuração inteira é rejeitada
+        let mut config = quiet_config();
+        config.thresholds.temp_max = 150.0;
+        assert_eq!(config.validate(), Err("limite fora da faixa do sensor"));
+    }
+    
+    
+    #[test]
+    fn weekend_profile_alerts_on_weekday_normal_reading() {
+        // Sábado 18/11/2023 e segunda 20/11/2023, 00h UTC
+        let saturday = 1_700_265_600;
+        let monday = saturday + 2 * 86_400;
+        assert_eq!(day_of_week(saturday), 6);
+        assert_eq!(day_of_week(monday), 1);
+        
+        // Escritório vazio no fim de semana: limite de CO2 mais baixo
+        let weekday = ThresholdConfig { air_quality_max: 1000.0, ..ThresholdConfig::default() };
+        let weekend = ThresholdConfig { air_quality_max: 300.0, ..ThresholdConfig::default() };
+        
+        for (rtc, expect_alert) in [(monday, false), (saturday, true)] {
+            let mut rig = rig(quiet_config());
+            rig.system.set_alert_profiles(weekday, weekend);
+            rig.system.record_rtc_time(rtc);
+            rig.transport.take_output();
+            rig.next_reading().unwrap();
+            
+            let output = rig.transport.take_output();
+            assert_eq!(output.contains("ALERT[WARNING]: Qualidade do ar"), expect_alert, "{}", output);
+            assert_eq!(rig.indicators.get().alert_led, expect_alert);
+        }
+    }
+    
+    
+    #[test]
+    fn active_alerts_track_current_conditions() {
+        let mut alerts = AlertSystem::new(quiet_config()).with_hysteresis([10.0, 1.0, 2.0, 0.5]);
+        let codes = |alerts: &AlertSystem| alerts.active_alerts().iter().map(|alert| alert.code).collect::<Vec<_>>();
+        
+        alerts.check_alerts(&sample(40.0, 95.0, 400.0, 101.0, 1000));
+        assert_eq!(codes(&alerts), [AlertCode::TemperatureHigh, AlertCode::HumidityHigh]);
+        
+        // Condições persistentes mantêm o instante em que começaram
+        alerts.check_alerts(&sample(41.0, 96.0, 400.0, 101.0, 2000));
+        let active = alerts.active_alerts();
+        assert_eq!(active.len(), 2);
+        assert!(active.iter().all(|alert| alert.timestamp == 1000));
+        assert_eq!(active[0].value, 41.0);
+        
+        // Umidade normalizada sai; temperatura ainda dentro da folga de 1°C continua
+        alerts.check_alerts(&sample(34.5, 80.0, 400.0, 101.0, 3000));
+        assert_eq!(codes(&alerts), [AlertCode::TemperatureHigh]);
+        
+        alerts.check_alerts(&sample(30.0, 80.0, 400.0, 101.0, 4000));
+        assert!(alerts.active_alerts().is_empty());
+    }
+    
+    #[test]
+    fn hysteresis_emits_one_alert_while_oscillating_around_threshold() {
+        let mut alerts = AlertSystem::new(quiet_config()).with_hysteresis([10.0, 1.0, 2.0, 0.5]);
+        let mut emitted = 0;
+        
+        // Limite de 1000 ppm, leituras entrando e saindo por poucos ppm
+        for (i, air_quality) in [1005.0, 995.0, 1003.0, 998.0, 1001.0, 992.0, 1004.0].into_iter().enumerate() {
+            emitted += alerts.check_alerts(&sample(22.0, 50.0, air_quality, 101.0, i as u32 * 1000)).len();
+        }
+        assert_eq!(emitted, 1);
+        assert_eq!(alerts.active_alerts().len(), 1);
+        
+        // Só depois de entrar 10 ppm na faixa o canal volta a alertar
+        assert!(alerts.check_alerts(&sample(22.0, 50.0, 985.0, 101.0, 8000)).is_empty());
+        assert!(alerts.active_alerts().is_empty());
+        assert_eq!(alerts.check_alerts(&sample(22.0, 50.0, 1002.0, 101.0, 9000)).len(), 1);
+    }
+    
+    
+    #[test]
+    fn lookup_table_interpolates_and_clamps() {
+        let table = LookupTable::new([(0.0, 0.0), (10.0, 100.0), (20.0, 150.0)]).unwrap();
+        // Dentro de cada segmento
+        assert_eq!(table.lookup(5.0), 50.0);
+        assert_eq!(table.lookup(15.0), 125.0);
+        // Exato nos pontos
+        assert_eq!(table.lookup(10.0), 100.0);
+        // Saturação nos extremos
+        assert_eq!(table.lookup(-5.0), 0.0);
+        assert_eq!(table.lookup(50.0), 150.0);
+        
+        // Tamanho mínimo: um único ponto vira constante
+        let single = LookupTable::new([(3.0, 42.0)]).unwrap();
+        assert_eq!(single.lookup(-1.0), 42.0);
+        assert_eq!(single.lookup(3.0), 42.0);
+        assert_eq!(single.lookup(9.0), 42.0);
+        let two = LookupTable::new([(0.0, 10.0), (4.0, 30.0)]).unwrap();
+        assert_eq!(two.lookup(1.0), 15.0);
+        
+        assert!(LookupTable::<0>::new([]).is_none());
+        assert!(LookupTable::new([(0.0, 0.0), (0.0, 1.0)]).is_none());
+        assert!(LookupTable::new([(1.0, 0.0), (0.0, 1.0)]).is_none());
+        assert!(LookupTable::new([(0.0, f32::NAN), (1.0, 1.0)]).is_none());
+    }
+    
+    
+    #[test]
+    fn adc_read_time_is_measured_per_channel() {
+        let clock = MockClock::default();
+        let source = MockSensorSource::with_clock(NORMAL_RAW, clock.clone());
+        let mut manager = SensorManager::with_source(source.clone(), quiet_config());
+        assert_eq!(manager.adc_read_time_us(), None);
+        
+        // 2 ms por varredura dos 4 canais = 500 µs por canal
+        source.set_read_delay(2);
+        manager.read_all_sensors(&clock).unwrap();
+        assert_eq!(manager.adc_read_time_us(), Some(500));
+        
+        // Com 3 leituras por amostra o tempo total é dividido por 12
+        let config = SystemConfig { samples_per_cycle: 3, ..quiet_config() };
+        let mut manager = SensorManager::with_source(source.clone(), config);
+        source.set_read_delay(6);
+        manager.read_all_sensors(&clock).unwrap();
+        assert_eq!(manager.adc_read_time_us(), Some(1500));
+        
+        // Pelo sistema, com o relógio compartilhado pelo rig
+        let mut rig = rig(quiet_config());
+        rig.source.set_read_delay(1);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.system.adc_read_time_us(), Some(250));
+    }
+    
+    
+    #[test]
+    fn alert_history_rle_round_trip() {
+        let normal = sample(20.0, 50.0, 400.0, 101.0, 0);
+        let critical = sample(40.0, 50.0, 400.0, 101.0, 0);
+        let warning = sample(20.0, 95.0, 400.0, 101.0, 0);
+        let cycles = [
+            &normal, &normal, &normal, &critical, &normal, &normal,
+            &normal, &normal, &warning, &normal, &normal, &normal,
+        ];
+        
+        let mut alerts = AlertSystem::new(quiet_config());
+        for data in cycles {
+            alerts.check_alerts(data);
+        }
+        
+        // Só os 10 ciclos mais recentes ficam no histórico
+        let pairs = alerts.encode_alert_history_rle();
+        assert_eq!(pairs, [(0, 1), (3, 1), (0, 4), (2, 1), (0, 3)]);
+        
+        let history = decode_alert_history_rle(&pairs).unwrap();
+        let mut expected = [None; 10];
+        expected[1] = Some(AlertLevel::Critical);
+        expected[6] = Some(AlertLevel::Warning);
+        assert_eq!(history, expected);
+        
+        assert!(decode_alert_history_rle(&[(0, 11)]).is_none());
+        assert!(decode_alert_history_rle(&[(9, 1)]).is_none());
+        assert_eq!(decode_alert_history_rle(&[]).unwrap().len(), 0);
+    }
+    
+    
+    #[test]
+    fn air_quality_alerts_only_after_its_warmup() {
+        let mut config = quiet_config();
+        config.warmup_ms = [0, 0, 20_000, 0];
+        config.thresholds.air_quality_max = 300.0;
+        let mut rig = rig(config);
+        rig.transport.take_output();
+        
+        assert!(rig.system.is_metric_ready(SensorType::Temperature));
+        assert!(!rig.system.is_metric_ready(SensorType::AirQuality));
+        
+        // Temperatura alta alerta já na primeira leitura; os ~400 ppm não
+        rig.source.set([80, 512, 624, 154]);
+        rig.next_reading().unwrap();
+        let output = rig.transport.take_output();
+        assert!(output.contains("ALERT[CRITICAL]: Temperatura"), "{}", output);
+        assert!(!output.contains("Qualidade do ar"), "{}", output);
+        
+        rig.source.set(NORMAL_RAW);
+        for _ in 0..2 {
+            rig.next_reading().unwrap();
+            assert!(!rig.transport.take_output().contains("ALERT["));
+        }
+        
+        // 20 s depois do boot o MQ-135 está aquecido
+        rig.next_reading().unwrap();
+        assert_eq!(rig.clock.now_ms(), 20_000);
+        assert!(rig.system.is_metric_ready(SensorType::AirQuality));
+        assert!(rig.transport.take_output().contains("ALERT[WARNING]: Qualidade do ar"));
+    }
+    
+    
+    fn snapshot() -> StateSnapshot {
+        StateSnapshot {
+            reading_interval: 7000,
+            thresholds: ThresholdConfig { temp_max: 30.0, air_quality_max: 800.0, ..ThresholdConfig::default() },
+            gains: [1.1, 1.0, 1.0, 0.9],
+            offsets: [-2.0, 0.0, 5.0, 0.0],
+            calibration_factors: [1.0, 1.0, 1.2, 1.0],
+            alert_hysteresis: [0.0, 1.0, 2.0, 0.0],
+            latest: Some(sample(24.9, 50.0, 400.0, 100.8, 5000)),
+            cycle_timeouts: 3,
+            discarded_readings: 7,
+            sensor_hours: 42,
+        }
+    }
+    
+    fn assert_same_state(a: &StateSnapshot, b: &StateSnapshot) {
+        assert_eq!(a.reading_interval, b.reading_interval);
+        assert_eq!(a.thresholds, b.thresholds);
+        assert_eq!(a.gains, b.gains);
+        assert_eq!(a.offsets, b.offsets);
+        assert_eq!(a.calibration_factors, b.calibration_factors);
+        assert_eq!(a.alert_hysteresis, b.alert_hysteresis);
+        assert_eq!((a.cycle_timeouts, a.discarded_readings, a.sensor_hours), (b.cycle_timeouts, b.discarded_readings, b.sensor_hours));
+    }
+    
+    #[test]
+    fn state_snapshot_round_trip() {
+        let original = snapshot();
+        let blob = original.to_bytes();
+        assert_eq!(&blob[0..4], &[b'M', b'A', STATE_VERSION, 0x01]);
+        
+        let decoded = StateSnapshot::from_bytes(&blob).unwrap();
+        assert_same_state(&decoded, &original);
+        assert_eq!(decoded.latest.unwrap().to_bytes(), original.latest.as_ref().unwrap().to_bytes());
+        
+        // Restaurado num sistema novo, o estado exportado é o mesmo
+        let mut rig = rig(quiet_config());
+        rig.system.restore_state(&blob).unwrap();
+        let exported = StateSnapshot::from_bytes(&rig.system.export_state()).unwrap();
+        assert_same_state(&exported, &original);
+        assert!(exported.latest.is_none());
+    }
+    
+    #[test]
+    fn state_snapshot_rejects_other_versions() {
+        let mut blob = snapshot().to_bytes();
+        blob[2] = 1;
+        assert_eq!(StateSnapshot::from_bytes(&blob).unwrap_err(), StateError::UnsupportedVersion(1));
+        
+        let mut rig = rig(quiet_config());
+        assert_eq!(rig.system.restore_state(&blob), Err(StateError::UnsupportedVersion(1)));
+        let current = StateSnapshot::from_bytes(&rig.system.export_state()).unwrap();
+        assert_eq!(current.reading_interval, 5000);
+        assert_eq!(current.thresholds, quiet_config().thresholds);
+        
+        // Um byte do payload alterado cai no checksum
+        let mut blob = snapshot().to_bytes();
+        blob[10] ^= 0xFF;
+        assert_eq!(StateSnapshot::from_bytes(&blob).unwrap_err(), StateError::ChecksumMismatch);
+    }
+    
+    
+    #[test]
+    fn rising_trend_predicts_threshold_crossing() {
+        // Qualidade do ar subindo 2 ppm/s: 10 ppm a cada leitura de 5 s
+        let mut storage = DataStorage::new();
+        for i in 0..10u32 {
+            storage.store_data(sample(22.0, 50.0, 400.0 + 10.0 * i as f32, 101.0, 1000 + i * 5000));
+        }
+        
+        // Da última leitura (490 ppm) até 1000 ppm: 510 / 2 = 255 s
+        let eta = storage.predict_threshold_crossing(SensorType::AirQuality, 1000.0).unwrap();
+        assert!(eta.abs_diff(255_000) <= 5, "{}", eta);
+        
+        // Limite já ultrapassado ou métrica sem tendência: sem previsão
+        assert_eq!(storage.predict_threshold_crossing(SensorType::AirQuality, 450.0), None);
+        assert_eq!(storage.predict_threshold_crossing(SensorType::Temperature, 30.0), None);
+        assert_eq!(storage_with([1000]).predict_threshold_crossing(SensorType::AirQuality, 1000.0), None);
+    }
+    
+    
+    // CF=1: 13/20/24 µg/m³; atmosférico: 12/18/21 µg/m³; checksum 0x033B
+    const PMS_FRAME: [u8; pms5003::FRAME_SIZE] = [
+        0x42, 0x4D, 0x00, 0x1C, 0x00, 0x0D, 0x00, 0x14,
+        0x00, 0x18, 0x00, 0x0C, 0x00, 0x12, 0x00, 0x15,
+        0x07, 0xD4, 0x02, 0x40, 0x00, 0x64, 0x00, 0x0A,
+        0x00, 0x02, 0x00, 0x00, 0x97, 0x00, 0x03, 0x3B,
+    ];
+    
+    #[test]
+    fn pms5003_frame_decodes_and_rejects_bad_checksum() {
+        let expected = pms5003::ParticulateMatter { pm1_0: 12, pm2_5: 18, pm10: 21 };
+        assert_eq!(pms5003::parse_frame(&PMS_FRAME), Ok(expected));
+        
+        let mut corrupted = PMS_FRAME;
+        corrupted[13] = 0x13;
+        assert_eq!(pms5003::parse_frame(&corrupted), Err(pms5003::FrameError::ChecksumMismatch));
+        let mut bad_length = PMS_FRAME;
+        bad_length[3] = 0x1D;
+        assert_eq!(pms5003::parse_frame(&bad_length), Err(pms5003::FrameError::BadLength));
+        
+        // Pela UART: lixo antes do cabeçalho é descartado
+        let mut reader = pms5003::FrameReader::new();
+        let mut results = Vec::new();
+        for &byte in [0x00, 0x42].iter().chain(&PMS_FRAME).chain(&corrupted) {
+            results.extend(reader.push(byte));
+        }
+        assert_eq!(results, [Ok(expected), Err(pms5003::FrameError::ChecksumMismatch)]);
+    }
+    
+    
+    #[test]
+    fn on_change_policy_is_silent_in_steady_state() {
+        let mut rig = rig(quiet_config());
+        rig.system.set_output_policy(OutputPolicy::OnChange);
+        rig.transport.take_output();
+        
+        for _ in 0..5 {
+            rig.next_reading().unwrap();
+        }
+        assert_eq!(rig.transport.take_output(), "");
+        assert_eq!(rig.system.data_storage.len(), 5);
+        
+        // Condição nova: uma transição, e nada enquanto ela persiste
+        rig.source.set([51, 512, 754, 154]);
+        rig.next_reading().unwrap();
+        let output = rig.transport.take_output();
+        assert!(output.starts_with("ALERT_ON:1,Qualidade do ar"), "{}", output);
+        assert_eq!(output.lines().count(), 1);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.transport.take_output(), "");
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        assert!(rig.transport.take_output().starts_with("ALERT_OFF:1,"));
+        
+        rig.system.pause();
+        assert_eq!(rig.transport.take_output(), "STATUS:PAUSED\n");
+    }
+    
+    
+    #[test]
+    fn humidity_compensation_corrects_away_from_reference() {
+        let plain = manager(quiet_config());
+        let compensated = manager(SystemConfig { humidity_temp_coeff: 0.2, ..quiet_config() });
+        
+        let reference = plain.convert_humidity(512).unwrap();
+        assert_eq!(compensated.convert_humidity_compensated(512, 25.0).unwrap(), reference);
+        assert_eq!(plain.convert_humidity_compensated(512, 40.0).unwrap(), reference);
+        
+        // O sensor lê alto no calor e baixo no frio: 0.2 %RH/°C em sentido oposto
+        let hot = compensated.convert_humidity_compensated(512, 40.0).unwrap();
+        let cold = compensated.convert_humidity_compensated(512, 10.0).unwrap();
+        assert!(hot < reference && cold > reference);
+        assert!((reference - hot - 3.0).abs() < 1e-4);
+        assert!((cold - reference - 3.0).abs() < 1e-4);
+    }
+    
+    
+    #[test]
+    fn scheduler_runs_tasks_at_their_periods() {
+        let mut scheduler: Scheduler<[u32; 3], 3> = Scheduler::new();
+        scheduler.add(250, |counts| counts[0] += 1).unwrap();
+        scheduler.add(1000, |counts| counts[1] += 1).unwrap();
+        scheduler.add_after(3000, 0, |counts| counts[2] += 1).unwrap();
+        assert_eq!(scheduler.add(1, |_| {}), Err(SchedulerFull));
+        
+        // 10 s em passos de 50 ms (0 a 9950 ms)
+        let mut counts = [0; 3];
+        let mut executed = 0;
+        for step in 0..200 {
+            executed += scheduler.run_pending(step * 50, &mut counts);
+        }
+        
+        assert_eq!(counts, [40, 10, 3]);
+        assert_eq!(executed, 53);
+    }
+    
+    
+    #[test]
+    fn outlier_far_from_recent_mean_is_rejected() {
+        assert!(is_relative_outlier(37.5, 25.0, 0.2));
+        assert!(!is_relative_outlier(27.5, 25.0, 0.2));
+        assert!(!is_relative_outlier(37.5, 25.0, 0.0));
+        
+        let mut config = quiet_config();
+        config.outlier_tolerance[SensorType::Temperature.index()] = 0.2;
+        let mut rig = rig(config);
+        for _ in 0..3 {
+            rig.next_reading().unwrap();
+        }
+        rig.transport.take_output();
+        
+        // 37.6°C: ~50% acima da média de 24.9°C
+        rig.source.set([77, 512, 624, 154]);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.system.get_rejected_outlier_count(), 1);
+        assert_eq!(rig.system.data_storage.sample_flags(3), SAMPLE_MISSING);
+        assert_eq!(rig.transport.take_output(), "");
+        
+        // 27.3°C: ~10% acima, aceito, e a lacuna é interpolada
+        rig.source.set([56, 512, 624, 154]);
+        rig.next_reading().unwrap();
+        assert_eq!(rig.system.get_rejected_outlier_count(), 1);
+        let latest = rig.system.data_storage.get_latest_data().unwrap();
+        assert!((latest.temperature - 27.3).abs() < 0.1);
+        assert!(rig.transport.take_output().starts_with("T:27.3C"));
+        assert_eq!(rig.system.data_storage.sample_flags(3), SAMPLE_INTERPOLATED);
+    }
+    
+    
+    #[test]
+    fn demo_script_produces_expected_sequence() {
+        let clock = MockClock::default();
+        let transport = MockTransport::default();
+        let indicators = MockIndicators::default();
+        let config = demo_config();
+        let source = DemoSensorSource::new(DemoScript::new(&DEMO_STEPS), clock.clone());
+        let communication = CommunicationSystem::with_parts(
+            transport.clone(),
+            indicators.clone(),
+            MockEeprom::default(),
+            ResetCause::PowerOn,
+        );
+        let mut system = EnvironmentalMonitoringSystem::from_parts(
+            SensorManager::with_source(source, config.clone()),
+            communication,
+            clock.clone(),
+            config,
+        )
+        .unwrap();
+        system.set_output_policy(OutputPolicy::OnChange);
+        transport.take_output();
+        
+        // Uma volta do roteiro: (passo em vigor, LED de alerta) a cada leitura
+        let mut sequence = Vec::new();
+        for _ in 0..13 {
+            clock.advance(5000);
+            system.run_monitoring_cycle().unwrap();
+            let step = system.sensor_manager.source.current_step().label;
+            sequence.push((step, indicators.get().alert_led));
+        }
+        
+        assert_eq!(
+            sequence,
+            [
+                ("AQUECIMENTO", false),
+                ("NORMAL", false), ("NORMAL", false), ("NORMAL", false), ("NORMAL", false),
+                ("PICO_AQ", true), ("PICO_AQ", true), ("PICO_AQ", true),
+                ("RECUPERACAO", false), ("RECUPERACAO", false), ("RECUPERACAO", false), ("RECUPERACAO", false),
+                ("AQUECIMENTO", false),
+            ]
+        );
+        
+        let output = transport.take_output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", output);
+        assert!(lines[0].starts_with("ALERT_ON:1,"));
+        assert!(lines[1].starts_with("ALERT_OFF:1,"));
+    }
+    
+    
+    #[test]
+    fn dead_sensor_needs_consecutive_successes_to_recover() {
+        let mut tracker = SensorHealthTracker::new();
+        assert_eq!(tracker.record(0, false, 3, 3), None);
+        assert_eq!(tracker.record(0, false, 3, 3), None);
+        assert_eq!(tracker.record(0, false, 3, 3), Some(SensorHealth::Dead));
+        
+        // Sucessos intercalados com falhas não reabilitam
+        for ok in [true, true, false, true, false, true, true] {
+            assert_eq!(tracker.record(0, ok, 3, 3), None);
+            assert!(!tracker.is_alive(0));
+        }
+        assert_eq!(tracker.record(0, true, 3, 3), Some(SensorHealth::Alive));
+        assert!(tracker.is_alive(0));
+        // Os outros canais não são afetados
+        assert!(tracker.is_alive(1));
+        
+        // No sistema: LM35 saturado no ADC conta como falha do canal
+        let mut rig = rig(SystemConfig { dead_after_failures: 2, recovery_readings: 2, ..quiet_config() });
+        rig.next_reading().unwrap();
+        let saturated = [ADC_MAX_RAW, 512, 624, 154];
+        rig.source.set(saturated);
+        // Um canal com erro descarta a amostra: nada de valor repetido como leitura
+        assert!(matches!(rig.next_reading(), Err(SensorError::ReadError)));
+        assert_eq!(rig.system.sensor_manager.channel_status(), [false, true, true, true]);
+        assert_eq!(rig.system.sensor_health(SensorType::Temperature), SensorHealth::Alive);
+        for raw in [saturated, NORMAL_RAW, saturated, NORMAL_RAW] {
+            rig.source.set(raw);
+            let _ = rig.next_reading();
+            assert_eq!(rig.system.sensor_health(SensorType::Temperature), SensorHealth::Dead);
+        }
+        rig.next_reading().unwrap();
+        assert_eq!(rig.system.sensor_health(SensorType::Temperature), SensorHealth::Alive);
+    }
+    
+    #[test]
+    fn air_quality_uncertainty_follows_raw_spread() {
+        let manager = manager(quiet_config());
+        let ppm_at = |raw: u16| manager.convert_air_quality(raw).unwrap();
+        
+        // Média 624 LSB com desvio padrão de 4 LSB
+        let (ppm, band) = manager.convert_air_quality_with_uncertainty(&[620, 628, 620, 628]).unwrap();
+        assert!((ppm - ppm_at(624)).abs() < 1e-3);
+        let per_lsb = (ppm_at(625) - ppm_at(623)) / 2.0;
+        assert!((band - 4.0 * per_lsb).abs() < 0.02 * band, "{} {}", band, per_lsb);
+        
+        // O dobro do desvio dobra a faixa; sem variação não há incerteza
+        let (_, wide) = manager.convert_air_quality_with_uncertainty(&[616, 632, 616, 632]).unwrap();
+        assert!((wide - 2.0 * band).abs() < 0.02 * wide);
+        assert_eq!(manager.convert_air_quality_with_uncertainty(&[624; 8]).unwrap().1, 0.0);
+        assert!(manager.convert_air_quality_with_uncertainty(&[]).is_err());
+    }
+    
+    
+    #[test]
+    fn device_id_prefixes_telemetry_and_alerts() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        assert!(rig.transport.take_output().lines().all(|line| !line.starts_with('@')));
+        
+        rig.transport.receive("SET ID 7\n");
+        rig.source.set([51, 512, 754, 154]);
+        rig.next_reading().unwrap();
+        
+        let output = rig.transport.take_output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.iter().any(|line| line.starts_with("@7:T:24.9C,")), "{}", output);
+        assert!(lines.iter().any(|line| line.starts_with("@7:ALERT[WARNING]:")), "{}", output);
+        assert!(lines.iter().all(|line| line.starts_with("@7:")), "{}", output);
+        
+        // O ID fica na EEPROM e vale após um reset
+        let communication = CommunicationSystem::with_parts(
+            MockTransport::default(),
+            MockIndicators::default(),
+            rig.eeprom.clone(),
+            ResetCause::External,
+        );
+        assert_eq!(communication.device_id(), Some(7));
+    }
+    
+    
+    #[test]
+    fn sample_rate_from_known_spacing() {
+        // Sem dados suficientes
+        assert_eq!(DataStorage::new().effective_sample_rate_hz(10), None);
+        assert_eq!(storage_with([1000]).effective_sample_rate_hz(10), None);
+        assert_eq!(storage_with([1000, 1000]).effective_sample_rate_hz(10), None);
+        
+        // Uma leitura a cada 500 ms = 2 Hz
+        let storage = storage_with((0..5).map(|i| 1000 + i * 500));
+        assert_eq!(storage.effective_sample_rate_hz(10), Some(2.0));
+        
+        // Só a janela pedida conta: o intervalo mudou de 5 s para 250 ms
+        let storage = storage_with([0, 5000, 10_000, 10_250, 10_500, 10_750]);
+        assert_eq!(storage.effective_sample_rate_hz(4), Some(4.0));
+        assert_eq!(storage.effective_sample_rate_hz(2), Some(4.0));
+        
+        // Pelo sistema, com a leitura padrão a cada 5 s
+        let mut rig = rig(quiet_config());
+        for _ in 0..3 {
+            rig.next_reading().unwrap();
+        }
+        assert_eq!(rig.system.effective_sample_rate_hz(), Some(0.2));
+    }
+    
+    
+    #[test]
+    fn load_resistance_scales_sensor_resistance_and_ppm() {
+        let rl_10 = manager(quiet_config());
+        let rl_20 = manager(SystemConfig { load_resistance_kohm: 20.0, ..quiet_config() });
+        
+        // Rs = RL * (Vc - V) / V: 2.5 V no divisor dá Rs = RL
+        assert!((rl_10.gas_sensor_resistance_kohm(2.5) - 10.0).abs() < 1e-4);
+        assert!((rl_20.gas_sensor_resistance_kohm(2.5) - 20.0).abs() < 1e-4);
+        
+        // Com o mesmo R0, Rs/R0 dobra e o ppm segue a curva: fator 2^B
+        let ppm_10 = rl_10.convert_air_quality(624).unwrap();
+        let ppm_20 = rl_20.convert_air_quality(624).unwrap();
+        assert!(ppm_20 < ppm_10);
+        assert!((ppm_20 / ppm_10 - 2f32.powf(MQ135_CURVE_B)).abs() < 1e-4);
+        
+        // Recalibrando R0 no ar de referência a diferença some
+        let mut rl_20 = rl_20;
+        let r0 = rl_20.calibrate_r0(624, ppm_10).unwrap();
+        assert!((r0 - 20.0).abs() < 0.01, "{}", r0);
+        assert!((rl_20.convert_air_quality(624).unwrap() - ppm_10).abs() < 0.1);
+    }
+    
+    
+    #[test]
+    fn only_unexpected_resets_increment_the_persisted_counter() {
+        let eeprom = MockEeprom::default();
+        let boot = |cause| {
+            CommunicationSystem::with_parts(MockTransport::default(), MockIndicators::default(), eeprom.clone(), cause)
+                .count_unexpected_reset()
+                .unwrap()
+        };
+        
+        // EEPROM apagada conta como zero
+        assert_eq!(boot(ResetCause::PowerOn), 0);
+        assert_eq!(boot(ResetCause::Watchdog), 1);
+        assert_eq!(boot(ResetCause::External), 1);
+        assert_eq!(boot(ResetCause::Watchdog), 2);
+        assert_eq!(boot(ResetCause::BrownOut), 3);
+        assert_eq!(boot(ResetCause::PowerOn), 3);
+        
+        let mut count = [0u8; 4];
+        eeprom.read(EEPROM_UNEXPECTED_RESETS_ADDR, &mut count).unwrap();
+        assert_eq!(u32::from_le_bytes(count), 3);
+    }
+    
+    
+    #[test]
+    fn two_point_humidity_calibration_fits_salt_references() {
+        let mut manager = manager(quiet_config());
+        // Sensor lendo ~3% alto: 36% sobre MgCl2 e 79% sobre NaCl
+        let (raw_33, raw_75) = (369, 809);
+        let uncalibrated = |raw: u16| raw as f32 / ADC_RESOLUTION * 100.0;
+        assert!((manager.convert_humidity(raw_33).unwrap() - uncalibrated(raw_33)).abs() < 1e-3);
+        
+        manager.calibrate_humidity_two_point(raw_33, raw_75, 1000).unwrap();
+        assert!((manager.convert_humidity(raw_33).unwrap() - SALT_MGCL2_RH).abs() < 1e-3);
+        assert!((manager.convert_humidity(raw_75).unwrap() - SALT_NACL_RH).abs() < 1e-3);
+        
+        // Leituras intermediárias seguem a mesma reta
+        let slope = (SALT_NACL_RH - SALT_MGCL2_RH) / (uncalibrated(raw_75) - uncalibrated(raw_33));
+        for raw in [450, 589, 700] {
+            let expected = SALT_MGCL2_RH + (uncalibrated(raw) - uncalibrated(raw_33)) * slope;
+            assert!((manager.convert_humidity(raw).unwrap() - expected).abs() < 1e-3);
+        }
+        
+        // Pontos invertidos são recusados
+        assert!(matches!(manager.calibrate_humidity_two_point(raw_75, raw_33, 2000), Err(SensorError::CalibrationError)));
+    }
+    
+    
+    #[test]
+    fn tx_queue_defers_output_until_pumped() {
+        // Com a fila ligada o ciclo só enfileira; pump_serial drena byte a byte
+        let mut rig = rig(quiet_config());
+        rig.system.set_tx_queue(true);
+        rig.transport.take_output();
+        rig.next_reading().unwrap();
+        assert_eq!(rig.transport.output(), "");
+        let pending = rig.system.tx_pending();
+        assert!(pending > 0);
+        
+        let mut pumped = 0;
+        while rig.system.pump_serial().is_ok() {
+            pumped += 1;
+        }
+        assert_eq!(pumped, pending);
+        assert!(rig.transport.take_output().starts_with("T:24.9C,"));
+    }
+    
+    #[test]
+    fn full_tx_queue_drops_newest_lines_whole() {
+        let mut rig = rig(quiet_config());
+        rig.system.set_tx_queue(true);
+        rig.transport.take_output();
+        let communication = &mut rig.system.communication;
+        
+        // 42 x "OK\n" ocupam 126 dos 127 bytes; as próximas linhas não cabem
+        for _ in 0..42 {
+            communication.send_response(Ok(())).unwrap();
+        }
+        assert_eq!(communication.tx_pending(), TX_QUEUE_SIZE - 2);
+        communication.send_response(Ok(())).unwrap();
+        communication.send_response(Err("busy")).unwrap();
+        
+        // Nada bloqueou nem saiu pela serial; a fila ficou como estava
+        assert_eq!(rig.transport.output(), "");
+        assert_eq!(communication.tx_pending(), TX_QUEUE_SIZE - 2);
+        assert_eq!(communication.tx_dropped(), 3 + 9);
+        
+        while communication.pump_serial().is_ok() {}
+        assert_eq!(rig.transport.take_output(), "OK\n".repeat(42));
+        
+        // Com a fila drenada as linhas voltam a entrar
+        communication.send_response(Err("busy")).unwrap();
+        while communication.pump_serial().is_ok() {}
+        assert_eq!(rig.transport.take_output(), "ERR busy\n");
+        assert_eq!(rig.system.tx_dropped(), 12);
+    }
+    
+    // Alimenta a hora 03h com leituras que oscilam pouco e depois mede uma leitura
+    // dentro dos limites absolutos mas longe do normal daquela hora
+    fn diurnal_rig(sigma: f32, wall_clock: bool) -> Rig {
+        let mut rig = rig(SystemConfig { diurnal_sigma: sigma, ..quiet_config() });
+        if wall_clock {
+            rig.clock.set_unix(3 * 3_600);
+        }
+        let history: std::vec::Vec<[u16; 4]> = (0..DIURNAL_MIN_SAMPLES + 10)
+            .map(|i| [51, 512, if i % 2 == 0 { 620 } else { 628 }, 154])
+            .collect();
+        rig.source.queue(&history);
+        for _ in 0..history.len() {
+            rig.next_reading().unwrap();
+        }
+        rig.transport.take_output();
+        rig
+    }
+    
+    #[test]
+    fn reading_unusual_for_its_hour_raises_diurnal_alert() {
+        let mut rig = diurnal_rig(3.0, true);
+        assert!(rig.system.get_daily_profile().hourly_average(3).is_some());
+        
+        rig.source.set([51, 512, 660, 154]);
+        rig.next_reading().unwrap();
+        let output = rig.transport.take_output();
+        assert!(output.contains("Qualidade do ar atípica para o horário"), "{}", output);
+        // Só a qualidade do ar destoou: temperatura e umidade seguem o perfil
+        assert!(!output.contains("Temperatura atípica"));
+        assert!(!output.contains("Umidade atípica"));
+        // Nada de alerta por limite absoluto: a leitura continua "normal"
+        assert!(!output.contains("ALERT[WARNING]") && !output.contains("ALERT[CRITICAL]"));
+    }
+    
+    #[test]
+    fn diurnal_alert_needs_sigma_and_wall_clock() {
+        for (sigma, wall_clock) in [(0.0, true), (3.0, false)] {
+            let mut rig = diurnal_rig(sigma, wall_clock);
+            rig.source.set([51, 512, 660, 154]);
+            rig.next_reading().unwrap();
+            assert!(!rig.transport.take_output().contains("atípica para o horário"));
+        }
+    }
+    
+    #[test]
+    fn base64_round_trip_covers_padding_cases() {
+        // Vetores da RFC 4648: 0, 1 e 2 caracteres de padding
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            let text: heapless::String<8> = encode_base64(plain.as_bytes()).unwrap();
+            assert_eq!(text.as_str(), encoded);
+            let bytes: heapless::Vec<u8, 6> = decode_base64(encoded).unwrap();
+            assert_eq!(bytes.as_slice(), plain.as_bytes());
+        }
+        
+        // Todos os valores de byte, em tamanhos que caem em cada resto de 3
+        let payload: std::vec::Vec<u8> = (0..=255u8).rev().collect();
+        for len in [1, 2, 3, 4, 5, 20, 64, 255, 256] {
+            let text: heapless::String<344> = encode_base64(&payload[..len]).unwrap();
+            assert_eq!(text.len(), len.div_ceil(3) * 4);
+            let bytes: heapless::Vec<u8, 256> = decode_base64(&text).unwrap();
+            assert_eq!(bytes.as_slice(), &payload[..len]);
+        }
+        
+        // Frame de telemetria: 20 bytes cabem exatos nos 28 caracteres
+        let data = sample(24.5, 55.0, 420.0, 101.3, 123_456);
+        let text: heapless::String<28> = encode_base64(&data.to_bytes()).unwrap();
+        let bytes: heapless::Vec<u8, 20> = decode_base64(&text).unwrap();
+        assert_eq!(bytes.as_slice(), &data.to_bytes()[..]);
+    }
+    
+    #[test]
+    fn base64_rejects_malformed_input_and_small_buffers() {
+        assert!(decode_base64::<8>("Zm9").is_none());
+        assert!(decode_base64::<8>("Zg==Zm9v").is_none());
+        assert!(decode_base64::<8>("Z===").is_none());
+        assert!(decode_base64::<8>("Zm9*").is_none());
+        assert!(decode_base64::<2>("Zm9v").is_none());
+        assert!(encode_base64::<3>(b"foo").is_none());
+    }
+    
+    // Conta as chamadas por tipo de erro; o teste fica com um clone do contador
+    #[derive(Clone, Default)]
+    struct CountingHandler {
+        counts: Rc<RefCell<[u32; 4]>>,
+    }
+    
+    impl CountingHandler {
+        fn slot(err: &SensorError) -> usize {
+            match err {
+                SensorError::ReadError => 0,
+                SensorError::CalibrationError => 1,
+                SensorError::CommunicationError => 2,
+                SensorError::ConfigError => 3,
+            }
+        }
+        
+        fn count(&self, err: SensorError) -> u32 {
+            self.counts.borrow()[Self::slot(&err)]
+        }
+    }
+    
+    impl ErrorHandler for CountingHandler {
+        fn on_error(&mut self, err: &SensorError, _status: &SystemStatus) -> ErrorAction {
+            self.counts.borrow_mut()[Self::slot(err)] += 1;
+            ErrorAction::Continue
+        }
+    }
+    
+    #[test]
+    fn handler_sees_every_failed_cycle_by_error_type() {
+        let handler = CountingHandler::default();
+        let mut rig = rig_with_handler(quiet_config(), handler.clone());
+        
+        rig.source.set([ADC_MAX_RAW, 512, 624, 154]);
+        assert!(matches!(rig.next_reading(), Err(SensorError::ReadError)));
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        
+        rig.transport.set_failing(true);
+        for _ in 0..3 {
+            assert!(matches!(rig.next_reading(), Err(SensorError::CommunicationError)));
+        }
+        rig.transport.set_failing(false);
+        rig.next_reading().unwrap();
+        
+        assert_eq!(handler.count(SensorError::ReadError), 1);
+        assert_eq!(handler.count(SensorError::CommunicationError), 3);
+        assert_eq!(handler.count(SensorError::CalibrationError), 0);
+        assert_eq!(handler.count(SensorError::ConfigError), 0);
+    }
+    
+    #[test]
+    fn readings_run_faster_than_transmissions() {
+        let mut rig = rig(SystemConfig { transmit_interval_ms: 20_000, ..quiet_config() });
+        rig.transport.take_output();
+        
+        for _ in 0..12 {
+            rig.next_reading().unwrap();
+        }
+        
+        // 12 leituras em 60 s, mas só uma transmissão a cada 20 s
+        assert_eq!(rig.source.reads(), 12);
+        assert_eq!(rig.system.data_storage.get_latest_data().unwrap().timestamp, 60_000);
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [5_000, 25_000, 45_000]);
+        assert_eq!(rig.system.transmit_interval_ms(), 20_000);
+    }
+    
+    #[test]
+    fn reinit_brings_a_jammed_sensor_back() {
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        
+        // Reinicialização manual: o canal volta a converter sem reiniciar o sistema
+        rig.source.jam(SensorType::Temperature);
+        assert!(rig.system.reinit_sensor(SensorType::Temperature).is_ok());
+        assert_eq!(rig.source.reinits(), 1);
+        assert!(rig.system.sensor_manager.channel_status()[SensorType::Temperature.index()]);
+        rig.next_reading().unwrap();
+        
+        // Travado até morrer: o próprio ciclo tenta uma reinicialização
+        rig.source.jam(SensorType::Temperature);
+        for _ in 0..5 {
+            let _ = rig.next_reading();
+        }
+        assert_eq!(rig.system.sensor_health(SensorType::Temperature), SensorHealth::Dead);
+        assert_eq!(rig.source.reinits(), 2);
+        
+        // Leituras válidas de novo, e após recovery_readings o sensor volta a valer
+        for _ in 0..3 {
+            rig.next_reading().unwrap();
+        }
+        assert_eq!(rig.system.sensor_health(SensorType::Temperature), SensorHealth::Alive);
+        let latest = rig.system.data_storage.get_latest_data().unwrap();
+        assert!((latest.temperature - 24.9).abs() < 0.5);
+    }
+    
+    #[test]
+    fn vcc_from_bandgap_reading() {
+        // 1.1V lido contra AVcc: quanto menor a alimentação, maior a leitura
+        assert!((vcc_from_bandgap(225) - 5.006).abs() < 0.001);
+        assert!((vcc_from_bandgap(341) - 3.303).abs() < 0.001);
+        assert!((vcc_from_bandgap(1024) - 1.1).abs() < 0.001);
+        assert_eq!(vcc_from_bandgap(0), 0.0);
+        
+        let mut fixed = manager(quiet_config());
+        assert_eq!(fixed.read_device_health().supply_voltage, vcc_from_bandgap(225));
+        // Sem compensação a referência do ADC não muda
+        assert_eq!(fixed.measure_vcc(), vcc_from_bandgap(225));
+        assert_eq!(fixed.vref(), ADC_VREF);
+        
+        let mut compensated = manager(SystemConfig { vref_compensation: true, ..quiet_config() });
+        compensated.measure_vcc();
+        assert_eq!(compensated.vref(), vcc_from_bandgap(225));
+    }
+    
+    #[test]
+    fn derived_metrics_match_individual_functions() {
+        let config = SystemConfig { sea_level_pressure_kpa: 102.0, ..quiet_config() };
+        let data = sample(31.0, 70.0, 420.0, 98.5, 1000);
+        let particulates = pms5003::parse_frame(&PMS_FRAME).ok();
+        
+        let derived = data.derived(&config, particulates);
+        assert_eq!(derived.dew_point, data.dew_point());
+        assert_eq!(derived.heat_index, data.heat_index());
+        assert_eq!(derived.absolute_humidity, data.absolute_humidity());
+        assert_eq!(derived.aqi, Some(pm25_aqi(18)));
+        assert_eq!(derived.altitude_m, data.altitude(102.0));
+        
+        // Sem medição de partículas não há AQI; o resto não muda
+        let without_pm = data.derived(&config, None);
+        assert_eq!(without_pm.aqi, None);
+        assert_eq!(without_pm.dew_point, derived.dew_point);
+        
+        // No sistema: calculadas sobre a última leitura armazenada
+        let mut rig = rig(quiet_config());
+        assert_eq!(rig.system.derived_metrics(), None);
+        rig.next_reading().unwrap();
+        let latest = rig.system.data_storage.get_latest_data().unwrap();
+        assert_eq!(rig.system.derived_metrics(), Some(latest.derived(&quiet_config(), None)));
+    }
+    
+    #[test]
+    fn alert_events_mark_their_samples() {
+        // Sem amostra não há o que marcar
+        let mut storage = DataStorage::new();
+        assert!(!storage.mark_event(EVENT_USER));
+        
+        let mut storage = storage_with([1000, 2000, 3000]);
+        assert!(storage.mark_event(EVENT_USER));
+        assert!(storage.mark_event(EVENT_ALERT_WARNING));
+        assert_eq!(storage.event_flags(2), EVENT_USER | EVENT_ALERT_WARNING);
+        assert_eq!(storage.find_events(EVENT_USER).as_slice(), [2]);
+        assert!(storage.find_events(EVENT_ALERT_CRITICAL).is_empty());
+        
+        // Slot sobrescrito perde o marcador
+        for timestamp in 4..=53 {
+            storage.store_data(sample(20.0, 50.0, 400.0, 101.0, timestamp * 1000));
+        }
+        assert!(storage.find_events(EVENT_USER).is_empty());
+        
+        // No sistema: cada alerta marca a amostra que o disparou
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.source.set([51, 512, 754, 154]);
+        rig.next_reading().unwrap();
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        rig.source.set([80, 512, 624, 154]);
+        rig.next_reading().unwrap();
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        
+        let storage = &rig.system.data_storage;
+        let timestamps = |mask| -> Vec<u32> {
+            storage.find_events(mask).iter().map(|&index| storage.data_buffer[index].timestamp).collect()
+        };
+        assert_eq!(timestamps(EVENT_ALERT_WARNING), [10_000]);
+        assert_eq!(timestamps(EVENT_ALERT_CRITICAL), [20_000]);
+        assert_eq!(timestamps(EVENT_ALERT_WARNING | EVENT_ALERT_CRITICAL), [10_000, 20_000]);
+    }
+    
+    #[test]
+    fn temperature_at_adc_full_scale_is_flagged() {
+        assert!(is_adc_saturated(ADC_MAX_RAW));
+        assert!(is_adc_saturated(ADC_MAX_RAW - ADC_SATURATION_MARGIN));
+        assert!(!is_adc_saturated(ADC_MAX_RAW - ADC_SATURATION_MARGIN - 1));
+        assert!(matches!(manager(quiet_config()).convert_temperature(ADC_MAX_RAW), Err(SensorError::ReadError)));
+        
+        let mut rig = rig(quiet_config());
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        let saturated = [ADC_MAX_RAW, 512, 624, 154];
+        rig.source.set(saturated);
+        let _ = rig.next_reading();
+        assert!(rig.system.sensor_manager.is_saturated(SensorType::Temperature));
+        assert!(!rig.system.sensor_manager.is_saturated(SensorType::Humidity));
+        assert_eq!(rig.system.sensor_manager.channel_status(), [false, true, true, true]);
+        assert!(rig.transport.take_output().contains("Temperatura no fundo de escala do ADC"));
+        
+        // Um alerta por episódio, não um por ciclo
+        let _ = rig.next_reading();
+        assert!(!rig.transport.take_output().contains("fundo de escala"));
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        assert!(!rig.system.sensor_manager.is_saturated(SensorType::Temperature));
+        
+        rig.source.set(saturated);
+        let _ = rig.next_reading();
+        assert!(rig.transport.take_output().contains("fundo de escala"));
+    }
+    
+    #[test]
+    fn adaptive_interval_backs_off_and_resets_on_change() {
+        let mut tx = AdaptiveTxInterval::new(5_000, 40_000);
+        assert!(tx.is_enabled());
+        
+        // Estável: cada envio dobra o intervalo até o teto
+        let sent: Vec<u32> = (0..=24).map(|i| i * 5_000).filter(|&now| tx.should_transmit(now, false)).collect();
+        assert_eq!(sent, [0, 5_000, 15_000, 35_000, 75_000, 115_000]);
+        assert_eq!(tx.interval_ms(), 40_000);
+        
+        // Mudança: volta ao mínimo e sai assim que o mínimo tiver passado
+        assert!(!tx.should_transmit(117_000, true));
+        assert_eq!(tx.interval_ms(), 5_000);
+        assert!(tx.should_transmit(120_000, false));
+        assert_eq!(tx.interval_ms(), 5_000);
+        // E recomeça a dobrar a partir dele
+        assert!(tx.should_transmit(125_000, false));
+        assert!(!tx.should_transmit(130_000, false));
+        assert!(tx.should_transmit(135_000, false));
+        assert_eq!(tx.interval_ms(), 20_000);
+        
+        // Teto igual ao mínimo desliga o backoff
+        assert!(!AdaptiveTxInterval::new(5_000, 5_000).is_enabled());
+        
+        // No sistema: o intervalo cresce com o ambiente estável (dentro da banda morta)
+        let band = AsymmetricDeadband { up: 0.5, down: 0.5 };
+        let mut rig = rig(SystemConfig { transmit_backoff_max_ms: 40_000, deadbands: [band; 4], ..quiet_config() });
+        rig.transport.take_output();
+        for _ in 0..20 {
+            rig.next_reading().unwrap();
+        }
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [5_000, 10_000, 20_000, 40_000, 80_000]);
+        assert_eq!(rig.system.transmit_interval_ms(), 40_000);
+        
+        rig.source.set([51, 512, 754, 154]);
+        rig.next_reading().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [105_000]);
+        assert_eq!(rig.system.transmit_interval_ms(), 5_000);
+    }
+    
+    #[test]
+    fn storage_invariants_catch_deliberate_corruption() {
+        assert_eq!(DataStorage::new().check_invariants(), Ok(()));
+        assert_eq!(storage_with((1..=10).map(|i| i * 1000)).check_invariants(), Ok(()));
+        // Cheio, após várias voltas, e atravessando a virada do millis
+        assert_eq!(storage_with((0..120).map(|i| i * 1000)).check_invariants(), Ok(()));
+        assert_eq!(storage_with((0..60).map(|i| (u32::MAX - 20_000).wrapping_add(i * 1000))).check_invariants(), Ok(()));
+        
+        let mut storage = storage_with((1..=10).map(|i| i * 1000));
+        storage.write_index = 50;
+        assert_eq!(storage.check_invariants(), Err(StorageError::WriteIndexOutOfBounds(50)));
+        
+        let mut storage = storage_with((1..=10).map(|i| i * 1000));
+        storage.data_buffer[30].timestamp = 99_000;
+        assert_eq!(storage.check_invariants(), Err(StorageError::UnwrittenSlotInUse(30)));
+        
+        // is_full incoerente com o número de gravações, nos dois sentidos
+        let mut storage = storage_with((1..=10).map(|i| i * 1000));
+        storage.is_full = true;
+        assert_eq!(storage.check_invariants(), Err(StorageError::FullFlagMismatch));
+        let mut storage = storage_with((1..=60).map(|i| i * 1000));
+        storage.is_full = false;
+        assert_eq!(storage.check_invariants(), Err(StorageError::FullFlagMismatch));
+        
+        let mut storage = storage_with((1..=10).map(|i| i * 1000));
+        storage.data_buffer[4].timestamp = 2_500;
+        assert_eq!(storage.check_invariants(), Err(StorageError::NonMonotonicTimestamp(4)));
+        
+        let mut storage = storage_with((1..=10).map(|i| i * 1000));
+        storage.data_buffer[2].temperature += 5.0;
+        assert_eq!(storage.check_invariants(), Err(StorageError::RunningSumsOutOfSync));
+    }
+    
+    #[test]
+    fn spline_is_exact_at_nodes_and_smooth_between() {
+        let points: [(f32, f32); 8] = core::array::from_fn(|i| (i as f32, (i * i) as f32));
+        let spline = CubicSpline::new(points).unwrap();
+        let table = LookupTable::new(points).unwrap();
+        
+        for (x, y) in points {
+            assert!((spline.evaluate(x) - y).abs() < 1e-4, "nó {}", x);
+        }
+        
+        // Derivada contínua nos nós internos; a tabela linear quebra ali
+        let slope = |f: &dyn Fn(f32) -> f32, from: f32, to: f32| (f(to) - f(from)) / (to - from);
+        let step = 0.01;
+        for (x, _) in &points[1..7] {
+            let x = *x;
+            let curve = |v| spline.evaluate(v);
+            let left = slope(&curve, x - step, x);
+            let right = slope(&curve, x, x + step);
+            assert!((left - right).abs() < 0.1, "nó {}: {} vs {}", x, left, right);
+            
+            let line = |v| table.lookup(v);
+            assert!((slope(&line, x - step, x) - slope(&line, x, x + step)).abs() > 1.5);
+        }
+        
+        // Entre os nós fica mais perto da curva real do que a interpolação linear
+        let (x, exact) = (3.5, 12.25);
+        assert!((spline.evaluate(x) - exact).abs() < (table.lookup(x) - exact).abs());
+        
+        // Pontos colineares: a spline natural é a própria reta
+        let line = CubicSpline::new([(0.0, 1.0), (1.0, 3.0), (2.5, 6.0), (4.0, 9.0)]).unwrap();
+        assert!((line.evaluate(1.75) - 4.5).abs() < 1e-4);
+        
+        // Satura nos extremos e rejeita x fora de ordem ou poucos pontos
+        assert_eq!(spline.evaluate(-1.0), 0.0);
+        assert_eq!(spline.evaluate(10.0), 49.0);
+        assert!(CubicSpline::new([(0.0, 0.0), (0.0, 1.0), (1.0, 2.0)]).is_none());
+        assert!(CubicSpline::new([(0.0, 0.0)]).is_none());
+    }
+    
+    #[test]
+    fn text_lines_match_the_wire_format() {
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        let communication = &mut rig.system.communication;
+        
+        communication.send_data(&sample(24.5, 55.0, 420.0, 101.3, 123_456)).unwrap();
+        assert_eq!(rig.transport.take_output(), "T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*75E1\n");
+        
+        let high = Alert::new(AlertKind::Temperature, AlertCode::TemperatureHigh, AlertLevel::Critical, "Temperatura alta", 36.25, 9000);
+        communication.send_alert(&high).unwrap();
+        assert_eq!(rig.transport.take_output(), "ALERT[CRITICAL]: Temperatura alta - Value: 36.2 at 9000\n");
+        
+        communication.send_alert(&alert(AlertKind::Humidity, AlertCode::HumidityLow, AlertLevel::Info)).unwrap();
+        assert_eq!(rig.transport.take_output(), "ALERT[INFO]: teste - Value: 1.0 at 1000\n");
+        
+        communication.set_alert_format(AlertFormat::Code);
+        communication.send_alert(&high).unwrap();
+        assert_eq!(rig.transport.take_output(), "AC:2,2,36.2,9000\n");
+    }
+    
+    #[test]
+    fn alert_storm_switches_to_summary_mode() {
+        let mut detector = AlertStormDetector::new(2, 2);
+        let mut cycle = |alerts: u16| {
+            (0..alerts).for_each(|_| detector.note_alert());
+            detector.end_cycle()
+        };
+        // Entra só após dois ciclos seguidos acima do limite
+        assert_eq!(cycle(3), None);
+        assert_eq!(cycle(1), None);
+        assert_eq!(cycle(3), None);
+        assert_eq!(cycle(4), Some(4));
+        // No modo resumo até dois ciclos calmos seguidos
+        assert_eq!(cycle(0), Some(0));
+        assert_eq!(cycle(5), Some(5));
+        assert_eq!(cycle(2), Some(2));
+        assert_eq!(cycle(1), None);
+        
+        let config = SystemConfig { alert_storm_threshold: 2, alert_storm_cycles: 2, ..quiet_config() };
+        let mut rig = rig(config);
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        
+        // Várias métricas fora do normal ao mesmo tempo
+        rig.source.set([80, 900, 754, 154]);
+        rig.next_reading().unwrap();
+        assert!(!rig.system.is_alert_storm());
+        assert!(rig.transport.take_output().contains("ALERT[CRITICAL]"));
+        
+        // Segundo ciclo seguido: entra no modo resumo ao fechar o ciclo
+        rig.next_reading().unwrap();
+        assert!(rig.system.is_alert_storm());
+        assert!(rig.transport.take_output().ends_with("ALERT_STORM:5,15000\n"));
+        
+        // Alertas individuais deixam de sair; só a contagem agregada
+        rig.next_reading().unwrap();
+        let output = rig.transport.take_output();
+        assert!(!output.contains("ALERT["), "{}", output);
+        assert!(output.ends_with("ALERT_STORM:5,20000\n"));
+        
+        rig.source.set(NORMAL_RAW);
+        rig.next_reading().unwrap();
+        assert!(rig.transport.take_output().ends_with("ALERT_STORM:0,25000\n"));
+        rig.next_reading().unwrap();
+        assert!(!rig.system.is_alert_storm());
+        assert!(!rig.transport.take_output().contains("ALERT_STORM"));
+    }
+    
+    #[test]
+    fn crc16_check_value_and_frame_verification() {
+        // Valor de verificação do CRC-16/CCITT-FALSE
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(b""), 0xFFFF);
+        
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        let data = sample(24.5, 55.0, 420.0, 101.3, 123_456);
+        rig.system.communication.send_data(&data).unwrap();
+        let line = rig.transport.take_output();
+        assert!(verify_frame(line.as_bytes()));
+        assert!(verify_frame(line.trim_end().as_bytes()));
+        assert!(verify_frame(b"T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*75E1\r\n"));
+        
+        // O prefixo de ID fica fora do CRC
+        rig.system.communication.set_device_id(7).unwrap();
+        rig.system.communication.send_data(&data).unwrap();
+        let line = rig.transport.take_output();
+        assert!(line.starts_with("@7:"));
+        assert!(verify_frame(line.as_bytes()));
+        
+        // Um byte trocado, CRC errado ou malformado
+        assert!(!verify_frame(b"T:24.6C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*75E1\n"));
+        assert!(!verify_frame(b"T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*75E2\n"));
+        assert!(!verify_frame(b"T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*75E\n"));
+        assert!(!verify_frame(b"T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456*ZZZZ\n"));
+        assert!(!verify_frame(b"T:24.5C,H:55.0%,AQ:420.0ppm,P:101.3kPa,T:123456\n"));
+        assert!(!verify_frame(b"@7"));
+    }
+    
+    #[test]
+    fn page_bounds_follow_the_write_sequence() {
+        let storage = storage_with((1..=25).map(|i| i * 1000));
+        assert_eq!(storage.page_count(10), 3);
+        assert_eq!(storage.page_bounds(0, 10), Some(0..10));
+        assert_eq!(storage.page_bounds(1, 10), Some(10..20));
+        // Última página parcial e páginas que ainda não começaram
+        assert_eq!(storage.page_bounds(2, 10), Some(20..25));
+        assert_eq!(storage.page_bounds(3, 10), None);
+        assert_eq!(storage.page_bounds(u32::MAX, 10), None);
+        assert_eq!(storage.page_bounds(0, 0), None);
+        assert_eq!(storage.page_count(0), 0);
+        
+        // Buffer girando: 70 gravações, as 20 primeiras já sobrescritas
+        let storage = storage_with((1..=70).map(|i| i * 1000));
+        assert_eq!(storage.page_count(10), 7);
+        assert_eq!(storage.page_bounds(0, 10), None);
+        assert_eq!(storage.page_bounds(1, 10), None);
+        assert_eq!(storage.page_bounds(2, 10), Some(0..10));
+        assert_eq!(storage.page_bounds(6, 10), Some(40..50));
+        assert_eq!(storage.page_bounds(7, 10), None);
+        // Página parcialmente sobrescrita traz só o que restou
+        assert_eq!(storage.page_bounds(1, 15), Some(0..10));
+        let first = storage.iter().next().unwrap();
+        assert_eq!(first.timestamp, 21_000);
+        
+        let mut rig = rig(quiet_config());
+        for _ in 0..3 {
+            rig.next_reading().unwrap();
+        }
+        rig.transport.take_output();
+        rig.system.dump_page(1, 2).unwrap();
+        let output = rig.transport.take_output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "DUMP:1,2");
+        assert!(lines[1].contains(",T:15000*"));
+        assert_eq!(lines[2], "DUMP_END:1");
+        
+        assert_eq!(rig.system.dump_page(2, 2), Err("página fora do histórico"));
+        assert_eq!(rig.transport.take_output(), "");
+    }
+    
+    #[test]
+    fn binary_packet_round_trip_and_rejection() {
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        
+        let data = sample(-12.5, 87.25, 1520.0, 99.75, 0x0A0D_0A0D);
+        let packet = data.to_binary();
+        assert_eq!(packet.len(), BINARY_PACKET_SIZE);
+        assert_eq!(packet[0], BINARY_START_BYTE);
+        assert_eq!(&packet[1..21], &data.to_bytes()[..]);
+        
+        let decoded = EnvironmentalData::from_binary(&packet).unwrap();
+        assert_eq!(decoded.to_bytes(), data.to_bytes());
+        
+        let mut bad_start = packet;
+        bad_start[0] = 0x55;
+        assert!(matches!(EnvironmentalData::from_binary(&bad_start), Err(SensorError::CommunicationError)));
+        
+        let mut bad_crc = packet;
+        bad_crc[21] ^= 0x01;
+        assert!(matches!(EnvironmentalData::from_binary(&bad_crc), Err(SensorError::CommunicationError)));
+        
+        // Payload corrompido em trânsito também falha no CRC
+        let mut corrupted = packet;
+        corrupted[5] ^= 0x80;
+        assert!(EnvironmentalData::from_binary(&corrupted).is_err());
+        
+        // Pela serial: bytes crus, sem prefixo nem fim de linha
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.system.communication.set_output_format(OutputFormat::Binary);
+        rig.system.communication.send_data(&data).unwrap();
+        assert_eq!(rig.transport.written.borrow().as_slice(), &packet[..]);
+    }
+    
+    // RTC que pode falhar (I2C fora do ar); None = leitura com erro
+    #[derive(Clone, Default)]
+    struct MockRtc {
+        seconds: Rc<Cell<Option<u32>>>,
+        reads: Rc<Cell<u32>>,
+    }
+    
+    impl RealTimeClock for MockRtc {
+        fn unix_seconds(&self) -> Result<u32, SensorError> {
+            self.reads.set(self.reads.get() + 1);
+            self.seconds.get().ok_or(SensorError::CommunicationError)
+        }
+    }
+    
+    #[test]
+    fn failing_rtc_falls_back_to_millis_and_sets_degraded() {
+        let rtc = MockRtc::default();
+        let millis = MockClock::default();
+        let clock = FallbackClock::new(rtc.clone(), millis.clone());
+        
+        rtc.seconds.set(Some(1_000));
+        assert_eq!(clock.unix_seconds(), Some(1_000));
+        assert!(!clock.is_degraded());
+        millis.advance(500);
+        assert_eq!(clock.now_ms(), 500);
+        
+        // RTC fora: a hora real some, a contagem do millis continua
+        rtc.seconds.set(None);
+        millis.advance(2_000);
+        assert_eq!(clock.unix_seconds(), None);
+        assert!(clock.is_degraded());
+        assert_eq!(clock.now_ms(), 2_500);
+        
+        // De volta: a flag é limpa na próxima leitura do RTC
+        rtc.seconds.set(Some(1_003));
+        millis.advance(100);
+        assert_eq!(clock.unix_seconds(), Some(1_003));
+        assert!(!clock.is_degraded());
+        
+        // No sistema: leituras continuam, com a degradação exposta
+        let config = quiet_config();
+        let source = MockSensorSource::with_clock(NORMAL_RAW, millis.clone());
+        let transport = MockTransport::default();
+        let communication = CommunicationSystem::with_parts(
+            transport.clone(),
+            MockIndicators::default(),
+            MockEeprom::default(),
+            ResetCause::PowerOn,
+        );
+        let mut system = EnvironmentalMonitoringSystem::from_parts(
+            SensorManager::with_source(source, config.clone()),
+            communication,
+            clock,
+            config,
+        )
+        .unwrap();
+        
+        rtc.seconds.set(None);
+        millis.advance(5_000);
+        system.run_monitoring_cycle().unwrap();
+        assert!(system.is_clock_degraded());
+        assert_eq!(system.data_storage.get_latest_data().unwrap().timestamp, 7_600);
+        
+        rtc.seconds.set(Some(1_013));
+        millis.advance(5_000);
+        system.run_monitoring_cycle().unwrap();
+        assert!(!system.is_clock_degraded());
+        assert_eq!(system.data_storage.get_latest_data().unwrap().timestamp, 12_600);
+    }
+    
+    #[test]
+    fn fallback_clock_never_steps_back_when_millis_runs_ahead_of_rtc() {
+        let rtc = MockRtc::default();
+        let millis = MockClock::default();
+        let clock = FallbackClock::new(rtc.clone(), millis.clone());
+        
+        // Millis adianta 400 ms por segundo do RTC: o RTC só vira para 1_001
+        // depois de o millis já ter passado 1,4 s
+        rtc.seconds.set(Some(1_000));
+        let mut last = clock.now_ms();
+        for (elapsed, seconds) in [(700, 1_000), (700, 1_000), (100, 1_001), (700, 1_001), (800, 1_002)] {
+            millis.advance(elapsed);
+            rtc.seconds.set(Some(seconds));
+            assert_eq!(clock.unix_seconds(), Some(seconds));
+            let now = clock.now_ms();
+            assert!(now >= last, "{} -> {}", last, now);
+            last = now;
+        }
+        assert_eq!(last, 3_000);
+        
+        // Intervalos e sono do agendador não tocam no barramento do RTC
+        let reads = rtc.reads.get();
+        for _ in 0..10 {
+            clock.now_ms();
+        }
+        assert_eq!(rtc.reads.get(), reads);
+    }
+    
+    #[test]
+    fn memory_footprint_matches_size_of() {
+        use core::mem::size_of;
+        let expected = [
+            size_of::<EnvironmentalData>(),
+            size_of::<SystemConfig>(),
+            size_of::<Alert>(),
+            size_of::<DataStorage>(),
+            size_of::<AlertSystem>(),
+            size_of::<DailyProfile>(),
+            size_of::<SensorManager>(),
+            size_of::<CommunicationSystem>(),
+            size_of::<EnvironmentalMonitoringSystem>(),
+        ];
+        let footprint = memory_footprint();
+        for ((name, size), expected) in footprint.iter().zip(expected) {
+            assert_eq!(*size, expected, "{}", name);
+        }
+        assert_eq!(footprint[0], ("EnvironmentalData", 20));
+        // O sistema contém o armazenamento: nunca menor que ele
+        assert!(footprint[8].1 > footprint[3].1);
+        
+        // Comando MEM: uma linha por estrutura, na mesma ordem
+        let mut rig = rig(quiet_config());
+        rig.transport.take_output();
+        rig.transport.receive("MEM\n");
+        rig.next_reading().unwrap();
+        let output = rig.transport.take_output();
+        let lines: Vec<&str> = output.lines().filter(|line| line.starts_with("MEM:")).collect();
+        assert_eq!(lines.len(), footprint.len(), "{}", output);
+        for (line, (name, size)) in lines.iter().zip(footprint.iter()) {
+            assert_eq!(*line, format!("MEM:{},{}", name, size));
+        }
+    }
+    
+    #[test]
+    fn reading_interval_survives_millis_wrap() {
+        let mut rig = rig(quiet_config());
+        rig.clock.set(u32::MAX - 999 - 5_000);
+        rig.next_reading().unwrap();
+        rig.transport.take_output();
+        assert_eq!(rig.system.data_storage.get_latest_data().unwrap().timestamp, u32::MAX - 999);
+        
+        // millis() virou: 4999 ms decorridos ainda não bastam
+        rig.clock.advance(4_999);
+        assert_eq!(rig.clock.now_ms(), 3_999);
+        assert_eq!(rig.system.time_until_next_reading(rig.clock.now_ms()), 1);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(rig.transport.output(), "");
+        
+        rig.clock.advance(1);
+        rig.system.run_monitoring_cycle().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [4_000]);
+        
+        // E segue no ritmo normal depois da virada
+        rig.next_reading().unwrap();
+        assert_eq!(sent_timestamps(&rig.transport.take_output()), [9_000]);
+        assert_eq!(rig.system.data_storage.len(), 3);
+        assert_eq!(rig.system.data_storage.check_invariants(), Ok(()));
+    }
+    
+    #[test]
+    fn stored_median_ignores_outliers() {
+        let mut storage = DataStorage::new();
+        assert!(storage.get_median_data(5).is_none());
+        
+        let readings = [
+            (24.8, 50.0, 410.0, 101.0),
+            (25.0, 51.0, 2500.0, 101.1),
+            (24.9, 49.0, 400.0, 101.2),
+            (-40.0, 50.5, 405.0, 80.0),
+            (25.1, 99.0, 415.0, 101.3),
+        ];
+        for (index, (t, h, aq, p)) in readings.into_iter().enumerate() {
+            storage.store_data(sample(t, h, aq, p, (index as u32 + 1) * 1000));
+        }
+        
+        let median = storage.get_median_data(5).unwrap();
+        assert_eq!(median.temperature, 24.9);
+        assert_eq!(median.humidity, 50.5);
+        assert_eq!(median.air_quality, 410.0);
+        assert_eq!(median.pressure, 101.1);
+        assert_eq!(median.timestamp, 3000);
+        // A média das mesmas amostras é arrastada pelos picos
+        assert!(storage.get_average_data(5).unwrap().air_quality > 700.0);
+        
+        // Janela par: média dos dois centrais; pedido maior que o histórico é limitado
+        let last_four = storage.get_median_data(4).unwrap();
+        assert_eq!(last_four.air_quality, (405.0 + 415.0) / 2.0);
+        assert_eq!(last_four.timestamp, 4000);
+        assert_eq!(storage.get_median_data(50).unwrap().temperature, 24.9);
+        
+        // Atravessando a volta do buffer
+        for timestamp in 6..=52 {
+            storage.store_data(sample(20.0, 50.0, 400.0, 101.0, timestamp * 1000));
+        }
+        storage.store_data(sample(90.0, 50.0, 400.0, 101.0, 53_000));
+        let wrapped = storage.get_median_data(5).unwrap();
+        assert_eq!(wrapped.temperature, 20.0);
+        assert_eq!(wrapped.timestamp, 51_000);
     }
 }